@@ -10,6 +10,10 @@ pub trait ArtifactStore: Send + Sync {
     fn write_manifest(&self, run_dir: &Path, manifest: &EvidenceManifest) -> Result<()>;
     fn append_worklog(&self, run_dir: &Path, line: &str) -> Result<()>;
     fn write_role_bytes(&self, run_dir: &Path, role: EvidenceRole, name: &str, bytes: &[u8]) -> Result<PathBuf>;
+
+    /// Durable reference to an artifact written by this store, for handing it to a later run.
+    fn artifact_ref(&self, path: &Path) -> Result<String>;
+    fn read_ref(&self, artifact_ref: &str) -> Result<Vec<u8>>;
 }
 
 #[derive(Clone)]
@@ -67,6 +71,19 @@ impl ArtifactStore for FsArtifactStore {
         std::fs::write(&path, bytes)?;
         Ok(path)
     }
+
+    /// Refs are paths relative to the store root, so they survive moving the root.
+    fn artifact_ref(&self, path: &Path) -> Result<String> {
+        let rel = path
+            .strip_prefix(&self.root)
+            .with_context(|| format!("{} is outside artifact root {}", path.display(), self.root.display()))?;
+        Ok(rel.to_string_lossy().into_owned())
+    }
+
+    fn read_ref(&self, artifact_ref: &str) -> Result<Vec<u8>> {
+        let path = self.root.join(artifact_ref);
+        std::fs::read(&path).with_context(|| format!("read artifact {}", path.display()))
+    }
 }
 
 #[cfg(test)]
@@ -94,4 +111,17 @@ mod tests {
         assert!(run_dir.join("worklog.md").exists());
         assert!(run_dir.join("evidence_manifest.json").exists());
     }
+
+    #[test]
+    fn artifact_ref_round_trips() {
+        let dir = tempdir().unwrap();
+        let store = FsArtifactStore::new(dir.path().to_path_buf());
+        let run_dir = store.create_run_dir("p", &RunId::from_str("r")).unwrap();
+        let path = store.write_role_bytes(&run_dir, EvidenceRole::Diff, "diff.patch", b"patch").unwrap();
+
+        let r = store.artifact_ref(&path).unwrap();
+        assert_eq!(r, "p/r/diff/diff.patch");
+        assert_eq!(store.read_ref(&r).unwrap(), b"patch");
+        assert!(store.artifact_ref(Path::new("/elsewhere/x")).is_err());
+    }
 }
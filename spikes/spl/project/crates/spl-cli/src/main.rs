@@ -1,8 +1,10 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use clap::{Parser, Subcommand};
 use tracing_subscriber::EnvFilter;
 
 use spl_runner::Runner;
-use spl_storage::Storage;
 
 #[derive(Parser)]
 #[command(name = "spl", version)]
@@ -22,6 +24,13 @@ enum Command {
     /// Show a basic status snapshot
     Status,
 
+    /// Apply pending schema migrations to the configured storage (any command that opens it also does)
+    DbMigrate {
+        /// Only list the pending migrations and their SQL; leave the db untouched
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Create a task (v0: minimal)
     TaskAdd {
         #[arg(long)]
@@ -30,6 +39,78 @@ enum Command {
         title: String,
         #[arg(long, default_value_t = true)]
         ready: bool,
+        /// Higher runs first
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        priority: i32,
+        /// Repeat for several tags
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+
+    /// List tasks, highest priority first
+    TaskList {
+        /// draft | ready | blocked_hitl | blocked_failure | done | cancelled
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long)]
+        tag: Option<String>,
+    },
+
+    /// Show a task with its revisions, queue items, runs, gates and messages
+    TaskShow { id: String },
+
+    /// Change a task's priority, including its work still waiting in the queue
+    TaskSetPriority {
+        id: String,
+        #[arg(allow_negative_numbers = true)]
+        priority: i32,
+    },
+
+    /// Add or remove task tags
+    TaskTag {
+        id: String,
+        #[arg(long)]
+        add: Vec<String>,
+        #[arg(long)]
+        remove: Vec<String>,
+    },
+
+    /// Cancel an unfinished task and drop its queued work
+    TaskCancel {
+        id: String,
+        /// Recorded with the cancellation
+        #[arg(long, default_value = "")]
+        note: String,
+    },
+
+    /// Move a blocked task back to ready, re-queueing its latest revision if nothing is queued
+    TaskReset {
+        id: String,
+        /// Recorded in the reset message
+        #[arg(long, default_value = "")]
+        note: String,
+    },
+
+    /// List tasks blocked on an ASK
+    AskList,
+
+    /// Print the ASK a task is waiting on
+    AskShow {
+        task: String,
+        /// Print the ASK packet as JSON instead of markdown
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Answer a task's ASK: records a DECISION and carries out the chosen option
+    AskAnswer {
+        task: String,
+        /// 1-based option number from the ASK
+        #[arg(long)]
+        option: usize,
+        /// Recorded with the decision
+        #[arg(long, default_value = "")]
+        note: String,
     },
 
     /// Compile a spec pack YAML into a spec revision + runnable revision row (v0: minimal)
@@ -38,8 +119,44 @@ enum Command {
         task: String,
         #[arg(long)]
         spec: String,
-        #[arg(long, default_value = "standard")]
-        profile: String,
+        /// Defaults to the pack's profile, else standard
+        #[arg(long)]
+        profile: Option<String>,
+    },
+
+    /// Check a spec pack YAML and report every problem with its line and column
+    SpecLint { file: String },
+
+    /// Print the JSON Schema of the spec pack format
+    SpecSchema,
+
+    /// Index the public items of the repo's Rust crates and TypeScript modules into .spl/index.json,
+    /// re-parsing only files whose content changed
+    IndexBuild,
+
+    /// Update .spl/index.json from the files the VCS reports changed between two revs; the
+    /// working tree must be at --to
+    IndexUpdate {
+        #[arg(long)]
+        from: String,
+        /// Defaults to mainline
+        #[arg(long)]
+        to: Option<String>,
+    },
+
+    /// List the profiles revisions can be compiled under (built-in and configured)
+    ProfileList,
+
+    /// Show a profile's gates, validations, smoke commands and spec requirements
+    ProfileShow { name: String },
+
+    /// Replace a task's spec: new spec revision chained to the current one, with a diff
+    SpecAmend {
+        task: String,
+        file: String,
+        /// Defaults to the pack's profile, else the superseded revision's
+        #[arg(long)]
+        profile: Option<String>,
     },
 
     /// Enqueue an execute-lane queue item for a task+revision (v0 helper)
@@ -55,6 +172,19 @@ enum Command {
         #[arg(long, default_value_t = true)]
         dry_run: bool,
     },
+
+    /// Poll the queue and run items until SIGINT/SIGTERM (in-flight runs finish first)
+    WorkerServe {
+        /// Items run in parallel, each in its own workspace
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+        /// Seconds an idle worker waits before polling again
+        #[arg(long, default_value_t = 5)]
+        poll_secs: u64,
+        /// Exit once no item is visible and none is running
+        #[arg(long)]
+        exit_when_idle: bool,
+    },
 }
 
 fn main() -> anyhow::Result<()> {
@@ -83,62 +213,283 @@ fn main() -> anyhow::Result<()> {
             println!("Visible queue items: {}", snap.queue.len());
             println!("Active leases: {}", snap.leases.len());
         }
-        Command::TaskAdd { id, title, ready } => {
+        Command::DbMigrate { dry_run } => {
+            let cfg = spl_runner::Config::load_for_repo(&repo_root)?;
+            let plan = spl_runner::plan_storage_migrations(&repo_root, &cfg)?;
+            println!("Storage: {}", cfg.storage.backend);
+            let legacy = if plan.legacy { " (unversioned; inferred from its tables)" } else { "" };
+            println!("Schema version: {}{}", plan.current, legacy);
+            if plan.pending.is_empty() {
+                println!("Up to date");
+            }
+            for m in &plan.pending {
+                println!("Pending: {:04}_{}", m.version, m.name);
+                if dry_run {
+                    println!("{}", m.sql.trim_end());
+                }
+            }
+            if let Some(last) = plan.pending.last().filter(|_| !dry_run) {
+                spl_runner::open_storage(&repo_root, &cfg)?;
+                println!("Migrated to version {}", last.version);
+            }
+        }
+        Command::TaskAdd { id, title, ready, priority, tag } => {
             let r = Runner::open(repo_root)?;
             let status = if ready { spl_core::TaskStatus::Ready } else { spl_core::TaskStatus::Draft };
             r.storage.insert_task(spl_core::Task {
                 id: spl_core::TaskId::from_str(id.clone()),
                 title,
                 status,
-                priority: 0,
-                tags: vec![],
+                priority,
+                tags: tag,
             })?;
             println!("Added task {}", id);
         }
+        Command::TaskList { status, tag } => {
+            let r = Runner::open(repo_root)?;
+            let status = status.as_deref().map(parse_task_status).transpose()?;
+            for t in r.list_tasks(status.as_ref(), tag.as_deref())? {
+                let tags = if t.tags.is_empty() { String::new() } else { format!(" #{}", t.tags.join(" #")) };
+                println!("- {} [{:?}] p{} {}{}", t.id.as_str(), t.status, t.priority, t.title, tags);
+            }
+        }
+        Command::TaskShow { id } => {
+            let r = Runner::open(repo_root)?;
+            print_task_detail(&r.task_detail(&spl_core::TaskId::from_str(id))?);
+        }
+        Command::TaskSetPriority { id, priority } => {
+            let r = Runner::open(repo_root)?;
+            r.set_task_priority(&spl_core::TaskId::from_str(id.clone()), priority)?;
+            println!("Task {} priority {}", id, priority);
+        }
+        Command::TaskTag { id, add, remove } => {
+            let r = Runner::open(repo_root)?;
+            let tags = r.tag_task(&spl_core::TaskId::from_str(id.clone()), &add, &remove)?;
+            println!("Task {} tags: {}", id, tags.join(", "));
+        }
+        Command::TaskCancel { id, note } => {
+            let r = Runner::open(repo_root)?;
+            r.cancel_task(&spl_core::TaskId::from_str(id.clone()), &note)?;
+            println!("Cancelled task {}", id);
+        }
+        Command::TaskReset { id, note } => {
+            let r = Runner::open(repo_root)?;
+            match r.reset_task(&spl_core::TaskId::from_str(id.clone()), &note)? {
+                Some(queue_id) => println!("Reset task {} to ready; re-queued execute ({})", id, queue_id),
+                None => println!("Reset task {} to ready", id),
+            }
+        }
+        Command::AskList => {
+            let r = Runner::open(repo_root)?;
+            for (t, ask) in r.open_asks()? {
+                let decision = spl_hitl::parse_ask(&ask.body_md).map(|a| a.decision_needed).unwrap_or_default();
+                println!("- {} p{} {}: {}", t.id.as_str(), t.priority, t.title, decision.lines().next().unwrap_or(""));
+            }
+        }
+        Command::AskShow { task, json } => {
+            let r = Runner::open(repo_root)?;
+            let task_id = spl_core::TaskId::from_str(task);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&r.open_ask_packet(&task_id)?)?);
+            } else {
+                print!("{}", r.open_ask(&task_id)?.body_md);
+            }
+        }
+        Command::AskAnswer { task, option, note } => {
+            let r = Runner::open(repo_root)?;
+            let next = r.answer_ask(&spl_core::TaskId::from_str(task.clone()), option, &note)?;
+            println!("Answered {} with option {}; SPL will do next: {}", task, option, next);
+        }
         Command::SpecCompile { task, spec, profile } => {
             let r = Runner::open(repo_root)?;
-            let pack = spl_spec::load_spec_pack(std::path::Path::new(&spec))?;
-            let mut pack = pack;
-            pack.profile = Some(profile);
-            let draft = spl_spec::compile_revision_draft(&pack);
-
-            // copy spec into repo-local area for durability
-            let specs_dir = r.repo_root.join(".spl").join("specs").join(&task);
-            std::fs::create_dir_all(&specs_dir)?;
-            let dest = specs_dir.join("spec_pack.yaml");
-            std::fs::copy(&spec, &dest)?;
-
-            // Insert spec_rev + revision rows (v0 minimal; ids derived)
-            let now = spl_runner::now_unix();
-            let spec_rev_id = spl_core::SpecRevId::new().0;
-            let revision_id = spl_core::RevisionId::new().0;
-
-            r.storage.insert_spec_revision(&spec_rev_id, &task, &draft.spec_hash, dest.to_str().unwrap(), now)?;
-            r.storage.insert_revision_row(
-                &revision_id,
-                &task,
-                &spec_rev_id,
-                &draft.spec_hash,
-                &draft.profile,
-                &serde_json::to_string(&draft.required_gates)?,
-                "[]",
-                &serde_json::to_string(&draft.anchors.iter().map(|a| a.as_str()).collect::<Vec<_>>())?,
-                now,
-            )?;
-
-            println!("Compiled spec for {} -> revision {}", task, revision_id);
+            let compiled = r.compile_spec(&spl_core::TaskId::from_str(task.clone()), std::path::Path::new(&spec), profile.as_deref())?;
+            println!("Compiled spec for {} -> revision {}", task, compiled.revision.id.as_str());
+        }
+        Command::SpecLint { file } => {
+            let yaml = std::fs::read_to_string(&file).map_err(|e| anyhow::anyhow!("read spec pack {}: {}", file, e))?;
+            let profiles = spl_runner::Config::profiles_for_repo(&repo_root)?;
+            match spl_spec::parse_spec_pack(&yaml, &profiles) {
+                Ok(_) => println!("{}: ok", file),
+                Err(diagnostics) => {
+                    for d in &diagnostics {
+                        eprintln!("{}", d.render(&file));
+                    }
+                    anyhow::bail!("{} problem(s) in {}", diagnostics.len(), file);
+                }
+            }
+        }
+        Command::IndexBuild => update_index(&repo_root, None)?,
+        Command::IndexUpdate { from, to } => {
+            let r = Runner::open(repo_root.clone())?;
+            let to = match to {
+                Some(to) => to,
+                None => r.vcs.get_base_rev(&repo_root)?,
+            };
+            let changed = r.vcs.changed_files(&repo_root, &from, &to)?;
+            update_index(&repo_root, Some(&changed))?;
+        }
+        Command::ProfileList => {
+            let profiles = spl_runner::Config::profiles_for_repo(&repo_root)?;
+            for (name, profile) in profiles.iter() {
+                println!("- {} gates=[{}]", name, profile.required_gates.join(", "));
+            }
+        }
+        Command::ProfileShow { name } => {
+            let profiles = spl_runner::Config::profiles_for_repo(&repo_root)?;
+            let p = profiles.get(&name)?;
+            let smoke = |own: &Option<Vec<String>>| own.as_ref().map(|c| format!("[{}]", c.join("; "))).unwrap_or("[commands] default".into());
+            println!("Profile {}", name);
+            println!("Required gates: {}", p.required_gates.join(", "));
+            println!("Required validations: {}", if p.required_validations.is_empty() { "-".into() } else { p.required_validations.join("; ") });
+            println!("pre_smoke: {}", smoke(&p.pre_smoke));
+            println!("post_smoke: {}", smoke(&p.post_smoke));
+            println!("Spec pack: use cases {}, anchors {}", required(p.require_use_cases), required(p.require_anchors));
+        }
+        Command::SpecSchema => {
+            println!("{}", serde_json::to_string_pretty(&spl_spec::spec_pack_schema())?);
+        }
+        Command::SpecAmend { task, file, profile } => {
+            let r = Runner::open(repo_root)?;
+            let amended = r.amend_spec(&spl_core::TaskId::from_str(task.clone()), std::path::Path::new(&file), profile.as_deref())?;
+            let parent = amended.compiled.spec_rev.parent_id.as_ref().map(|p| p.as_str()).unwrap_or("-");
+            println!(
+                "Amended spec for {}: {} -> {} (revision {})",
+                task,
+                parent,
+                amended.compiled.spec_rev.id.as_str(),
+                amended.compiled.revision.id.as_str()
+            );
+            print!("{}", amended.diff.to_markdown());
+            for id in &amended.cancelled {
+                println!("Dropped superseded queue item {}", id.as_str());
+            }
+            println!("Next: spl queue-enqueue --task {} --revision {}", task, amended.compiled.revision.id.as_str());
         }
         Command::QueueEnqueue { task, revision } => {
             let r = Runner::open(repo_root)?;
-            r.enqueue_execute(&task, &revision)?;
-            println!("Enqueued execute for {} {}", task, revision);
+            match r.enqueue_execute(&task, &revision)? {
+                spl_storage::EnqueueResult::Inserted(id) => println!("Enqueued execute for {} {} ({})", task, revision, id.as_str()),
+                spl_storage::EnqueueResult::Existing(id) => {
+                    println!("Execute for {} {} already queued ({})", task, revision, id.as_str())
+                }
+            }
         }
         Command::WorkerRun { dry_run } => {
             let r = Runner::open(repo_root)?;
             r.run_once(dry_run)?;
             println!("worker run complete");
         }
+        Command::WorkerServe { concurrency, poll_secs, exit_when_idle } => {
+            let r = Runner::open(repo_root)?;
+            let shutdown = Arc::new(AtomicBool::new(false));
+            let flag = shutdown.clone();
+            ctrlc::set_handler(move || {
+                if flag.swap(true, Ordering::SeqCst) {
+                    // second signal: give up on in-flight runs; their leases expire and get reclaimed
+                    std::process::exit(130);
+                }
+                eprintln!("shutting down: finishing in-flight runs (signal again to abort)");
+            })?;
+
+            let opts = spl_runner::ServeOptions {
+                concurrency,
+                poll_interval: std::time::Duration::from_secs(poll_secs),
+                exit_when_idle,
+            };
+            let report = r.serve(&opts, &shutdown)?;
+            println!("worker stopped: {} runs, {} errors", report.runs, report.errors);
+        }
     }
 
     Ok(())
 }
+
+/// Bring `.spl/index.json` up to date (from scratch if it is missing or in an older format)
+/// and report what was indexed.
+fn update_index(repo_root: &std::path::Path, changed: Option<&[String]>) -> anyhow::Result<()> {
+    let path = spl_index::default_index_path(repo_root);
+    let mut index = spl_index::SimpleIndex::load(&path)?;
+    let parsed = spl_index::update_index(&mut index, repo_root, &spl_index::default_indexers(), changed)?;
+    index.save(&path)?;
+    let mut counts = std::collections::BTreeMap::new();
+    for indexer in index.indexers.values() {
+        *counts.entry(indexer.as_str()).or_insert(0) += 1;
+    }
+    let counts: Vec<String> = counts.iter().map(|(indexer, n)| format!("{}: {}", indexer, n)).collect();
+    println!(
+        "Indexed {} anchors ({}) -> {}; parsed {} of {} files",
+        index.anchors.len(),
+        counts.join(", "),
+        path.display(),
+        parsed,
+        index.files.len()
+    );
+    Ok(())
+}
+
+/// `--status` value, spelled as storage stores it.
+fn parse_task_status(s: &str) -> anyhow::Result<spl_core::TaskStatus> {
+    let status = spl_storage::codec::str_to_task_status(s);
+    if spl_storage::codec::task_status_to_str(&status) != s {
+        anyhow::bail!("unknown task status {:?} (draft, ready, blocked_hitl, blocked_failure, done, cancelled)", s);
+    }
+    Ok(status)
+}
+
+/// How `profile-show` prints a spec requirement flag.
+fn required(yes: bool) -> &'static str {
+    if yes {
+        "required"
+    } else {
+        "optional"
+    }
+}
+
+fn print_task_detail(d: &spl_runner::TaskDetail) {
+    let t = &d.task;
+    println!("{} [{:?}] {}", t.id.as_str(), t.status, t.title);
+    println!("Priority: {}", t.priority);
+    println!("Tags: {}", t.tags.join(", "));
+
+    println!("Revisions: {}", d.revisions.len());
+    for rev in &d.revisions {
+        println!("- {} profile={} spec={}", rev.id.as_str(), rev.profile, rev.spec_hash);
+    }
+
+    println!("Queue items: {}", d.queue.len());
+    for q in &d.queue {
+        let state = match &q.finished {
+            None => format!("queued, visible at {}", q.item.visible_at_unix),
+            Some(spl_storage::QueueFinish::Completed { at_unix }) => format!("completed at {}", at_unix),
+            Some(spl_storage::QueueFinish::DeadLetter { reason, at_unix }) => format!("dead-lettered at {}: {}", at_unix, reason),
+            Some(spl_storage::QueueFinish::Cancelled { reason, at_unix }) => format!("cancelled at {}: {}", at_unix, reason),
+        };
+        println!(
+            "- {} {:?} rev={} attempts={}/{} p{}: {}",
+            q.item.id.as_str(),
+            q.item.lane,
+            q.item.revision_id.as_str(),
+            q.item.attempts,
+            q.item.max_attempts,
+            q.item.priority,
+            state
+        );
+    }
+
+    println!("Runs: {}", d.runs.len());
+    for (run, gates) in &d.runs {
+        let result = run.result.as_ref().map(|r| format!("{:?}", r)).unwrap_or_else(|| "running".to_string());
+        println!("- {} {:?} rev={} {}", run.id.as_str(), run.lane, run.revision_id.as_str(), result);
+        for g in gates {
+            println!("    {:?} {:?} ({}s)", g.outcome.gate, g.outcome.status, g.finished_at_unix - g.started_at_unix);
+        }
+    }
+
+    println!("Messages: {}", d.messages.len());
+    for m in &d.messages {
+        println!("- [{:?}] at {}", m.ty, m.created_at_unix);
+        for line in m.body_md.lines() {
+            println!("    {}", line);
+        }
+    }
+}
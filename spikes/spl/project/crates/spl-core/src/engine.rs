@@ -1,12 +1,11 @@
-use crate::{Command, GateName, Lane, Snapshot, TaskStatus};
+use crate::{Command, GateName, GateOutcome, GateStatus, Lane, Outcome, Revision, Run, RunId, RunResult, Snapshot, TaskId, TaskStatus};
 
 /// Very small v0 planner:
 /// - pick the highest priority visible queue item in Execute lane
 /// - ask shell to acquire a lease
 ///
-/// The imperative shell will translate this into storage calls and then call back
-/// with outcomes (future work). For now, `plan_next` is used by the runner as
-/// a convenience for dry-run printing.
+/// The imperative shell translates this into storage calls and then reports the
+/// result back through `step` as an `Outcome`.
 pub fn plan_next(snapshot: &Snapshot) -> Vec<Command> {
     // Derive visible items: visible_at <= now and no lease present.
     // KISS: assume snapshot.queue is already filtered by storage for v0.
@@ -26,9 +25,247 @@ pub fn task_is_enqueueable(status: &TaskStatus) -> bool {
     matches!(status, TaskStatus::Ready)
 }
 
+/// Pure helper: reset revives blocked tasks only.
+pub fn task_is_resettable(status: &TaskStatus) -> bool {
+    matches!(status, TaskStatus::BlockedHitl | TaskStatus::BlockedFailure)
+}
+
+/// Pure helper: anything not finished (done or cancelled) can be cancelled.
+pub fn task_is_cancellable(status: &TaskStatus) -> bool {
+    !matches!(status, TaskStatus::Done | TaskStatus::Cancelled)
+}
+
+/// Gates that only make sense against the landed mainline.
+fn is_land_lane_gate(gate: &GateName) -> bool {
+    matches!(gate, GateName::Land | GateName::PostSmoke)
+}
+
+/// Gates run by the execute lane, in the order the revision lists them; a gate listed
+/// twice runs once, so a run's current gate always names one position.
+pub fn execute_lane_gates(revision: &Revision) -> Vec<GateName> {
+    first_occurrences(revision.required_gates.iter().filter(|g| !is_land_lane_gate(g)))
+}
+
+/// Gates run by the land lane: `land` always runs first, followed by any
+/// required post-landing gates in revision order.
+pub fn land_lane_gates(revision: &Revision) -> Vec<GateName> {
+    first_occurrences(std::iter::once(&GateName::Land).chain(revision.required_gates.iter().filter(|g| **g == GateName::PostSmoke)))
+}
+
+fn first_occurrences<'a>(gates: impl Iterator<Item = &'a GateName>) -> Vec<GateName> {
+    let mut seen = vec![];
+    for gate in gates {
+        if !seen.contains(gate) {
+            seen.push(gate.clone());
+        }
+    }
+    seen
+}
+
+pub fn lane_gates(revision: &Revision, lane: &Lane) -> Vec<GateName> {
+    match lane {
+        Lane::Execute => execute_lane_gates(revision),
+        Lane::Land => land_lane_gates(revision),
+    }
+}
+
+/// Pure reducer: fold one shell-reported outcome into the snapshot and decide
+/// the commands that follow from it.
+///
+/// A run walks: `StartRun` → `EnsureWorkspace` → `BuildContextPack` → `RunGate`…
+/// → `EnqueueLandLane` (execute lane) / `MarkDone` (land lane) / `EmitAsk` /
+/// `MarkBlockedFailure` → `ReleaseLease`.
+pub fn step(snapshot: &Snapshot, outcome: &Outcome) -> (Snapshot, Vec<Command>) {
+    let mut next = snapshot.clone();
+    apply_outcome(&mut next, outcome);
+    let commands = commands_after(&next, outcome);
+    (next, commands)
+}
+
+/// State half of `step`: record what the shell already did.
+pub fn apply_outcome(snapshot: &mut Snapshot, outcome: &Outcome) {
+    match outcome {
+        Outcome::LeaseAcquired { .. } | Outcome::LeaseUnavailable { .. } => {}
+        Outcome::RunStarted { queue_id, run_id } => {
+            if let Some(qi) = snapshot.queue.iter().find(|q| q.id == *queue_id) {
+                let run = Run {
+                    id: run_id.clone(),
+                    queue_id: qi.id.clone(),
+                    task_id: qi.task_id.clone(),
+                    revision_id: qi.revision_id.clone(),
+                    lane: qi.lane.clone(),
+                    result: None,
+                    current_gate: None,
+                };
+                snapshot.runs.push(run);
+            }
+        }
+        Outcome::WorkspaceReady { .. } | Outcome::ContextPackBuilt { .. } => {}
+        Outcome::GateCompleted { run_id, outcome } => {
+            if let Some(run) = run_mut(snapshot, run_id) {
+                run.current_gate = Some(outcome.gate.clone());
+                if outcome.status == GateStatus::Fail {
+                    run.result = Some(failure_result(&run.lane, &outcome.gate));
+                }
+            }
+        }
+        Outcome::AskEmitted { run_id, task_id } => {
+            set_result(snapshot, run_id, RunResult::BlockedHitl);
+            set_task_status(snapshot, task_id, TaskStatus::BlockedHitl);
+        }
+        Outcome::LandEnqueued { run_id, .. } => {
+            set_result(snapshot, run_id, RunResult::Pass);
+        }
+        Outcome::DoneMarked { run_id, task_id } => {
+            set_result(snapshot, run_id, RunResult::Pass);
+            set_task_status(snapshot, task_id, TaskStatus::Done);
+        }
+        Outcome::BlockedFailureMarked { run_id, task_id } => {
+            if let Some(run) = run_mut(snapshot, run_id) {
+                run.result.get_or_insert(RunResult::FailGate);
+            }
+            set_task_status(snapshot, task_id, TaskStatus::BlockedFailure);
+        }
+        Outcome::CrashRecorded { run_id } => {
+            set_result(snapshot, run_id, RunResult::Crash);
+        }
+        Outcome::LeaseReleased { queue_id } => {
+            snapshot.leases.retain(|l| l.queue_id != *queue_id);
+        }
+    }
+}
+
+/// Command half of `step`, evaluated against the already-updated snapshot.
+fn commands_after(snapshot: &Snapshot, outcome: &Outcome) -> Vec<Command> {
+    match outcome {
+        Outcome::LeaseAcquired { queue_id } => vec![Command::StartRun { queue_id: queue_id.clone() }],
+        Outcome::LeaseUnavailable { .. } | Outcome::LeaseReleased { .. } => vec![],
+        Outcome::RunStarted { run_id, .. } => match find_run(snapshot, run_id) {
+            Some(run) if run.lane == Lane::Execute => vec![Command::EnsureWorkspace {
+                task_id: run.task_id.clone(),
+                vcs: snapshot.vcs.clone(),
+            }],
+            Some(run) => next_gate(snapshot, run, None),
+            None => vec![],
+        },
+        Outcome::WorkspaceReady { run_id } => match find_run(snapshot, run_id) {
+            Some(run) => vec![Command::BuildContextPack { revision_id: run.revision_id.clone() }],
+            None => vec![],
+        },
+        Outcome::ContextPackBuilt { run_id } => match find_run(snapshot, run_id) {
+            Some(run) => next_gate(snapshot, run, None),
+            None => vec![],
+        },
+        Outcome::GateCompleted { run_id, outcome } => match find_run(snapshot, run_id) {
+            Some(run) if outcome.status == GateStatus::Fail => gate_failed(run, outcome),
+            Some(run) => next_gate(snapshot, run, Some(&outcome.gate)),
+            None => vec![],
+        },
+        Outcome::AskEmitted { run_id, .. }
+        | Outcome::LandEnqueued { run_id, .. }
+        | Outcome::DoneMarked { run_id, .. }
+        | Outcome::BlockedFailureMarked { run_id, .. }
+        | Outcome::CrashRecorded { run_id } => match find_run(snapshot, run_id) {
+            Some(run) => vec![Command::ReleaseLease { queue_id: run.queue_id.clone() }],
+            None => vec![],
+        },
+    }
+}
+
+/// Next gate after `completed` (or the first gate when `None`); once the lane's
+/// gates are exhausted, hand off to landing (execute) or finish the task (land).
+/// A completed gate the lane does not run blocks the task rather than skipping ahead.
+fn next_gate(snapshot: &Snapshot, run: &Run, completed: Option<&GateName>) -> Vec<Command> {
+    let Some(revision) = snapshot.revisions.iter().find(|r| r.id == run.revision_id) else {
+        return vec![Command::MarkBlockedFailure {
+            task_id: run.task_id.clone(),
+            reason: format!("revision {} not found", run.revision_id.as_str()),
+        }];
+    };
+
+    let gates = lane_gates(revision, &run.lane);
+    let start = match completed {
+        Some(gate) => match gates.iter().position(|g| g == gate) {
+            Some(i) => i + 1,
+            None => {
+                return vec![Command::MarkBlockedFailure {
+                    task_id: run.task_id.clone(),
+                    reason: format!("gate {:?} is not a {:?} lane gate of revision {}", gate, run.lane, run.revision_id.as_str()),
+                }];
+            }
+        },
+        None => 0,
+    };
+
+    if let Some(gate) = gates.get(start) {
+        return vec![Command::RunGate { gate: gate.clone() }];
+    }
+
+    match run.lane {
+        Lane::Execute => vec![Command::EnqueueLandLane {
+            task_id: run.task_id.clone(),
+            revision_id: run.revision_id.clone(),
+        }],
+        Lane::Land => vec![Command::MarkDone { task_id: run.task_id.clone() }],
+    }
+}
+
+/// A failed landing is a HITL decision (conflict); anything else blocks the task.
+fn gate_failed(run: &Run, outcome: &GateOutcome) -> Vec<Command> {
+    let detail = outcome
+        .remediation
+        .clone()
+        .unwrap_or_else(|| format!("{:?} failed", outcome.gate));
+
+    if failure_result(&run.lane, &outcome.gate) == RunResult::BlockedHitl {
+        return vec![Command::EmitAsk {
+            task_id: run.task_id.clone(),
+            body_md: format!(
+                "Landing revision {} did not apply cleanly.\n\n{}\n",
+                run.revision_id.as_str(),
+                detail
+            ),
+        }];
+    }
+
+    vec![Command::MarkBlockedFailure {
+        task_id: run.task_id.clone(),
+        reason: detail,
+    }]
+}
+
+fn failure_result(lane: &Lane, gate: &GateName) -> RunResult {
+    if *lane == Lane::Land && *gate == GateName::Land {
+        RunResult::BlockedHitl
+    } else {
+        RunResult::FailGate
+    }
+}
+
+fn find_run<'a>(snapshot: &'a Snapshot, run_id: &RunId) -> Option<&'a Run> {
+    snapshot.runs.iter().find(|r| r.id == *run_id)
+}
+
+fn run_mut<'a>(snapshot: &'a mut Snapshot, run_id: &RunId) -> Option<&'a mut Run> {
+    snapshot.runs.iter_mut().find(|r| r.id == *run_id)
+}
+
+fn set_result(snapshot: &mut Snapshot, run_id: &RunId, result: RunResult) {
+    if let Some(run) = run_mut(snapshot, run_id) {
+        run.result = Some(result);
+    }
+}
+
+fn set_task_status(snapshot: &mut Snapshot, task_id: &TaskId, status: TaskStatus) {
+    if let Some(task) = snapshot.tasks.iter_mut().find(|t| t.id == *task_id) {
+        task.status = status;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{QueueId, QueueItem, RevisionId, SpecRevId, Task};
 
     #[test]
     fn enqueueable_only_when_ready() {
@@ -37,5 +274,243 @@ mod tests {
         assert!(!task_is_enqueueable(&TaskStatus::BlockedFailure));
         assert!(!task_is_enqueueable(&TaskStatus::BlockedHitl));
         assert!(!task_is_enqueueable(&TaskStatus::Done));
+        assert!(!task_is_enqueueable(&TaskStatus::Cancelled));
+    }
+
+    #[test]
+    fn only_blocked_tasks_reset_and_finished_ones_do_not_cancel() {
+        assert!(task_is_resettable(&TaskStatus::BlockedHitl));
+        assert!(task_is_resettable(&TaskStatus::BlockedFailure));
+        assert!(!task_is_resettable(&TaskStatus::Ready));
+        assert!(!task_is_resettable(&TaskStatus::Cancelled));
+
+        assert!(task_is_cancellable(&TaskStatus::Draft));
+        assert!(task_is_cancellable(&TaskStatus::BlockedHitl));
+        assert!(!task_is_cancellable(&TaskStatus::Done));
+        assert!(!task_is_cancellable(&TaskStatus::Cancelled));
+    }
+
+    fn snapshot_with(lane: Lane, required_gates: Vec<GateName>) -> Snapshot {
+        Snapshot {
+            tasks: vec![Task {
+                id: TaskId::from_str("t1"),
+                title: "t".into(),
+                status: TaskStatus::Ready,
+                priority: 0,
+                tags: vec![],
+            }],
+            revisions: vec![Revision {
+                id: RevisionId::from_str("r1"),
+                task_id: TaskId::from_str("t1"),
+                spec_rev_id: SpecRevId::from_str("s1"),
+                spec_hash: "h".into(),
+                profile: "standard".into(),
+                required_gates,
+                required_validations: vec![],
+                anchors: vec![],
+            }],
+            queue: vec![QueueItem {
+                id: QueueId::from_str("q1"),
+                task_id: TaskId::from_str("t1"),
+                revision_id: RevisionId::from_str("r1"),
+                lane,
+                visible_at_unix: 0,
+                attempts: 0,
+                max_attempts: 3,
+                priority: 0,
+                idempotency_key: "k".into(),
+                artifact_ref: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// Deterministic fake shell: performs every command successfully except gates,
+    /// whose status comes from `gate_status`. Returns the final snapshot and the
+    /// full command trace.
+    fn drive(snapshot: Snapshot, gate_status: impl Fn(&GateName) -> GateStatus) -> (Snapshot, Vec<Command>) {
+        let run_id = RunId::from_str("run-1");
+        let mut snap = snapshot;
+        let mut pending = plan_next(&snap);
+        if pending.is_empty() {
+            // land-lane items are not planned by `plan_next`; lease them directly
+            pending.push(Command::AcquireLease { queue_id: snap.queue[0].id.clone() });
+        }
+        let mut trace = vec![];
+        while let Some(cmd) = pending.pop() {
+            trace.push(cmd.clone());
+            let outcome = match cmd {
+                Command::AcquireLease { queue_id } => Outcome::LeaseAcquired { queue_id },
+                Command::StartRun { queue_id } => Outcome::RunStarted { queue_id, run_id: run_id.clone() },
+                Command::EnsureWorkspace { .. } => Outcome::WorkspaceReady { run_id: run_id.clone() },
+                Command::BuildContextPack { .. } => Outcome::ContextPackBuilt { run_id: run_id.clone() },
+                Command::RunGate { gate } => Outcome::GateCompleted {
+                    run_id: run_id.clone(),
+                    outcome: GateOutcome { status: gate_status(&gate), gate, remediation: None },
+                },
+                Command::EnqueueLandLane { task_id, .. } => Outcome::LandEnqueued {
+                    run_id: run_id.clone(),
+                    task_id,
+                    queue_id: QueueId::from_str("q-land"),
+                },
+                Command::EmitAsk { task_id, .. } => Outcome::AskEmitted { run_id: run_id.clone(), task_id },
+                Command::MarkBlockedFailure { task_id, .. } => {
+                    Outcome::BlockedFailureMarked { run_id: run_id.clone(), task_id }
+                }
+                Command::MarkDone { task_id } => Outcome::DoneMarked { run_id: run_id.clone(), task_id },
+                Command::ReleaseLease { queue_id } => Outcome::LeaseReleased { queue_id },
+            };
+            let (next, cmds) = step(&snap, &outcome);
+            snap = next;
+            pending.extend(cmds.into_iter().rev());
+        }
+        (snap, trace)
+    }
+
+    fn gates_run(trace: &[Command]) -> Vec<GateName> {
+        trace
+            .iter()
+            .filter_map(|c| match c {
+                Command::RunGate { gate } => Some(gate.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn standard_gates() -> Vec<GateName> {
+        vec![
+            GateName::PreSmoke,
+            GateName::Audit,
+            GateName::AdversarialReview,
+            GateName::Validate,
+            GateName::PostSmoke,
+        ]
+    }
+
+    #[test]
+    fn execute_lane_walks_gates_then_enqueues_land() {
+        let (snap, trace) = drive(snapshot_with(Lane::Execute, standard_gates()), |_| GateStatus::Pass);
+
+        assert!(matches!(trace[0], Command::AcquireLease { .. }));
+        assert!(matches!(trace[1], Command::StartRun { .. }));
+        assert!(matches!(trace[2], Command::EnsureWorkspace { vcs: crate::VcsType::Git, .. }));
+        assert!(matches!(trace[3], Command::BuildContextPack { .. }));
+        assert_eq!(
+            gates_run(&trace),
+            vec![GateName::PreSmoke, GateName::Audit, GateName::AdversarialReview, GateName::Validate]
+        );
+        assert!(matches!(trace[trace.len() - 2], Command::EnqueueLandLane { .. }));
+        assert!(matches!(trace[trace.len() - 1], Command::ReleaseLease { .. }));
+
+        assert_eq!(snap.runs[0].result, Some(RunResult::Pass));
+        assert_eq!(snap.runs[0].current_gate, Some(GateName::Validate));
+        // execute lane never finishes the task; landing does
+        assert_eq!(snap.tasks[0].status, TaskStatus::Ready);
+    }
+
+    #[test]
+    fn profile_gate_list_drives_execution() {
+        let docs = vec![GateName::Audit, GateName::AdversarialReview];
+        let (_, trace) = drive(snapshot_with(Lane::Execute, docs.clone()), |_| GateStatus::Pass);
+        assert_eq!(gates_run(&trace), docs);
+    }
+
+    #[test]
+    fn repeated_gate_runs_once() {
+        let gates = vec![GateName::PreSmoke, GateName::Audit, GateName::PreSmoke, GateName::Validate, GateName::Audit];
+        let (_, trace) = drive(snapshot_with(Lane::Execute, gates), |_| GateStatus::Pass);
+        assert_eq!(gates_run(&trace), vec![GateName::PreSmoke, GateName::Audit, GateName::Validate]);
+        assert!(trace.iter().any(|c| matches!(c, Command::EnqueueLandLane { .. })));
+
+        let gates = vec![GateName::PostSmoke, GateName::PostSmoke];
+        let (snap, trace) = drive(snapshot_with(Lane::Land, gates), |_| GateStatus::Pass);
+        assert_eq!(gates_run(&trace), vec![GateName::Land, GateName::PostSmoke]);
+        assert_eq!(snap.tasks[0].status, TaskStatus::Done);
+    }
+
+    #[test]
+    fn gate_outside_the_lane_blocks_instead_of_landing() {
+        let snap = snapshot_with(Lane::Execute, standard_gates());
+        let run_id = RunId::from_str("run-1");
+        let (snap, _) = step(&snap, &Outcome::RunStarted { queue_id: QueueId::from_str("q1"), run_id: run_id.clone() });
+        let outcome = GateOutcome { gate: GateName::Land, status: GateStatus::Pass, remediation: None };
+        let (_, cmds) = step(&snap, &Outcome::GateCompleted { run_id, outcome });
+        assert!(matches!(&cmds[..], [Command::MarkBlockedFailure { reason, .. }] if reason.contains("Land")), "{:?}", cmds);
+    }
+
+    #[test]
+    fn execute_gate_failure_blocks_and_releases() {
+        let (snap, trace) = drive(snapshot_with(Lane::Execute, standard_gates()), |g| {
+            if *g == GateName::Audit { GateStatus::Fail } else { GateStatus::Pass }
+        });
+
+        assert_eq!(gates_run(&trace), vec![GateName::PreSmoke, GateName::Audit]);
+        assert!(trace.iter().any(|c| matches!(c, Command::MarkBlockedFailure { .. })));
+        assert!(!trace.iter().any(|c| matches!(c, Command::EnqueueLandLane { .. })));
+        assert!(matches!(trace.last(), Some(Command::ReleaseLease { .. })));
+        assert_eq!(snap.tasks[0].status, TaskStatus::BlockedFailure);
+        assert_eq!(snap.runs[0].result, Some(RunResult::FailGate));
+    }
+
+    #[test]
+    fn land_lane_lands_then_post_smokes_then_marks_done() {
+        let (snap, trace) = drive(snapshot_with(Lane::Land, standard_gates()), |_| GateStatus::Pass);
+
+        assert!(!trace.iter().any(|c| matches!(c, Command::EnsureWorkspace { .. })));
+        assert_eq!(gates_run(&trace), vec![GateName::Land, GateName::PostSmoke]);
+        assert!(trace.iter().any(|c| matches!(c, Command::MarkDone { .. })));
+        assert!(matches!(trace.last(), Some(Command::ReleaseLease { .. })));
+        assert_eq!(snap.tasks[0].status, TaskStatus::Done);
+        assert_eq!(snap.runs[0].result, Some(RunResult::Pass));
+    }
+
+    #[test]
+    fn land_conflict_emits_ask() {
+        let (snap, trace) = drive(snapshot_with(Lane::Land, standard_gates()), |g| {
+            if *g == GateName::Land { GateStatus::Fail } else { GateStatus::Pass }
+        });
+
+        assert_eq!(gates_run(&trace), vec![GateName::Land]);
+        assert!(trace.iter().any(|c| matches!(c, Command::EmitAsk { .. })));
+        assert!(!trace.iter().any(|c| matches!(c, Command::MarkDone { .. })));
+        assert_eq!(snap.tasks[0].status, TaskStatus::BlockedHitl);
+        assert_eq!(snap.runs[0].result, Some(RunResult::BlockedHitl));
+    }
+
+    #[test]
+    fn post_smoke_failure_blocks_failure() {
+        let (snap, _) = drive(snapshot_with(Lane::Land, standard_gates()), |g| {
+            if *g == GateName::PostSmoke { GateStatus::Fail } else { GateStatus::Pass }
+        });
+        assert_eq!(snap.tasks[0].status, TaskStatus::BlockedFailure);
+    }
+
+    #[test]
+    fn warn_and_skipped_do_not_stop_the_pipeline() {
+        let (_, trace) = drive(snapshot_with(Lane::Execute, standard_gates()), |g| match g {
+            GateName::Audit => GateStatus::Warn,
+            GateName::Validate => GateStatus::Skipped,
+            _ => GateStatus::Pass,
+        });
+        assert!(trace.iter().any(|c| matches!(c, Command::EnqueueLandLane { .. })));
+    }
+
+    #[test]
+    fn missing_revision_blocks_instead_of_guessing_gates() {
+        let mut snap = snapshot_with(Lane::Execute, standard_gates());
+        snap.revisions.clear();
+        let (snap, trace) = drive(snap, |_| GateStatus::Pass);
+        assert!(gates_run(&trace).is_empty());
+        assert_eq!(snap.tasks[0].status, TaskStatus::BlockedFailure);
+    }
+
+    #[test]
+    fn crash_releases_lease() {
+        let snap = snapshot_with(Lane::Execute, standard_gates());
+        let run_id = RunId::from_str("run-1");
+        let (snap, _) = step(&snap, &Outcome::RunStarted { queue_id: QueueId::from_str("q1"), run_id: run_id.clone() });
+        let (snap, cmds) = step(&snap, &Outcome::CrashRecorded { run_id });
+        assert!(matches!(&cmds[..], [Command::ReleaseLease { queue_id }] if queue_id.as_str() == "q1"));
+        assert_eq!(snap.runs[0].result, Some(RunResult::Crash));
     }
 }
@@ -7,6 +7,7 @@ pub enum TaskStatus {
     BlockedHitl,
     BlockedFailure,
     Done,
+    Cancelled,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -53,8 +54,9 @@ pub enum MessageType {
     Reset,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum VcsType {
+    #[default]
     Git,
     Jj,
 }
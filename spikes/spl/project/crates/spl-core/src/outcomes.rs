@@ -7,15 +7,30 @@ pub struct GateOutcome {
     pub remediation: Option<String>,
 }
 
+/// A finished gate as persisted in a run's history.
+#[derive(Clone, Debug)]
+pub struct GateOutcomeRecord {
+    pub run_id: RunId,
+    pub outcome: GateOutcome,
+    pub started_at_unix: i64,
+    pub finished_at_unix: i64,
+    /// Evidence the gate wrote, as artifact refs (relative to the artifact root).
+    pub artifacts: Vec<String>,
+}
+
+/// Result of executing a `Command`, reported back to the functional core by the shell.
 #[derive(Clone, Debug)]
 pub enum Outcome {
     LeaseAcquired { queue_id: QueueId },
     LeaseUnavailable { queue_id: QueueId },
-    RunStarted { run_id: RunId },
+    RunStarted { queue_id: QueueId, run_id: RunId },
+    WorkspaceReady { run_id: RunId },
+    ContextPackBuilt { run_id: RunId },
     GateCompleted { run_id: RunId, outcome: GateOutcome },
-    AskEmitted { task_id: TaskId },
-    LandEnqueued { task_id: TaskId, queue_id: QueueId },
-    DoneMarked { task_id: TaskId },
-    BlockedFailureMarked { task_id: TaskId },
+    AskEmitted { run_id: RunId, task_id: TaskId },
+    LandEnqueued { run_id: RunId, task_id: TaskId, queue_id: QueueId },
+    DoneMarked { run_id: RunId, task_id: TaskId },
+    BlockedFailureMarked { run_id: RunId, task_id: TaskId },
     CrashRecorded { run_id: RunId },
+    LeaseReleased { queue_id: QueueId },
 }
@@ -1,10 +1,12 @@
-use crate::{types::*, Lease, QueueItem, Revision, Run, Task};
+use crate::{types::*, Lease, QueueItem, Revision, Run, Task, VcsType};
 
 /// Read-only view of state used by the functional core to plan next actions.
 /// The imperative shell is responsible for producing this snapshot from storage.
 #[derive(Clone, Debug, Default)]
 pub struct Snapshot {
     pub now_unix: i64,
+    /// Project-level VCS choice; the core passes it through to workspace commands.
+    pub vcs: VcsType,
     pub tasks: Vec<Task>,
     pub revisions: Vec<Revision>,
     pub queue: Vec<QueueItem>,
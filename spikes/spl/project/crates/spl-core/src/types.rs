@@ -9,6 +9,17 @@ pub struct Task {
     pub tags: Vec<String>,
 }
 
+/// A spec pack as compiled: the copy under `.spl/specs` that revisions are derived from.
+#[derive(Clone, Debug)]
+pub struct SpecRevision {
+    pub id: SpecRevId,
+    pub task_id: TaskId,
+    pub spec_hash: String,
+    pub spec_path: String,
+    /// The spec revision this one amends.
+    pub parent_id: Option<SpecRevId>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Revision {
     pub id: RevisionId,
@@ -32,6 +43,8 @@ pub struct QueueItem {
     pub max_attempts: u32,
     pub priority: i32,
     pub idempotency_key: String,
+    /// Artifact the item consumes (land lane: the exported patch), relative to the artifact root.
+    pub artifact_ref: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -46,6 +59,7 @@ pub struct Lease {
 #[derive(Clone, Debug)]
 pub struct Run {
     pub id: RunId,
+    pub queue_id: QueueId,
     pub task_id: TaskId,
     pub revision_id: RevisionId,
     pub lane: Lane,
@@ -59,5 +73,7 @@ pub struct Message {
     pub task_id: TaskId,
     pub ty: MessageType,
     pub body_md: String,
+    /// Machine-readable form of the message, e.g. the JSON of an ASK packet.
+    pub payload_json: Option<String>,
     pub created_at_unix: i64,
 }
@@ -38,6 +38,7 @@ fn test_queue_item_creation() {
         max_attempts: 3,
         priority: 1,
         idempotency_key: "key1".to_string(),
+        artifact_ref: None,
     };
     assert_eq!(queue_item.attempts, 0);
     assert_eq!(queue_item.max_attempts, 3);
@@ -59,6 +60,7 @@ fn test_lease_creation() {
 fn test_run_creation() {
     let run = Run {
         id: RunId::new(),
+        queue_id: QueueId::new(),
         task_id: TaskId::new(),
         revision_id: RevisionId::new(),
         lane: Lane::Execute,
@@ -1,4 +1,84 @@
-use spl_core::{TaskId};
+use serde::{Deserialize, Serialize};
+use spl_core::{GateName, TaskId};
+
+/// What SPL does once an ASK option is chosen.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NextAction {
+    /// Queue the latest revision's execute lane again, from the current mainline, so `gate`
+    /// gets another go.
+    RetryGate { gate: GateName },
+    /// Queue nothing: the task waits for a revision compiled from the amended spec.
+    AmendSpec,
+    /// The operator landed the work by hand: mark the task done without further gates.
+    /// SPL lands nothing itself. Packets stored before the rename say `force_land`.
+    #[serde(alias = "force_land")]
+    MarkDone,
+    /// Cancel the task.
+    Abandon,
+}
+
+impl NextAction {
+    /// The "If chosen, SPL will do next" line.
+    pub fn describe(&self) -> String {
+        match self {
+            NextAction::RetryGate { gate } => format!("re-enqueue the execute lane of the latest revision to retry {:?}", gate),
+            NextAction::AmendSpec => "wait for the amended spec to be compiled into a new revision and enqueue it".to_string(),
+            NextAction::MarkDone => "mark the task done without running further gates".to_string(),
+            NextAction::Abandon => "cancel the task and drop its queued work".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AskOption {
+    pub label: String,
+    pub action: NextAction,
+}
+
+/// A structured ASK: stored as JSON next to its markdown, so an answer names an option
+/// whose action the runner can carry out without reading prose.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AskPacket {
+    pub task_id: TaskId,
+    pub decision: String,
+    pub options: Vec<AskOption>,
+    /// Index into `options`.
+    pub recommended: usize,
+    /// Markdown shown after the options (e.g. conflicting hunks).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub details_md: String,
+}
+
+impl AskPacket {
+    /// Option `chosen`, 1-based as printed in the markdown.
+    pub fn option(&self, chosen: usize) -> Option<&AskOption> {
+        chosen.checked_sub(1).and_then(|i| self.options.get(i))
+    }
+
+    /// The `format_ask` markdown, followed by `details_md`.
+    pub fn to_markdown(&self) -> String {
+        let labels: Vec<&str> = self.options.iter().map(|o| o.label.as_str()).collect();
+        let (recommendation, next) = match self.options.get(self.recommended) {
+            Some(o) => (o.label.as_str(), o.action.describe()),
+            None => ("", String::new()),
+        };
+        let mut md = format_ask(&self.task_id, &self.decision, &labels, recommendation, &next);
+        if !self.details_md.is_empty() {
+            md.push('\n');
+            md.push_str(&self.details_md);
+        }
+        md
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("ask packet serializes")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
 
 /// Minimal ASK packet generator (markdown).
 pub fn format_ask(task_id: &TaskId, decision_needed: &str, options: &[&str], recommendation: &str, next_if_chosen: &str) -> String {
@@ -15,6 +95,51 @@ pub fn format_ask(task_id: &TaskId, decision_needed: &str, options: &[&str], rec
     s
 }
 
+/// An ASK read back from the markdown `format_ask` wrote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedAsk {
+    pub decision_needed: String,
+    pub options: Vec<String>,
+    pub recommendation: String,
+    pub next_if_chosen: String,
+}
+
+/// Parse an ASK written by `format_ask`; anything appended after it (e.g. conflict hunks)
+/// is ignored. None if `md` is not an ASK.
+pub fn parse_ask(md: &str) -> Option<ParsedAsk> {
+    let decision_needed = between(md, "**Decision needed:** ", "\n\n## Options\n")?;
+    let options = md
+        .split_once("## Options\n")?
+        .1
+        .lines()
+        .map_while(|line| line.strip_prefix("- ["))
+        .filter_map(|line| line.split_once("] ").map(|(_, opt)| opt.to_string()))
+        .collect();
+    Some(ParsedAsk {
+        decision_needed: decision_needed.to_string(),
+        options,
+        recommendation: between(md, "**Recommended:** ", "\n")?.to_string(),
+        next_if_chosen: between(md, "**If chosen, SPL will do next:** ", "\n")?.to_string(),
+    })
+}
+
+fn between<'a>(s: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let rest = &s[s.find(start)? + start.len()..];
+    Some(rest.find(end).map_or(rest, |i| &rest[..i]))
+}
+
+/// DECISION markdown answering an ASK with option `chosen` (1-based).
+pub fn format_decision(task_id: &TaskId, chosen: usize, option: &str, next: &str, note: &str) -> String {
+    let mut s = String::new();
+    s.push_str(&format!("# DECISION: {}\n\n", task_id.as_str()));
+    s.push_str(&format!("**Chosen:** [{}] {}\n\n", chosen, option));
+    s.push_str(&format!("**SPL will do next:** {}\n", next));
+    if !note.is_empty() {
+        s.push_str(&format!("\n{}\n", note));
+    }
+    s
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -31,4 +156,57 @@ mod tests {
         let actual = format_ask(&task_id, decision_needed, &options, recommendation, next_if_chosen);
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn parse_ask_reads_back_format_ask() {
+        let md = format_ask(&TaskId::from_str("t"), "Which way?\nDetails.", &["left", "right"], "right", "turn right");
+        let ask = parse_ask(&format!("{}\n### extra.rs\n- [x] not an option\n", md)).unwrap();
+        assert_eq!(ask.decision_needed, "Which way?\nDetails.");
+        assert_eq!(ask.options, vec!["left", "right"]);
+        assert_eq!(ask.recommendation, "right");
+        assert_eq!(ask.next_if_chosen, "turn right");
+        assert_eq!(parse_ask("# UPDATE\nnothing to decide"), None);
+    }
+
+    #[test]
+    fn test_format_decision() {
+        let md = format_decision(&TaskId::from_str("t"), 2, "right", "turn right", "mind the gap");
+        assert_eq!(md, "# DECISION: t\n\n**Chosen:** [2] right\n\n**SPL will do next:** turn right\n\nmind the gap\n");
+    }
+
+    fn packet() -> AskPacket {
+        AskPacket {
+            task_id: TaskId::from_str("t"),
+            decision: "How should it land?".into(),
+            options: vec![
+                AskOption { label: "retry".into(), action: NextAction::RetryGate { gate: GateName::Land } },
+                AskOption { label: "give up".into(), action: NextAction::Abandon },
+            ],
+            recommended: 0,
+            details_md: "### README.md\n".into(),
+        }
+    }
+
+    #[test]
+    fn ask_packet_round_trips_through_json() {
+        let packet = packet();
+        let json = packet.to_json();
+        assert!(json.contains(r#""action":{"kind":"retry_gate","gate":"Land"}"#), "{}", json);
+        assert_eq!(AskPacket::from_json(&json).unwrap(), packet);
+        assert_eq!(packet.option(2).unwrap().action, NextAction::Abandon);
+        assert!(packet.option(0).is_none() && packet.option(3).is_none());
+
+        let old: NextAction = serde_json::from_str(r#"{"kind":"force_land"}"#).unwrap();
+        assert_eq!(serde_json::to_string(&old).unwrap(), r#"{"kind":"mark_done"}"#);
+    }
+
+    #[test]
+    fn ask_packet_renders_the_ask_markdown() {
+        let md = packet().to_markdown();
+        assert!(md.starts_with("# ASK: t\n\n**Decision needed:** How should it land?\n\n## Options\n- [1] retry\n- [2] give up\n"));
+        assert!(md.contains("**If chosen, SPL will do next:** re-enqueue the execute lane of the latest revision to retry Land\n"));
+        assert!(md.ends_with("\n### README.md\n"));
+        let parsed = parse_ask(&md).unwrap();
+        assert_eq!((parsed.options.len(), parsed.recommendation.as_str()), (2, "retry"));
+    }
 }
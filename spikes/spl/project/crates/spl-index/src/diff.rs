@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::SimpleIndex;
+
+/// One anchor that differs between two indexes, with its hash on each side (None where
+/// it is absent).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchorChange {
+    pub anchor: String,
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+}
+
+/// Public anchors added, removed and changed from a base index to a head index, each list
+/// sorted by anchor.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexDiff {
+    pub added: Vec<AnchorChange>,
+    pub removed: Vec<AnchorChange>,
+    pub changed: Vec<AnchorChange>,
+}
+
+impl IndexDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Removed and changed anchors: what callers built against the base may no longer match.
+    pub fn breaking(&self) -> impl Iterator<Item = &AnchorChange> {
+        self.removed.iter().chain(&self.changed)
+    }
+}
+
+pub fn diff_indexes(base: &SimpleIndex, head: &SimpleIndex) -> IndexDiff {
+    let mut diff = IndexDiff::default();
+    for (anchor, new) in &head.anchors {
+        let change = |old: Option<&String>| AnchorChange { anchor: anchor.clone(), old_hash: old.cloned(), new_hash: Some(new.clone()) };
+        match base.anchors.get(anchor) {
+            None => diff.added.push(change(None)),
+            Some(old) if old != new => diff.changed.push(change(Some(old))),
+            Some(_) => {}
+        }
+    }
+    diff.removed = base
+        .anchors
+        .iter()
+        .filter(|(anchor, _)| !head.anchors.contains_key(*anchor))
+        .map(|(anchor, old)| AnchorChange { anchor: anchor.clone(), old_hash: Some(old.clone()), new_hash: None })
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(entries: &[(&str, &str)]) -> SimpleIndex {
+        SimpleIndex { anchors: entries.iter().map(|(a, h)| (a.to_string(), h.to_string())).collect(), ..Default::default() }
+    }
+
+    #[test]
+    fn diff_sorts_anchors_into_added_removed_changed() {
+        let base = index(&[("rust://c::a", "1"), ("rust://c::b", "2"), ("rust://c::keep", "3")]);
+        let head = index(&[("rust://c::b", "20"), ("rust://c::keep", "3"), ("rust://c::new", "4")]);
+
+        let diff = diff_indexes(&base, &head);
+        let change = |a: &str, old: Option<&str>, new: Option<&str>| AnchorChange {
+            anchor: a.into(),
+            old_hash: old.map(String::from),
+            new_hash: new.map(String::from),
+        };
+        assert_eq!(diff.added, vec![change("rust://c::new", None, Some("4"))]);
+        assert_eq!(diff.removed, vec![change("rust://c::a", Some("1"), None)]);
+        assert_eq!(diff.changed, vec![change("rust://c::b", Some("2"), Some("20"))]);
+        assert_eq!(diff.breaking().map(|c| c.anchor.as_str()).collect::<Vec<_>>(), vec!["rust://c::a", "rust://c::b"]);
+        assert!(diff_indexes(&head, &head).is_empty());
+    }
+}
@@ -1,24 +1,59 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use spl_core::AnchorId;
 
+/// Format of `SimpleIndex` files. Bump it whenever what is stored changes meaning; files of
+/// another version load as empty and get rebuilt. 1 was the unversioned anchors-only format;
+/// 2 kept a TypeScript module's parse as a bare symbol map; 3 did not record Rust module
+/// visibility.
+pub const INDEX_VERSION: u32 = 4;
+
 /// Minimal v0 index: file-based map of AnchorId -> signature hash.
-/// Real implementations can replace this with language-aware indexing.
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+/// `update_index` fills it from the language indexers; anchors sort by name on disk.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SimpleIndex {
-    pub anchors: HashMap<String, String>,
+    #[serde(default)]
+    pub version: u32,
+    pub anchors: BTreeMap<String, String>,
+    /// Anchor -> name of the indexer that produced it.
+    #[serde(default)]
+    pub indexers: BTreeMap<String, String>,
+    /// Every indexed file by path relative to the repo root, with what its indexers parsed.
+    #[serde(default)]
+    pub files: BTreeMap<String, IndexedFile>,
+}
+
+impl Default for SimpleIndex {
+    fn default() -> Self {
+        Self { version: INDEX_VERSION, anchors: BTreeMap::new(), indexers: BTreeMap::new(), files: BTreeMap::new() }
+    }
+}
+
+/// One file as of its last parse. `summaries` holds each matching indexer's parse of it
+/// (see `Indexer::parse_file`), valid while the content hash stays the same.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexedFile {
+    /// SHA-256 of the file's bytes, lowercase hex.
+    pub content_hash: String,
+    pub summaries: BTreeMap<String, serde_json::Value>,
 }
 
 impl SimpleIndex {
+    /// A missing file, or one written in another `INDEX_VERSION`, loads as an empty index.
     pub fn load(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Ok(Self::default());
         }
         let bytes = std::fs::read(path)?;
-        let idx: Self = serde_json::from_slice(&bytes)?;
+        let value: serde_json::Value = serde_json::from_slice(&bytes).with_context(|| format!("parse {}", path.display()))?;
+        if value.get("version").and_then(|v| v.as_u64()) != Some(INDEX_VERSION as u64) {
+            return Ok(Self::default());
+        }
+        let idx: Self = serde_json::from_value(value).with_context(|| format!("parse {}", path.display()))?;
         Ok(idx)
     }
 
@@ -26,13 +61,52 @@ impl SimpleIndex {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent).ok();
         }
-        let bytes = serde_json::to_vec_pretty(self)?;
+        let bytes = serde_json::to_vec_pretty(&Self { version: INDEX_VERSION, ..self.clone() })?;
         std::fs::write(path, bytes)?;
         Ok(())
     }
 
-    pub fn resolve_anchor(&self, anchor: &AnchorId) -> Option<String> {
-        self.anchors.get(anchor.as_str()).cloned()
+    /// Look up a spec anchor. Any `#...` suffix is ignored, and `rust://crate::<path>` (the
+    /// spec's "this crate") matches `<path>` in whichever crate has it, if only one does.
+    pub fn resolve_anchor(&self, anchor: &str) -> AnchorResolution {
+        let key = anchor.split_once('#').map_or(anchor, |(key, _)| key).trim();
+        if let Some(sig_hash) = self.anchors.get(key) {
+            return AnchorResolution::Resolved { anchor: key.to_string(), sig_hash: sig_hash.clone() };
+        }
+        let Some(path) = key.strip_prefix("rust://crate::") else { return AnchorResolution::Missing };
+        let mut matches: Vec<(&String, &String)> = self
+            .anchors
+            .iter()
+            .filter(|(k, _)| k.strip_prefix("rust://").and_then(|k| k.split_once("::")).is_some_and(|(_, rest)| rest == path))
+            .collect();
+        match matches.len() {
+            0 => AnchorResolution::Missing,
+            1 => {
+                let (anchor, sig_hash) = matches.remove(0);
+                AnchorResolution::Resolved { anchor: anchor.clone(), sig_hash: sig_hash.clone() }
+            }
+            _ => AnchorResolution::Ambiguous(matches.into_iter().map(|(k, _)| k.clone()).collect()),
+        }
+    }
+}
+
+/// What `SimpleIndex::resolve_anchor` found for a spec anchor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnchorResolution {
+    /// The indexed anchor (without `#`) and its current signature hash.
+    Resolved { anchor: String, sig_hash: String },
+    Missing,
+    /// `rust://crate::` anchor whose path exists in several crates; the candidates, sorted.
+    Ambiguous(Vec<String>),
+}
+
+impl AnchorResolution {
+    /// `<anchor>#<sig_hash>`: the anchor pinned to the signature it has now.
+    pub fn pinned(&self) -> Option<AnchorId> {
+        match self {
+            AnchorResolution::Resolved { anchor, sig_hash } => Some(AnchorId::from_str(format!("{}#{}", anchor, sig_hash))),
+            _ => None,
+        }
     }
 }
 
@@ -41,6 +115,12 @@ pub fn default_index_path(repo_root: &Path) -> PathBuf {
     repo_root.join(".spl").join("index.json")
 }
 
+/// SHA-256 of a canonical signature string, lowercase hex. Every indexer hashes its
+/// canonical form with it, whatever the language.
+pub fn sig_hash(canonical: &str) -> String {
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -56,4 +136,38 @@ mod tests {
         let idx2 = SimpleIndex::load(&path).unwrap();
         assert_eq!(idx2.anchors.get("a").unwrap(), "h");
     }
+
+    #[test]
+    fn other_versions_load_empty() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("index.json");
+        std::fs::write(&path, r#"{"anchors": {"rust://app::f": "h"}}"#).unwrap();
+        let idx = SimpleIndex::load(&path).unwrap();
+        assert!(idx.anchors.is_empty());
+        assert_eq!(idx.version, INDEX_VERSION);
+
+        std::fs::write(&path, r#"{"version": 99, "anchors": ["not", "a", "map"]}"#).unwrap();
+        assert!(SimpleIndex::load(&path).unwrap().anchors.is_empty());
+        std::fs::write(&path, format!(r#"{{"version": {}, "anchors": []}}"#, INDEX_VERSION)).unwrap();
+        assert!(SimpleIndex::load(&path).is_err());
+    }
+
+    #[test]
+    fn resolves_crate_relative_anchors_when_unique() {
+        let mut idx = SimpleIndex::default();
+        for (anchor, hash) in [("rust://app::auth::login", "h1"), ("rust://app::util::parse", "h2"), ("rust://lib::util::parse", "h3")] {
+            idx.anchors.insert(anchor.into(), hash.into());
+        }
+
+        let login = idx.resolve_anchor("rust://crate::auth::login#fn(user: &str)");
+        assert_eq!(login, AnchorResolution::Resolved { anchor: "rust://app::auth::login".into(), sig_hash: "h1".into() });
+        assert_eq!(login.pinned().unwrap().as_str(), "rust://app::auth::login#h1");
+        assert_eq!(idx.resolve_anchor("rust://lib::util::parse").pinned().unwrap().as_str(), "rust://lib::util::parse#h3");
+        assert_eq!(
+            idx.resolve_anchor("rust://crate::util::parse"),
+            AnchorResolution::Ambiguous(vec!["rust://app::util::parse".into(), "rust://lib::util::parse".into()])
+        );
+        assert_eq!(idx.resolve_anchor("rust://crate::auth::logout"), AnchorResolution::Missing);
+        assert_eq!(idx.resolve_anchor("rust://crate::auth"), AnchorResolution::Missing);
+    }
 }
@@ -0,0 +1,230 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use sha2::{Digest, Sha256};
+
+use crate::{IndexedFile, RustIndexer, SimpleIndex, TypeScriptIndexer, INDEX_VERSION};
+
+/// A language plugin for `update_index`: the files it reads, what it keeps of each, and the
+/// anchors that come out of them.
+pub trait Indexer {
+    /// Recorded in `SimpleIndex::indexers` for each anchor it produces.
+    fn name(&self) -> &'static str;
+
+    /// Files it reads, as globs matched against paths relative to the repo root.
+    fn globs(&self) -> &'static [&'static str];
+
+    /// What it keeps of one matching file (`path` relative to the repo root), as JSON. It may
+    /// depend only on the path and `src`: it is reused until the file's content changes.
+    fn parse_file(&self, path: &str, src: &str) -> Result<serde_json::Value>;
+
+    /// Anchor (without `#sig_hash`) -> signature hash, from the parse of every matching file.
+    fn anchors(&self, files: &BTreeMap<&str, &serde_json::Value>) -> Result<BTreeMap<String, String>>;
+}
+
+/// Every language SPL indexes: Rust and TypeScript.
+pub fn default_indexers() -> Vec<Box<dyn Indexer>> {
+    vec![Box::new(RustIndexer), Box::new(TypeScriptIndexer)]
+}
+
+/// Index `repo_root` with `default_indexers`, reusing `cache`'s parse of unchanged files.
+/// `cache` may come from another checkout of the same repo.
+pub fn index_repo(repo_root: &Path, cache: &SimpleIndex) -> Result<SimpleIndex> {
+    let mut index = cache.clone();
+    update_index(&mut index, repo_root, &default_indexers(), None)?;
+    Ok(index)
+}
+
+/// Index `repo_root` from scratch.
+pub fn build_index(repo_root: &Path, indexers: &[Box<dyn Indexer>]) -> Result<SimpleIndex> {
+    let mut index = SimpleIndex::default();
+    update_index(&mut index, repo_root, indexers, None)?;
+    Ok(index)
+}
+
+/// Bring `index` up to date with `repo_root`, parsing only files whose content hash changed,
+/// then rebuild its anchors from every indexer. `target`, `node_modules` and hidden
+/// directories are never read. Two indexers producing the same anchor is an error.
+///
+/// With `changed` (paths relative to the root, e.g. from `VcsAdapter::changed_files`) only
+/// those files are looked at; otherwise every file is hashed and files that are gone are
+/// dropped. An index without files is always scanned in full. Returns how many files were
+/// parsed.
+pub fn update_index(index: &mut SimpleIndex, repo_root: &Path, indexers: &[Box<dyn Indexer>], changed: Option<&[String]>) -> Result<usize> {
+    let globs = indexers.iter().map(|i| glob_set(i.as_ref())).collect::<Result<Vec<_>>>()?;
+    let paths: Vec<String> = match changed {
+        Some(changed) if !index.files.is_empty() => changed.iter().filter(|p| !skipped(p)).cloned().collect(),
+        _ => {
+            let all = repo_files(repo_root)?;
+            let present: BTreeSet<&String> = all.iter().collect();
+            index.files.retain(|path, _| present.contains(path));
+            all
+        }
+    };
+
+    let mut parsed = 0;
+    for path in paths {
+        let matching: Vec<&dyn Indexer> = indexers.iter().zip(&globs).filter(|(_, g)| g.is_match(&path)).map(|(i, _)| i.as_ref()).collect();
+        let file = repo_root.join(&path);
+        if matching.is_empty() || !file.is_file() {
+            index.files.remove(&path);
+            continue;
+        }
+        let bytes = std::fs::read(&file).with_context(|| format!("read {}", file.display()))?;
+        let content_hash = hex::encode(Sha256::digest(&bytes));
+        let old = index.files.get(&path).filter(|f| f.content_hash == content_hash);
+        let mut summaries = BTreeMap::new();
+        let mut reparsed = false;
+        for indexer in matching {
+            let summary = match old.and_then(|f| f.summaries.get(indexer.name())) {
+                Some(summary) => summary.clone(),
+                None => {
+                    reparsed = true;
+                    let src = std::str::from_utf8(&bytes).with_context(|| format!("{} is not UTF-8", file.display()))?;
+                    indexer.parse_file(&path, src).with_context(|| format!("{} indexer: {}", indexer.name(), path))?
+                }
+            };
+            summaries.insert(indexer.name().to_string(), summary);
+        }
+        parsed += reparsed as usize;
+        index.files.insert(path, IndexedFile { content_hash, summaries });
+    }
+
+    let mut anchors = BTreeMap::new();
+    let mut producers: BTreeMap<String, String> = BTreeMap::new();
+    for indexer in indexers {
+        let files: BTreeMap<&str, &serde_json::Value> =
+            index.files.iter().filter_map(|(path, f)| f.summaries.get(indexer.name()).map(|s| (path.as_str(), s))).collect();
+        for (anchor, sig_hash) in indexer.anchors(&files).with_context(|| format!("{} indexer", indexer.name()))? {
+            if let Some(other) = producers.get(&anchor) {
+                bail!("anchor {} produced by both the {} and {} indexers", anchor, other, indexer.name());
+            }
+            producers.insert(anchor.clone(), indexer.name().to_string());
+            anchors.insert(anchor, sig_hash);
+        }
+    }
+    index.version = INDEX_VERSION;
+    index.anchors = anchors;
+    index.indexers = producers;
+    Ok(parsed)
+}
+
+fn glob_set(indexer: &dyn Indexer) -> Result<GlobSet> {
+    let mut globs = GlobSetBuilder::new();
+    for glob in indexer.globs() {
+        globs.add(Glob::new(glob).with_context(|| format!("{} indexer glob {:?}", indexer.name(), glob))?);
+    }
+    Ok(globs.build()?)
+}
+
+/// Whether `path` lies under a directory indexing never reads.
+fn skipped(path: &str) -> bool {
+    let mut dirs = path.split('/').rev().skip(1);
+    dirs.any(|d| d.starts_with('.') || d == "target" || d == "node_modules")
+}
+
+/// Files under `root`, relative to it (`/`-separated) and sorted.
+fn repo_files(root: &Path) -> Result<Vec<String>> {
+    let mut files = vec![];
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("read {}", dir.display()))? {
+            let path = entry?.path();
+            let Ok(relative) = path.strip_prefix(root) else { continue };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if path.is_dir() {
+                if !skipped(&format!("{}/", relative)) {
+                    dirs.push(path);
+                }
+            } else {
+                files.push(relative);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// One anchor per matched file, named after it and hashed by its content.
+    struct FileIndexer(&'static str, &'static [&'static str]);
+
+    impl Indexer for FileIndexer {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn globs(&self) -> &'static [&'static str] {
+            self.1
+        }
+
+        fn parse_file(&self, _path: &str, src: &str) -> Result<serde_json::Value> {
+            Ok(src.into())
+        }
+
+        fn anchors(&self, files: &BTreeMap<&str, &serde_json::Value>) -> Result<BTreeMap<String, String>> {
+            Ok(files.iter().map(|(path, src)| (format!("file://{}", path), src.as_str().unwrap_or_default().to_string())).collect())
+        }
+    }
+
+    fn write(root: &Path, file: &str, content: &str) {
+        let path = root.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn merges_indexers_and_records_the_producer() {
+        let dir = tempdir().unwrap();
+        for file in ["a.txt", "docs/b.md", "node_modules/x/c.txt", ".git/d.txt"] {
+            write(dir.path(), file, "");
+        }
+
+        let indexers: Vec<Box<dyn Indexer>> = vec![Box::new(FileIndexer("text", &["**/*.txt"])), Box::new(FileIndexer("markdown", &["docs/*.md"]))];
+        let index = build_index(dir.path(), &indexers).unwrap();
+        assert_eq!(index.anchors.keys().collect::<Vec<_>>(), vec!["file://a.txt", "file://docs/b.md"]);
+        assert_eq!(index.indexers["file://a.txt"], "text");
+        assert_eq!(index.indexers["file://docs/b.md"], "markdown");
+
+        let overlapping: Vec<Box<dyn Indexer>> = vec![Box::new(FileIndexer("text", &["*.txt"])), Box::new(FileIndexer("any", &["**"]))];
+        let err = build_index(dir.path(), &overlapping).unwrap_err().to_string();
+        assert!(err.contains("produced by both the text and any indexers"), "{}", err);
+    }
+
+    #[test]
+    fn updates_reparse_only_changed_files() {
+        let dir = tempdir().unwrap();
+        for file in ["a.txt", "b.txt", "c.txt"] {
+            write(dir.path(), file, file);
+        }
+        let indexers: Vec<Box<dyn Indexer>> = vec![Box::new(FileIndexer("text", &["**/*.txt"]))];
+        let mut index = SimpleIndex::default();
+        assert_eq!(update_index(&mut index, dir.path(), &indexers, None).unwrap(), 3);
+        assert_eq!(update_index(&mut index, dir.path(), &indexers, None).unwrap(), 0);
+
+        write(dir.path(), "a.txt", "A");
+        std::fs::remove_file(dir.path().join("b.txt")).unwrap();
+        assert_eq!(update_index(&mut index, dir.path(), &indexers, None).unwrap(), 1);
+        assert_eq!(index.anchors["file://a.txt"], "A");
+        assert!(!index.files.contains_key("b.txt"));
+
+        // only the listed files are looked at: c.txt's edit goes unseen
+        write(dir.path(), "c.txt", "C");
+        write(dir.path(), "d.txt", "d");
+        let changed = vec!["d.txt".to_string(), "b.txt".to_string(), ".git/x.txt".to_string()];
+        assert_eq!(update_index(&mut index, dir.path(), &indexers, Some(&changed)).unwrap(), 1);
+        assert_eq!(index.anchors.keys().collect::<Vec<_>>(), vec!["file://a.txt", "file://c.txt", "file://d.txt"]);
+        assert_eq!(index.anchors["file://c.txt"], "c.txt");
+
+        // an empty (or outdated) index is rebuilt in full whatever changed
+        let mut fresh = SimpleIndex::default();
+        assert_eq!(update_index(&mut fresh, dir.path(), &indexers, Some(&changed)).unwrap(), 3);
+        assert_eq!(fresh.anchors["file://c.txt"], "C");
+    }
+}
@@ -1,3 +1,11 @@
+pub mod diff;
 pub mod index;
+pub mod indexer;
+pub mod rust;
+pub mod ts;
 
+pub use diff::*;
 pub use index::*;
+pub use indexer::*;
+pub use rust::*;
+pub use ts::*;
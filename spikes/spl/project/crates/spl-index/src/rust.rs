@@ -0,0 +1,423 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use syn::{Attribute, ImplItem, Item, TraitItem, UseTree, Visibility};
+
+use crate::{build_index, sig_hash, Indexer, SimpleIndex};
+
+/// Indexes the public Rust surface of every library crate in the repo, starting from the
+/// packages' `Cargo.toml` files.
+///
+/// Each `pub` item (and each `pub` inherent method of a type that is not private to its
+/// module, and each item of a `pub` trait) of an exported module becomes an anchor
+/// `rust://<crate>::<module path>::<Item>[::<method>]`, keyed where it is defined. A module is
+/// exported if it is `pub` inside an exported module, or if a `pub use` names it or an item
+/// in it (crates commonly re-export from private modules); test modules never are. The
+/// value is `sig_hash` of the item with
+/// bodies removed and only doc attributes kept, so a body edit keeps the hash while a
+/// signature, visibility or doc change moves it.
+///
+/// Each `.rs` file is parsed on its own, relative to its module; crates are put together
+/// from those parses, so a file that does not parse only fails indexing if a crate uses it.
+pub struct RustIndexer;
+
+/// `RustIndexer`'s parse of a `Cargo.toml` with a library target.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    #[serde(rename = "crate")]
+    krate: String,
+    /// The crate root, relative to the repo root.
+    lib: String,
+}
+
+/// `RustIndexer`'s parse of a `.rs` file.
+#[derive(Default, Serialize, Deserialize)]
+struct RustFile {
+    /// Path relative to the file's module (`Service::send`, `inline::f`) -> sig hash.
+    #[serde(default)]
+    items: BTreeMap<String, String>,
+    /// Out-of-line modules it declares (`mod x;`), by path relative to the file's module.
+    #[serde(default)]
+    mods: Vec<String>,
+    /// Every non-test module it declares, inline or not, by path relative to the file's
+    /// module -> whether it is declared `pub`.
+    #[serde(default)]
+    modules: BTreeMap<String, bool>,
+    /// Paths its `pub use`s name, relative to the file's module unless they start with `crate::`.
+    #[serde(default)]
+    reexports: Vec<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// What `add_module` gathers of one crate, module paths relative to the crate root.
+#[derive(Default)]
+struct CrateItems {
+    /// (module, item, sig hash)
+    items: Vec<(String, String, String)>,
+    /// Module -> declared `pub`.
+    modules: BTreeMap<String, bool>,
+    reexports: Vec<String>,
+}
+
+impl CrateItems {
+    /// Whether items of `module` are part of the crate's public surface.
+    fn exported(&self, module: &str) -> bool {
+        if module.is_empty() {
+            return true;
+        }
+        let parent = module.rsplit_once("::").map_or("", |(parent, _)| parent);
+        let reexported = self.reexports.iter().any(|r| r == module || r.rsplit_once("::").is_some_and(|(p, _)| p == module));
+        reexported || (self.modules.get(module) == Some(&true) && self.exported(parent))
+    }
+}
+
+impl Indexer for RustIndexer {
+    fn name(&self) -> &'static str {
+        "rust"
+    }
+
+    fn globs(&self) -> &'static [&'static str] {
+        &["**/Cargo.toml", "**/*.rs"]
+    }
+
+    fn parse_file(&self, path: &str, src: &str) -> Result<serde_json::Value> {
+        if path == "Cargo.toml" || path.ends_with("/Cargo.toml") {
+            let toml: toml::Value = toml::from_str(src).context("parse manifest")?;
+            let Some(package) = toml.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str()) else {
+                return Ok(serde_json::Value::Null);
+            };
+            let lib = toml.get("lib");
+            let krate = lib.and_then(|l| l.get("name")).and_then(|n| n.as_str()).unwrap_or(package).replace('-', "_");
+            let lib_path = lib.and_then(|l| l.get("path")).and_then(|p| p.as_str()).unwrap_or("src/lib.rs");
+            let dir = path.strip_suffix("Cargo.toml").unwrap_or_default().trim_end_matches('/');
+            return Ok(serde_json::to_value(Manifest { krate, lib: join(dir, lib_path.trim_start_matches("./")) })?);
+        }
+        let mut file = RustFile::default();
+        match syn::parse_file(src) {
+            Ok(parsed) => collect_items(&parsed.items, "", &mut file),
+            Err(err) => file.error = Some(err.to_string()),
+        }
+        Ok(serde_json::to_value(file)?)
+    }
+
+    fn anchors(&self, files: &BTreeMap<&str, &serde_json::Value>) -> Result<BTreeMap<String, String>> {
+        let mut anchors = BTreeMap::new();
+        for (path, parsed) in files {
+            if !path.ends_with("Cargo.toml") || parsed.is_null() {
+                continue;
+            }
+            let manifest: Manifest = serde_json::from_value((*parsed).clone())?;
+            if files.contains_key(manifest.lib.as_str()) {
+                let src_dir = manifest.lib.rsplit_once('/').map_or("", |(dir, _)| dir);
+                let mut krate = CrateItems::default();
+                add_module(files, &manifest.lib, src_dir, "", &mut krate)?;
+                for (module, item, sig_hash) in &krate.items {
+                    if krate.exported(module) {
+                        anchors.insert(format!("rust://{}", path_join(&path_join(&manifest.krate, module), item)), sig_hash.clone());
+                    }
+                }
+            }
+        }
+        Ok(anchors)
+    }
+}
+
+/// Index only the Rust crates under `repo_root`.
+pub fn index_rust_sources(repo_root: &Path) -> Result<SimpleIndex> {
+    build_index(repo_root, &[Box::new(RustIndexer)])
+}
+
+/// `a::b`, or whichever of the two is not empty.
+fn path_join(a: &str, b: &str) -> String {
+    match (a.is_empty(), b.is_empty()) {
+        (true, _) => b.to_string(),
+        (_, true) => a.to_string(),
+        _ => format!("{}::{}", a, b),
+    }
+}
+
+/// `path` with `self` dropped and each `super` taking off the segment before it.
+fn normalize(path: &str) -> String {
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split("::") {
+        match segment {
+            "self" | "" => {}
+            "super" => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("::")
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Gather the module in `file` (at `module`, relative to the crate root) and its `mod x;`
+/// children, which live in `child_dir`. A child whose file is missing (generated, or behind
+/// `#[path]`) is skipped.
+fn add_module(files: &BTreeMap<&str, &serde_json::Value>, file: &str, child_dir: &str, module: &str, krate: &mut CrateItems) -> Result<()> {
+    let parsed: RustFile = serde_json::from_value(files[file].clone())?;
+    if let Some(err) = parsed.error {
+        bail!("parse {}: {}", file, err);
+    }
+    for (item, sig_hash) in parsed.items {
+        // the innermost module declared in the file that the item sits in
+        let inner = parsed.modules.keys().filter(|m| item.starts_with(&format!("{}::", m))).max_by_key(|m| m.len()).map_or("", |m| m.as_str());
+        let name = if inner.is_empty() { item.as_str() } else { &item[inner.len() + 2..] };
+        krate.items.push((path_join(module, inner), name.to_string(), sig_hash));
+    }
+    for (child, public) in parsed.modules {
+        krate.modules.insert(path_join(module, &child), public);
+    }
+    for path in parsed.reexports {
+        krate.reexports.push(normalize(&path.strip_prefix("crate::").map_or_else(|| path_join(module, &path), str::to_string)));
+    }
+    for child in parsed.mods {
+        let dir = join(child_dir, &child.replace("::", "/"));
+        let candidates = [format!("{}.rs", dir), format!("{}/mod.rs", dir)];
+        if let Some(found) = candidates.iter().find(|f| files.contains_key(f.as_str())) {
+            add_module(files, found, &dir, &path_join(module, &child), krate)?;
+        }
+    }
+    Ok(())
+}
+
+/// Record the public items of a module body, and its out-of-line children, under `prefix`
+/// (empty for the file's own module, `inline::` inside an inline module).
+fn collect_items(items: &[Item], prefix: &str, file: &mut RustFile) {
+    let private_types: Vec<String> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(s) if !is_pub(&s.vis) => Some(s.ident.to_string()),
+            Item::Enum(e) if !is_pub(&e.vis) => Some(e.ident.to_string()),
+            Item::Union(u) if !is_pub(&u.vis) => Some(u.ident.to_string()),
+            Item::Type(t) if !is_pub(&t.vis) => Some(t.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    for item in items {
+        match item {
+            Item::Mod(m) if is_cfg_test(&m.attrs) => {}
+            Item::Mod(m) => {
+                file.modules.insert(format!("{}{}", prefix, m.ident), is_pub(&m.vis));
+                match &m.content {
+                    Some((_, inner)) => collect_items(inner, &format!("{}{}::", prefix, m.ident), file),
+                    None => file.mods.push(format!("{}{}", prefix, m.ident)),
+                }
+            }
+            Item::Use(u) if is_pub(&u.vis) && u.leading_colon.is_none() => {
+                let mut paths = vec![];
+                use_paths(&u.tree, "", &mut paths);
+                for path in paths {
+                    file.reexports.push(if path.starts_with("crate::") { path } else { format!("{}{}", prefix, path) });
+                }
+            }
+            Item::Impl(imp) if imp.trait_.is_none() => {
+                let Some(ty) = self_type_name(&imp.self_ty) else { continue };
+                if private_types.contains(&ty) {
+                    continue;
+                }
+                for member in &imp.items {
+                    if let ImplItem::Fn(f) = member {
+                        if matches!(f.vis, Visibility::Public(_)) {
+                            let mut f = f.clone();
+                            f.attrs = doc_attrs(&f.attrs);
+                            f.block = syn::parse_quote!({});
+                            let canonical = format!("impl {} {}", imp.self_ty.to_token_stream(), f.to_token_stream());
+                            file.items.insert(format!("{}{}::{}", prefix, ty, f.sig.ident), sig_hash(&canonical));
+                        }
+                    }
+                }
+            }
+            Item::Trait(t) if matches!(t.vis, Visibility::Public(_)) => {
+                let mut t = t.clone();
+                t.attrs = doc_attrs(&t.attrs);
+                for member in &mut t.items {
+                    if let TraitItem::Fn(f) = member {
+                        f.attrs = doc_attrs(&f.attrs);
+                        f.default = None;
+                        let canonical = format!("trait {} {}", t.ident, f.to_token_stream());
+                        file.items.insert(format!("{}{}::{}", prefix, t.ident, f.sig.ident), sig_hash(&canonical));
+                    }
+                }
+                file.items.insert(format!("{}{}", prefix, t.ident), sig_hash(&t.to_token_stream().to_string()));
+            }
+            _ => {
+                if let Some((ident, canonical)) = public_item(item) {
+                    file.items.insert(format!("{}{}", prefix, ident), sig_hash(&canonical));
+                }
+            }
+        }
+    }
+}
+
+/// Name and canonical form of a `pub` item that is not a module, impl or trait.
+fn public_item(item: &Item) -> Option<(String, String)> {
+    let mut item = item.clone();
+    let (vis, ident, attrs) = match &mut item {
+        Item::Fn(f) => {
+            f.block = syn::parse_quote!({});
+            (&f.vis, f.sig.ident.to_string(), &mut f.attrs)
+        }
+        Item::Struct(s) => (&s.vis, s.ident.to_string(), &mut s.attrs),
+        Item::Enum(e) => (&e.vis, e.ident.to_string(), &mut e.attrs),
+        Item::Union(u) => (&u.vis, u.ident.to_string(), &mut u.attrs),
+        Item::Type(t) => (&t.vis, t.ident.to_string(), &mut t.attrs),
+        Item::Const(c) => (&c.vis, c.ident.to_string(), &mut c.attrs),
+        Item::Static(s) => (&s.vis, s.ident.to_string(), &mut s.attrs),
+        _ => return None,
+    };
+    if !matches!(vis, Visibility::Public(_)) {
+        return None;
+    }
+    *attrs = doc_attrs(attrs);
+    Some((ident, item.to_token_stream().to_string()))
+}
+
+/// `a::b::c` for each leaf of a `use` tree (`*` for a glob; a rename keeps the original name).
+fn use_paths(tree: &UseTree, prefix: &str, paths: &mut Vec<String>) {
+    match tree {
+        UseTree::Path(p) => use_paths(&p.tree, &format!("{}{}::", prefix, p.ident), paths),
+        UseTree::Name(n) => paths.push(format!("{}{}", prefix, n.ident)),
+        UseTree::Rename(r) => paths.push(format!("{}{}", prefix, r.ident)),
+        UseTree::Glob(_) => paths.push(format!("{}*", prefix)),
+        UseTree::Group(g) => g.items.iter().for_each(|t| use_paths(t, prefix, paths)),
+    }
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+fn doc_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs.iter().filter(|a| a.path().is_ident("doc")).cloned().collect()
+}
+
+fn is_cfg_test(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|a| a.path().is_ident("cfg") && a.meta.to_token_stream().to_string().replace(' ', "") == "cfg(test)")
+}
+
+fn self_type_name(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, file: &str, content: &str) {
+        let path = root.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn fixture(root: &Path, send_body: &str) {
+        write(root, "Cargo.toml", "[package]\nname = \"my-app\"\nversion = \"0.1.0\"\n");
+        write(root, "src/lib.rs", "pub mod services;\nmod util;\npub use util::*;\n\n#[cfg(test)]\nmod tests {\n    pub fn helper() {}\n}\n");
+        write(root, "src/util.rs", "/// Adds.\npub fn add(a: u32, b: u32) -> u32 { a + b }\nfn private() {}\n");
+        write(
+            root,
+            "src/services/mod.rs",
+            &format!(
+                "pub mod notify {{\n    #[derive(Debug)]\n    pub struct Service;\n    impl Service {{\n        pub fn send(&self, msg: &str) -> bool {{ {} }}\n        fn internal(&self) {{}}\n    }}\n    pub trait Sink {{ fn push(&mut self, msg: String) {{}} }}\n}}\n",
+                send_body
+            ),
+        );
+        write(root, "target/debug/build/junk/Cargo.toml", "[package]\nname = \"junk\"\n");
+    }
+
+    #[test]
+    fn indexes_public_items_by_module_path() {
+        let dir = tempdir().unwrap();
+        fixture(dir.path(), "true");
+
+        let index = index_rust_sources(dir.path()).unwrap();
+        let keys: Vec<&str> = index.anchors.keys().map(|k| k.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "rust://my_app::services::notify::Service",
+                "rust://my_app::services::notify::Service::send",
+                "rust://my_app::services::notify::Sink",
+                "rust://my_app::services::notify::Sink::push",
+                "rust://my_app::util::add",
+            ]
+        );
+    }
+
+    #[test]
+    fn private_modules_and_types_are_not_exported() {
+        let dir = tempdir().unwrap();
+        write(dir.path(), "Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\n");
+        write(
+            dir.path(),
+            "src/lib.rs",
+            "mod internal;\nmod reached;\npub use reached::Thing;\npub(crate) mod crate_only { pub fn g() {} }\n\
+             struct Private;\nimpl Private { pub fn f() {} }\npub struct Open;\nimpl Open { pub fn f() {} }\n\
+             pub mod outer { mod hidden { pub fn h() {} } pub use self::hidden::h; }\n",
+        );
+        write(dir.path(), "src/internal.rs", "pub fn helper() {}\n");
+        write(dir.path(), "src/reached.rs", "pub struct Thing;\n");
+
+        let index = index_rust_sources(dir.path()).unwrap();
+        assert_eq!(
+            index.anchors.keys().collect::<Vec<_>>(),
+            vec!["rust://app::Open", "rust://app::Open::f", "rust://app::outer::hidden::h", "rust://app::reached::Thing"]
+        );
+    }
+
+    #[test]
+    fn hash_follows_signature_and_docs_not_bodies() {
+        let dir = tempdir().unwrap();
+        fixture(dir.path(), "true");
+        let before = index_rust_sources(dir.path()).unwrap();
+        fixture(dir.path(), "msg.is_empty()");
+        let body_changed = index_rust_sources(dir.path()).unwrap();
+        assert_eq!(before.anchors, body_changed.anchors);
+
+        write(dir.path(), "src/util.rs", "/// Adds two numbers.\npub fn add(a: u32, b: u32) -> u32 { a + b }\n");
+        let doc_changed = index_rust_sources(dir.path()).unwrap();
+        let key = "rust://my_app::util::add";
+        assert_ne!(before.anchors[key], doc_changed.anchors[key]);
+
+        write(dir.path(), "src/util.rs", "/// Adds two numbers.\npub fn add(a: u64, b: u64) -> u64 { a + b }\n");
+        let sig_changed = index_rust_sources(dir.path()).unwrap();
+        assert_ne!(doc_changed.anchors[key], sig_changed.anchors[key]);
+    }
+
+    #[test]
+    fn only_files_a_crate_uses_must_parse() {
+        let dir = tempdir().unwrap();
+        fixture(dir.path(), "true");
+        write(dir.path(), "tests/wip.rs", "fn broken( {");
+        assert!(index_rust_sources(dir.path()).is_ok());
+
+        write(dir.path(), "src/util.rs", "pub fn add( {");
+        let err = format!("{:#}", index_rust_sources(dir.path()).unwrap_err());
+        assert!(err.contains("parse src/util.rs"), "{}", err);
+    }
+
+    #[test]
+    fn indexes_this_workspace() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let index = index_rust_sources(&root).unwrap();
+        assert!(index.anchors.contains_key("rust://spl_index::index::SimpleIndex::resolve_anchor"));
+        assert!(index.anchors.contains_key("rust://spl_index::rust::index_rust_sources"));
+        assert!(!index.anchors.keys().any(|k| k.contains("::tests::")));
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Node, Parser};
+
+use crate::{sig_hash, Indexer};
+
+/// Indexes the exported surface of TypeScript modules (`.ts`, `.tsx`) with the tree-sitter
+/// TypeScript grammar.
+///
+/// Each top-level `export`ed declaration becomes `ts://<file>::<symbol>` (`default` for a
+/// default export), and each public method of an exported class `ts://<file>::<Class>::<method>`.
+/// `export { .. }` lists and `export * from` re-exports are not followed. The hash covers the
+/// declaration's leading `/** .. */` doc and its tokens with function bodies and other comments
+/// removed; overloads of one name hash together.
+///
+/// A module with a syntax error exports nothing; its error is kept in its parse, so one broken
+/// fixture or generated file does not fail the whole index.
+pub struct TypeScriptIndexer;
+
+/// `TypeScriptIndexer`'s parse of a module.
+#[derive(Default, Serialize, Deserialize)]
+struct TsFile {
+    /// Exported symbol -> sig hash.
+    #[serde(default)]
+    exports: BTreeMap<String, String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl Indexer for TypeScriptIndexer {
+    fn name(&self) -> &'static str {
+        "typescript"
+    }
+
+    fn globs(&self) -> &'static [&'static str] {
+        &["**/*.ts", "**/*.tsx"]
+    }
+
+    fn parse_file(&self, path: &str, src: &str) -> Result<serde_json::Value> {
+        let mut file = TsFile::default();
+        match exported_items(src, path.ends_with(".tsx"))? {
+            Ok(items) => {
+                let mut canonical: BTreeMap<String, String> = BTreeMap::new();
+                for (symbol, text) in items {
+                    let entry = canonical.entry(symbol).or_default();
+                    if !entry.is_empty() {
+                        entry.push('\n');
+                    }
+                    entry.push_str(&text);
+                }
+                file.exports = canonical.into_iter().map(|(symbol, text)| (symbol, sig_hash(&text))).collect();
+            }
+            Err(err) => file.error = Some(err),
+        }
+        Ok(serde_json::to_value(file)?)
+    }
+
+    fn anchors(&self, files: &BTreeMap<&str, &serde_json::Value>) -> Result<BTreeMap<String, String>> {
+        let mut anchors = BTreeMap::new();
+        for (path, parsed) in files {
+            let file: TsFile = serde_json::from_value((*parsed).clone())?;
+            anchors.extend(file.exports.into_iter().map(|(symbol, sig_hash)| (format!("ts://{}::{}", path, symbol), sig_hash)));
+        }
+        Ok(anchors)
+    }
+}
+
+/// (symbol, canonical form) of each exported item of a module, in source order, or where
+/// its first syntax error is.
+fn exported_items(src: &str, tsx: bool) -> Result<std::result::Result<Vec<(String, String)>, String>> {
+    let language = if tsx { tree_sitter_typescript::LANGUAGE_TSX } else { tree_sitter_typescript::LANGUAGE_TYPESCRIPT };
+    let mut parser = Parser::new();
+    parser.set_language(&language.into())?;
+    let tree = parser.parse(src, None).ok_or_else(|| anyhow!("parser gave up"))?;
+    let root = tree.root_node();
+    if root.has_error() {
+        let at = first_error(root).unwrap_or(root).start_position();
+        return Ok(Err(format!("syntax error at line {}, column {}", at.row + 1, at.column + 1)));
+    }
+
+    let src = src.as_bytes();
+    let mut items = vec![];
+    let mut cursor = root.walk();
+    for export in root.children(&mut cursor).filter(|n| n.kind() == "export_statement") {
+        let default = (0..export.child_count()).filter_map(|i| export.child(i)).any(|c| c.kind() == "default");
+        let doc = jsdoc(export, src);
+        let Some(mut decl) = export.child_by_field_name("declaration").or_else(|| export.child_by_field_name("value")) else { continue };
+        if decl.kind() == "ambient_declaration" {
+            let Some(inner) = decl.named_child(0) else { continue };
+            decl = inner;
+        }
+        if default {
+            items.push(("default".to_string(), format!("{}{}", doc, canonical(decl, src))));
+            continue;
+        }
+        match decl.kind() {
+            "lexical_declaration" | "variable_declaration" => {
+                let keyword = decl.child(0).map(|k| text(k, src)).unwrap_or_default();
+                let mut declarators = decl.walk();
+                for declarator in decl.named_children(&mut declarators).filter(|n| n.kind() == "variable_declarator") {
+                    if let Some(name) = declarator.child_by_field_name("name").filter(|n| n.kind() == "identifier") {
+                        items.push((text(name, src), format!("{}{} {}", doc, keyword, canonical(declarator, src))));
+                    }
+                }
+            }
+            _ => {
+                let Some(name) = decl.child_by_field_name("name") else { continue };
+                let name = text(name, src);
+                if matches!(decl.kind(), "class_declaration" | "abstract_class_declaration") {
+                    items.extend(public_methods(decl, &name, src));
+                }
+                items.push((name, format!("{}{}", doc, canonical(decl, src))));
+            }
+        }
+    }
+    Ok(Ok(items))
+}
+
+/// The first `ERROR` or missing node under `node`, in source order.
+fn first_error(node: Node) -> Option<Node> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    let found = node.children(&mut cursor).filter(|c| c.has_error()).find_map(first_error);
+    found
+}
+
+/// `<Class>::<method>` of each method in a class body that is not `private`, `protected` or `#private`.
+fn public_methods(class: Node, class_name: &str, src: &[u8]) -> Vec<(String, String)> {
+    let Some(body) = class.child_by_field_name("body") else { return vec![] };
+    let mut methods = vec![];
+    let mut cursor = body.walk();
+    for member in body.named_children(&mut cursor) {
+        if !matches!(member.kind(), "method_definition" | "method_signature" | "abstract_method_signature") {
+            continue;
+        }
+        let Some(name) = member.child_by_field_name("name") else { continue };
+        let mut modifiers = member.walk();
+        let hidden = member
+            .named_children(&mut modifiers)
+            .any(|c| c.kind() == "accessibility_modifier" && text(c, src) != "public");
+        if hidden || name.kind() == "private_property_identifier" {
+            continue;
+        }
+        let signature = format!("{}class {} {}", jsdoc(member, src), class_name, canonical(member, src));
+        methods.push((format!("{}::{}", class_name, text(name, src)), signature));
+    }
+    methods
+}
+
+/// The `/** .. */` comment right before `node`, whitespace-collapsed and followed by a space,
+/// or nothing.
+fn jsdoc(node: Node, src: &[u8]) -> String {
+    match node.prev_sibling().filter(|c| c.kind() == "comment").map(|c| text(c, src)) {
+        Some(doc) if doc.starts_with("/**") => format!("{} ", doc.split_whitespace().collect::<Vec<_>>().join(" ")),
+        _ => String::new(),
+    }
+}
+
+/// `node`'s tokens joined by single spaces, leaving out comments and function bodies.
+fn canonical(node: Node, src: &[u8]) -> String {
+    let mut tokens = vec![];
+    collect_tokens(node, src, &mut tokens);
+    tokens.join(" ")
+}
+
+fn collect_tokens(node: Node, src: &[u8], tokens: &mut Vec<String>) {
+    if node.kind() == "comment" {
+        return;
+    }
+    if node.child_count() == 0 {
+        tokens.push(text(node, src));
+        return;
+    }
+    let function = matches!(
+        node.kind(),
+        "function_declaration" | "generator_function_declaration" | "function_expression" | "generator_function" | "arrow_function" | "method_definition"
+    );
+    for i in 0..node.child_count() {
+        if function && node.field_name_for_child(i as u32) == Some("body") {
+            continue;
+        }
+        if let Some(child) = node.child(i) {
+            collect_tokens(child, src, tokens);
+        }
+    }
+}
+
+fn text(node: Node, src: &[u8]) -> String {
+    node.utf8_text(src).unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_index;
+    use tempfile::tempdir;
+
+    const CLIENT: &str = r#"
+/** Talks to the API. */
+export class Client {
+    constructor(private base: string) {}
+    /** Fetch one user. */
+    async getUser(id: string): Promise<User> { return fetch(this.base + id).then(r => r.json()); }
+    private sign(req: Request): void {}
+    #secret(): void {}
+}
+
+export interface User { id: string; name?: string }
+export type UserId = string;
+export enum Role { Admin, Member }
+export function parse(raw: string): User;
+export function parse(raw: Buffer): User;
+export function parse(raw: any): User { return JSON.parse(raw.toString()); }
+export const VERSION = "1.2.0", retry = (n: number): number => n * 2;
+export default function connect(url: string): Client { return new Client(url); }
+export { helper } from "./helper";
+function internal() {}
+"#;
+
+    fn index(files: &[(&str, &str)]) -> BTreeMap<String, String> {
+        let dir = tempdir().unwrap();
+        for (file, content) in files {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let index = build_index(dir.path(), &[Box::new(TypeScriptIndexer)]).unwrap();
+        assert!(index.indexers.values().all(|i| i == "typescript"));
+        index.anchors
+    }
+
+    #[test]
+    fn indexes_exported_declarations_and_public_methods() {
+        let anchors = index(&[("lib/src/client.ts", CLIENT), ("lib/node_modules/dep/index.ts", "export const x = 1;")]);
+        let keys: Vec<&str> = anchors.keys().map(|k| k.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "ts://lib/src/client.ts::Client",
+                "ts://lib/src/client.ts::Client::constructor",
+                "ts://lib/src/client.ts::Client::getUser",
+                "ts://lib/src/client.ts::Role",
+                "ts://lib/src/client.ts::User",
+                "ts://lib/src/client.ts::UserId",
+                "ts://lib/src/client.ts::VERSION",
+                "ts://lib/src/client.ts::default",
+                "ts://lib/src/client.ts::parse",
+                "ts://lib/src/client.ts::retry",
+            ]
+        );
+    }
+
+    #[test]
+    fn hash_follows_signatures_and_docs_not_bodies_or_comments() {
+        let key = "ts://client.ts::Client::getUser";
+        let before = index(&[("client.ts", CLIENT)]);
+        let body = index(&[("client.ts", &CLIENT.replace("r => r.json()", "r => r.text()").replace("function internal", "// helper\nfunction internal"))]);
+        assert_eq!(before, body);
+
+        let doc = index(&[("client.ts", &CLIENT.replace("/** Fetch one user. */", "/**\n     * Fetch one user,\n     * or none.\n     */"))]);
+        assert_ne!(before[key], doc[key]);
+        assert_eq!(before["ts://client.ts::Client"], doc["ts://client.ts::Client"]);
+        let class_doc = index(&[("client.ts", &CLIENT.replace("/** Talks to the API. */", "/**  Talks to the API. */"))]);
+        assert_eq!(before, class_doc);
+        let line_doc = index(&[("client.ts", &CLIENT.replace("/** Talks to the API. */", "// Talks to the API."))]);
+        assert_ne!(before["ts://client.ts::Client"], line_doc["ts://client.ts::Client"]);
+
+        let sig = index(&[("client.ts", &CLIENT.replace("getUser(id: string)", "getUser(id: number)"))]);
+        assert_ne!(before[key], sig[key]);
+        assert_ne!(before["ts://client.ts::Client"], sig["ts://client.ts::Client"]);
+        assert_eq!(before["ts://client.ts::parse"], sig["ts://client.ts::parse"]);
+
+        let overload = index(&[("client.ts", &CLIENT.replace("export function parse(raw: Buffer): User;\n", ""))]);
+        assert_ne!(before["ts://client.ts::parse"], overload["ts://client.ts::parse"]);
+    }
+
+    #[test]
+    fn a_module_with_a_syntax_error_exports_nothing_and_keeps_its_error() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("lib")).unwrap();
+        std::fs::write(dir.path().join("lib/good.ts"), "export const ok = 1;\n").unwrap();
+        std::fs::write(dir.path().join("lib/bad.tsx"), "export const x = 1;\nexport function f( {\n").unwrap();
+
+        let index = build_index(dir.path(), &[Box::new(TypeScriptIndexer)]).unwrap();
+        assert_eq!(index.anchors.keys().collect::<Vec<_>>(), vec!["ts://lib/good.ts::ok"]);
+        let bad = &index.files["lib/bad.tsx"].summaries["typescript"];
+        assert!(bad["error"].as_str().unwrap().starts_with("syntax error at line 2, column "), "{}", bad);
+    }
+}
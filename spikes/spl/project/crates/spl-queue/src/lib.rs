@@ -37,6 +37,23 @@ pub fn decide_retry(gate: GateName, class: FailureClass, attempt_number: u32) ->
     }
 }
 
+/// v0 gate failure classification (KISS):
+/// - evidence matching a known flake signature: Flake
+/// - adversarial_review / validate: SpecDrift (ambiguity or meaning change needs a human)
+/// - anything else (smoke, audit, ...): Deterministic
+///
+/// `Policy` is reserved for approval-required failures; no v0 gate produces it yet.
+/// Runner crashes never reach this; the shell classifies them as `Crash` directly.
+pub fn classify_gate_failure(gate: &GateName, evidence: &str, flake_signatures: &[String]) -> FailureClass {
+    if flake_signatures.iter().any(|sig| !sig.is_empty() && evidence.contains(sig.as_str())) {
+        return FailureClass::Flake;
+    }
+    match gate {
+        GateName::AdversarialReview | GateName::Validate => FailureClass::SpecDrift,
+        _ => FailureClass::Deterministic,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -46,4 +63,17 @@ mod tests {
         let d = decide_retry(GateName::PreSmoke, FailureClass::Crash, 2);
         assert!(matches!(d, RetryDecision::RetryAfterSecs(_)));
     }
+
+    #[test]
+    fn classifies_gate_failures() {
+        let sigs = vec!["connection reset by peer".to_string()];
+        assert_eq!(classify_gate_failure(&GateName::PreSmoke, "exit: 1", &sigs), FailureClass::Deterministic);
+        assert_eq!(
+            classify_gate_failure(&GateName::PreSmoke, "error: connection reset by peer", &sigs),
+            FailureClass::Flake
+        );
+        assert_eq!(classify_gate_failure(&GateName::Audit, "forbidden git commit", &sigs), FailureClass::Deterministic);
+        assert_eq!(classify_gate_failure(&GateName::AdversarialReview, "", &sigs), FailureClass::SpecDrift);
+        assert_eq!(classify_gate_failure(&GateName::Validate, "", &[]), FailureClass::SpecDrift);
+    }
 }
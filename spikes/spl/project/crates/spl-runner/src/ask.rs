@@ -0,0 +1,208 @@
+use anyhow::{anyhow, bail, Result};
+use spl_core::{Message, MessageType, Task, TaskId, TaskStatus};
+use spl_hitl::{format_decision, AskPacket, NextAction};
+
+use crate::{util::now_unix, Runner};
+
+impl Runner {
+    /// Block the task for HITL on `packet`, stored as its markdown plus the packet's JSON.
+    pub(crate) fn raise_ask(&self, packet: &AskPacket) -> Result<()> {
+        self.storage.set_task_status(&packet.task_id, TaskStatus::BlockedHitl)?;
+        self.storage.add_message_with_payload(&packet.task_id, MessageType::Ask, &packet.to_markdown(), Some(&packet.to_json()), now_unix())
+    }
+
+    /// Every task waiting on a human, with the ASK it is waiting on.
+    pub fn open_asks(&self) -> Result<Vec<(Task, Message)>> {
+        let snap = self.storage.load_snapshot(now_unix())?;
+        let mut asks = vec![];
+        for task in snap.tasks.into_iter().filter(|t| t.status == TaskStatus::BlockedHitl) {
+            let ask = snap.messages.iter().rev().find(|m| m.task_id == task.id && m.ty == MessageType::Ask);
+            if let Some(ask) = ask {
+                asks.push((task, ask.clone()));
+            }
+        }
+        asks.sort_by(|(a, _), (b, _)| b.priority.cmp(&a.priority).then_with(|| a.id.as_str().cmp(b.id.as_str())));
+        Ok(asks)
+    }
+
+    /// The latest ASK of a `BlockedHitl` task.
+    pub fn open_ask(&self, task_id: &TaskId) -> Result<Message> {
+        let task = self.task(task_id)?;
+        if task.status != TaskStatus::BlockedHitl {
+            bail!("task {} is {:?}; it has no open ASK", task_id.as_str(), task.status);
+        }
+        let snap = self.storage.load_snapshot(now_unix())?;
+        snap.messages
+            .into_iter()
+            .rev()
+            .find(|m| &m.task_id == task_id && m.ty == MessageType::Ask)
+            .ok_or_else(|| anyhow!("task {} is blocked for HITL but has no ASK message", task_id.as_str()))
+    }
+
+    /// The packet of a task's open ASK.
+    pub fn open_ask_packet(&self, task_id: &TaskId) -> Result<AskPacket> {
+        let message = self.open_ask(task_id)?;
+        let json = message.payload_json.ok_or_else(|| {
+            anyhow!("the ASK of task {} predates ASK packets; resolve it with task-reset or task-cancel", task_id.as_str())
+        })?;
+        Ok(AskPacket::from_json(&json)?)
+    }
+
+    /// Answer the open ASK of `task_id` with option `chosen` (1-based): carry out the
+    /// option's action, then record a DECISION message. Returns what SPL does next.
+    pub fn answer_ask(&self, task_id: &TaskId, chosen: usize, note: &str) -> Result<String> {
+        let packet = self.open_ask_packet(task_id)?;
+        let option = packet
+            .option(chosen)
+            .ok_or_else(|| anyhow!("option {} out of range; the ASK has {} options", chosen, packet.options.len()))?;
+
+        let now = now_unix();
+        let next = match &option.action {
+            NextAction::RetryGate { gate } => {
+                self.storage.set_task_status(task_id, TaskStatus::Ready)?;
+                match self.requeue_latest_revision(task_id)? {
+                    Some(queue_id) => format!("re-run the latest revision to retry {:?} (queue item {})", gate, queue_id),
+                    None => "resume the work already queued".to_string(),
+                }
+            }
+            NextAction::AmendSpec => {
+                self.storage.set_task_status(task_id, TaskStatus::Ready)?;
+                "run the revision compiled from the amended spec once it is enqueued".to_string()
+            }
+            NextAction::MarkDone => {
+                for record in self.unfinished_items_unless_leased(task_id)? {
+                    self.storage.cancel_queue_item(&record.item.id, "task marked done", now)?;
+                }
+                self.storage.set_task_status(task_id, TaskStatus::Done)?;
+                "nothing; the task is done".to_string()
+            }
+            NextAction::Abandon => {
+                self.cancel_task(task_id, "abandoned by decision")?;
+                "nothing; the task is cancelled".to_string()
+            }
+        };
+        self.storage.add_message(task_id, MessageType::Decision, &format_decision(task_id, chosen, &option.label, &next, note), now)?;
+        Ok(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::tests::{add_task, runner};
+    use spl_core::GateName;
+    use spl_hitl::AskOption;
+
+    /// Block `task` for HITL with the runner's land-conflict options.
+    fn ask(runner: &Runner, task: &str) {
+        let task_id = TaskId::from_str(task);
+        for record in runner.storage.list_queue_items_for_task(&task_id).unwrap() {
+            runner.storage.complete_queue_item(&record.item.id, now_unix()).unwrap();
+        }
+        runner
+            .raise_ask(&AskPacket {
+                task_id,
+                decision: "how should it land?".into(),
+                options: vec![
+                    AskOption { label: "re-run".into(), action: NextAction::RetryGate { gate: GateName::Land } },
+                    AskOption { label: "landed by hand".into(), action: NextAction::MarkDone },
+                    AskOption { label: "abandon".into(), action: NextAction::Abandon },
+                ],
+                recommended: 0,
+                details_md: String::new(),
+            })
+            .unwrap();
+    }
+
+    fn decisions(runner: &Runner, task: &str) -> Vec<String> {
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        snap.messages.into_iter().filter(|m| m.task_id.as_str() == task && m.ty == MessageType::Decision).map(|m| m.body_md).collect()
+    }
+
+    #[test]
+    fn open_asks_lists_only_blocked_tasks() {
+        let (_dir, runner) = runner();
+        add_task(&runner, "a", 0, &[]);
+        add_task(&runner, "b", 0, &[]);
+        ask(&runner, "b");
+
+        let open = runner.open_asks().unwrap();
+        assert_eq!(open.iter().map(|(t, _)| t.id.as_str()).collect::<Vec<_>>(), vec!["b"]);
+        assert!(runner.open_ask(&TaskId::from_str("b")).unwrap().body_md.starts_with("# ASK: b"));
+        assert!(runner.open_ask(&TaskId::from_str("a")).is_err());
+    }
+
+    #[test]
+    fn rerun_answer_requeues_and_records_decision() {
+        let (_dir, runner) = runner();
+        add_task(&runner, "t", 0, &[]);
+        ask(&runner, "t");
+        let task_id = TaskId::from_str("t");
+
+        assert!(runner.answer_ask(&task_id, 0, "").is_err());
+        assert!(runner.answer_ask(&task_id, 4, "").is_err());
+        let next = runner.answer_ask(&task_id, 1, "main moved on").unwrap();
+
+        assert!(next.contains("re-run"), "{}", next);
+        assert_eq!(runner.task(&task_id).unwrap().status, TaskStatus::Ready);
+        let queue = runner.storage.list_queue_items_for_task(&task_id).unwrap();
+        assert!(queue.last().unwrap().finished.is_none());
+        let decisions = decisions(&runner, "t");
+        assert_eq!(decisions.len(), 1);
+        assert!(decisions[0].contains("**Chosen:** [1] re-run") && decisions[0].contains("main moved on"));
+        // answered: nothing open any more
+        assert!(runner.answer_ask(&task_id, 1, "").is_err());
+    }
+
+    #[test]
+    fn done_and_abandon_answers_finish_the_task() {
+        let (_dir, runner) = runner();
+        add_task(&runner, "done", 0, &[]);
+        add_task(&runner, "gone", 0, &[]);
+        ask(&runner, "done");
+        ask(&runner, "gone");
+
+        runner.answer_ask(&TaskId::from_str("done"), 2, "").unwrap();
+        runner.answer_ask(&TaskId::from_str("gone"), 3, "").unwrap();
+
+        assert_eq!(runner.task(&TaskId::from_str("done")).unwrap().status, TaskStatus::Done);
+        assert_eq!(runner.task(&TaskId::from_str("gone")).unwrap().status, TaskStatus::Cancelled);
+        assert!(runner.storage.load_snapshot(i64::MAX).unwrap().queue.is_empty());
+        assert_eq!(decisions(&runner, "gone").len(), 1);
+    }
+
+    #[test]
+    fn done_answer_is_refused_while_an_item_is_leased() {
+        let (_dir, runner) = runner();
+        add_task(&runner, "t", 0, &[]);
+        ask(&runner, "t");
+        let task_id = TaskId::from_str("t");
+        // a re-run queued while the ASK was open, now picked up by a worker
+        let item = runner.enqueue_execute_as("t", "t-rev", "t:rerun".into()).unwrap().queue_id().clone();
+
+        runner.storage.try_acquire_lease(&item, "w1", 60).unwrap().unwrap();
+        let err = runner.answer_ask(&task_id, 2, "").unwrap_err().to_string();
+        assert!(err.contains("w1 holds queue item"), "{}", err);
+        assert_eq!(runner.task(&task_id).unwrap().status, TaskStatus::BlockedHitl);
+        assert!(runner.storage.list_queue_items_for_task(&task_id).unwrap().iter().any(|r| r.finished.is_none()));
+        assert!(decisions(&runner, "t").is_empty());
+
+        runner.storage.release_lease(&item, "w1").unwrap();
+        runner.answer_ask(&task_id, 2, "").unwrap();
+        assert_eq!(runner.task(&task_id).unwrap().status, TaskStatus::Done);
+    }
+
+    #[test]
+    fn asks_without_a_packet_are_not_answered_blindly() {
+        let (_dir, runner) = runner();
+        add_task(&runner, "t", 0, &[]);
+        let task_id = TaskId::from_str("t");
+        runner.storage.set_task_status(&task_id, TaskStatus::BlockedHitl).unwrap();
+        let md = spl_hitl::format_ask(&task_id, "which?", &["abandon the task"], "abandon the task", "cancel");
+        runner.storage.add_message(&task_id, MessageType::Ask, &md, now_unix()).unwrap();
+
+        let err = runner.answer_ask(&task_id, 1, "").unwrap_err().to_string();
+        assert!(err.contains("predates ASK packets"), "{}", err);
+        assert_eq!(runner.task(&task_id).unwrap().status, TaskStatus::BlockedHitl);
+    }
+}
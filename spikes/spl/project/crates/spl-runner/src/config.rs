@@ -1,8 +1,10 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use spl_core::VcsType;
+use spl_spec::{Profile, ProfileSet};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
@@ -10,6 +12,13 @@ pub struct Config {
     pub vcs: VcsConfig,
     pub workspace: WorkspaceConfig,
     pub commands: CommandsConfig,
+    #[serde(default)]
+    pub flakes: FlakesConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// `[profiles.<name>]`; see `Config::profiles` for the full set.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -44,6 +53,37 @@ pub struct CommandsConfig {
     pub post_smoke: Vec<String>,
 }
 
+/// Known-flaky failure signatures. A gate FAIL whose evidence contains one is retried
+/// (at most `auto_retry_count` times) instead of blocking the task.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FlakesConfig {
+    #[serde(default)]
+    pub auto_retry_count: u32,
+    #[serde(default)]
+    pub known_signatures: Vec<String>,
+}
+
+/// Where tasks, queue and runs live. `sqlite` (default) is `.spl/spl.db` in the repo;
+/// `postgres` lets workers on several hosts share one queue.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default = "default_storage_backend")]
+    pub backend: String, // "sqlite" | "postgres"
+    /// Postgres connection string; `$VAR`s are expanded so credentials can stay out of spl.toml.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+fn default_storage_backend() -> String {
+    "sqlite".to_string()
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self { backend: default_storage_backend(), url: None }
+    }
+}
+
 impl Config {
     pub fn default_for_repo(project_id: &str) -> Self {
         Self {
@@ -66,9 +106,24 @@ impl Config {
                 pre_smoke: vec!["true".to_string()],
                 post_smoke: vec!["true".to_string()],
             },
+            flakes: FlakesConfig::default(),
+            storage: StorageConfig::default(),
+            profiles: BTreeMap::new(),
         }
     }
 
+    /// Load the repo's spl.toml, writing the default config first if there is none.
+    pub fn load_for_repo(repo_root: &Path) -> Result<Self> {
+        let cfg_path = Self::config_path(repo_root);
+        if cfg_path.exists() {
+            return Self::load_from(&cfg_path);
+        }
+        let project_id = repo_root.file_name().and_then(|s| s.to_str()).unwrap_or("repo");
+        let cfg = Self::default_for_repo(project_id);
+        cfg.save_to(&cfg_path)?;
+        Ok(cfg)
+    }
+
     pub fn load_from(path: &Path) -> Result<Self> {
         let s = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
         let cfg: Config = toml::from_str(&s).with_context(|| "parse spl.toml")?;
@@ -102,4 +157,59 @@ impl Config {
     pub fn db_path(repo_root: &Path) -> PathBuf {
         repo_root.join(".spl").join("spl.db")
     }
+
+    /// Where a task's spec pack versions are kept, one file per spec hash.
+    pub fn specs_dir(repo_root: &Path, task_id: &str) -> PathBuf {
+        repo_root.join(".spl").join("specs").join(task_id)
+    }
+
+    /// One file per profile, named after it: `.spl/profiles/<name>.toml`.
+    pub fn profiles_dir(repo_root: &Path) -> PathBuf {
+        repo_root.join(".spl").join("profiles")
+    }
+
+    /// The built-in profiles, replaced by name by those of spl.toml and then by those of
+    /// `.spl/profiles/`. A profile defined both in spl.toml and in a file is an error.
+    pub fn profiles(&self, repo_root: &Path) -> Result<ProfileSet> {
+        let mut profiles = ProfileSet::builtin();
+        for (name, profile) in &self.profiles {
+            profiles.define(name, profile.clone())?;
+        }
+        let dir = Self::profiles_dir(repo_root);
+        if !dir.exists() {
+            return Ok(profiles);
+        }
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+            .with_context(|| format!("read {}", dir.display()))?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<std::io::Result<_>>()?;
+        files.retain(|p| p.extension().is_some_and(|e| e == "toml"));
+        files.sort();
+        for path in files {
+            let name = path.file_stem().and_then(|s| s.to_str()).ok_or_else(|| anyhow!("bad profile file name {}", path.display()))?;
+            if self.profiles.contains_key(name) {
+                bail!("profile {:?} is defined in both spl.toml and {}", name, path.display());
+            }
+            let s = std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+            let profile: Profile = toml::from_str(&s).with_context(|| format!("parse {}", path.display()))?;
+            profiles.define(name, profile)?;
+        }
+        Ok(profiles)
+    }
+
+    /// Profiles of the SPL repo at `repo_root` without creating its config; the built-ins
+    /// outside one.
+    pub fn profiles_for_repo(repo_root: &Path) -> Result<ProfileSet> {
+        let cfg_path = Self::config_path(repo_root);
+        if !cfg_path.exists() {
+            return Ok(ProfileSet::builtin());
+        }
+        Self::load_from(&cfg_path)?.profiles(repo_root)
+    }
+
+    /// `storage.url` with environment variables expanded; required by the postgres backend.
+    pub fn postgres_url(&self) -> Result<String> {
+        let url = self.storage.url.as_deref().ok_or_else(|| anyhow!("storage.backend = \"postgres\" needs storage.url in spl.toml"))?;
+        Ok(shellexpand::env(url).with_context(|| "expand storage.url")?.into_owned())
+    }
 }
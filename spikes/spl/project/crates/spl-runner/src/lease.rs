@@ -0,0 +1,107 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use anyhow::Result;
+
+/// Default lease TTL for a run; the heartbeat renews it every third of that.
+pub const LEASE_TTL_SECS: i64 = 300;
+
+/// Run `work` while a background thread calls `renew` every `ttl_secs / 3` (at least once
+/// a second) to extend whatever the run holds: its queue lease, plus the land lock when landing.
+///
+/// Returns the work's result and whether a hold was lost mid-run (renewal refused because
+/// it was reaped and possibly stolen, or the storage errored). A lost lease means another
+/// worker may own the item now; the caller must not settle it.
+pub fn with_heartbeat<T>(ttl_secs: i64, renew: impl Fn() -> Result<bool> + Send, work: impl FnOnce() -> T) -> (T, bool) {
+    with_heartbeat_every(Duration::from_secs((ttl_secs / 3).max(1) as u64), renew, work)
+}
+
+/// `with_heartbeat` with the renewal interval given directly.
+fn with_heartbeat_every<T>(interval: Duration, renew: impl Fn() -> Result<bool> + Send, work: impl FnOnce() -> T) -> (T, bool) {
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+    std::thread::scope(|scope| {
+        let beat = scope.spawn(move || loop {
+            match stop_rx.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => match renew() {
+                    Ok(true) => continue,
+                    Ok(false) | Err(_) => return true,
+                },
+                _ => return false,
+            }
+        });
+
+        let out = work();
+        drop(stop_tx);
+        let lost = beat.join().unwrap_or(true);
+        (out, lost)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use spl_core::{Lane, QueueId, QueueItem, RevisionId, TaskId};
+    use spl_storage::{InMemoryStorage, Storage};
+
+    fn expires_at(storage: &InMemoryStorage, queue_id: &QueueId) -> i64 {
+        let snap = storage.load_snapshot(0).unwrap();
+        snap.leases.iter().find(|l| &l.queue_id == queue_id).unwrap().expires_at_unix
+    }
+
+    fn enqueued(storage: &InMemoryStorage) -> QueueId {
+        let queue_id = QueueId::from_str("q1");
+        storage
+            .enqueue(QueueItem {
+                id: queue_id.clone(),
+                task_id: TaskId::from_str("t1"),
+                revision_id: RevisionId::from_str("r1"),
+                lane: Lane::Execute,
+                visible_at_unix: 0,
+                attempts: 0,
+                max_attempts: 3,
+                priority: 0,
+                idempotency_key: "q1".to_string(),
+                artifact_ref: None,
+            })
+            .unwrap();
+        queue_id
+    }
+
+    #[test]
+    fn heartbeat_keeps_lease_alive_during_long_work() {
+        let storage = InMemoryStorage::new();
+        let queue_id = enqueued(&storage);
+        storage.try_acquire_lease(&queue_id, "w1", 2).unwrap().unwrap();
+        let initial = expires_at(&storage, &queue_id);
+
+        let beats = AtomicUsize::new(0);
+        let renew = || {
+            beats.fetch_add(1, Ordering::SeqCst);
+            storage.renew_lease(&queue_id, "w1", 2)
+        };
+        let ((), lost) = with_heartbeat_every(Duration::from_millis(10), renew, || std::thread::sleep(Duration::from_millis(100)));
+
+        assert!(!lost);
+        assert!(beats.load(Ordering::SeqCst) >= 2);
+        assert!(expires_at(&storage, &queue_id) >= initial);
+    }
+
+    #[test]
+    fn stolen_lease_is_reported_as_lost() {
+        let storage = InMemoryStorage::new();
+        let queue_id = enqueued(&storage);
+        storage.try_acquire_lease(&queue_id, "w1", 2).unwrap().unwrap();
+
+        let renew = || storage.renew_lease(&queue_id, "w1", 2);
+        let ((), lost) = with_heartbeat_every(Duration::from_millis(10), renew, || {
+            storage.release_lease(&queue_id, "w1").unwrap();
+            storage.try_acquire_lease(&queue_id, "w2", 60).unwrap().unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+        });
+
+        assert!(lost);
+    }
+}
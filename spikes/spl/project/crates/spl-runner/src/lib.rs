@@ -1,13 +1,28 @@
+pub mod ask;
 pub mod config;
 pub mod doctor;
+pub mod gates;
+pub mod lease;
 pub mod runner;
+pub mod serve;
+pub mod smoke;
+pub mod spec;
+pub mod tasks;
 pub mod util;
+pub mod validate;
 pub mod scenario;
 
 pub use config::*;
 pub use doctor::*;
+pub use gates::*;
+pub use lease::*;
 pub use runner::*;
+pub use serve::*;
+pub use smoke::*;
+pub use spec::*;
+pub use tasks::*;
 pub use util::*;
+pub use validate::*;
 
 
 #[cfg(test)]
@@ -19,8 +34,9 @@ mod fixture_tests {
     fn loads_and_compiles_sc01_spec_pack() {
         let path = Path::new(env!("CARGO_MANIFEST_DIR"))
             .join("../../fixtures/scenarios/SC-01-happy-path/spec_pack.yaml");
-        let pack = spl_spec::load_spec_pack(&path).unwrap();
-        let draft = spl_spec::compile_revision_draft(&pack);
+        let profiles = spl_spec::ProfileSet::builtin();
+        let pack = spl_spec::load_spec_pack(&path, &profiles).unwrap();
+        let draft = spl_spec::compile_revision_draft(&pack, &profiles, &spl_index::SimpleIndex::default()).unwrap();
         assert_eq!(draft.profile, "standard");
         assert!(!draft.spec_hash.is_empty());
     }
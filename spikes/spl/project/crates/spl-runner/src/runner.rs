@@ -1,39 +1,93 @@
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use anyhow::{anyhow, Context, Result};
 use spl_artifacts::{ArtifactStore, EvidenceManifest, EvidenceRole, FsArtifactStore, GateRecord};
-use spl_core::{GateName, GateOutcome, GateStatus, Lane, QueueId, QueueItem, RunId, TaskId, TaskStatus, VcsType};
-use spl_storage::Storage;
+use spl_core::{
+    GateName, GateOutcome, GateOutcomeRecord, GateStatus, Lane, MessageType, QueueId, QueueItem, Revision, RunId, RunResult, TaskId,
+    TaskStatus, VcsType,
+};
+use spl_storage::{EnqueueResult, MigrationPlan, Storage};
+use spl_storage_postgres::PostgresStorage;
 use spl_storage_sqlite::SqliteStorage;
-use spl_vcs::{Patch, VcsAdapter};
+use spl_hitl::{AskOption, AskPacket, NextAction};
+use spl_spec::ProfileSet;
+use spl_queue::{classify_gate_failure, decide_retry, FailureClass, RetryDecision};
+use spl_vcs::{LandConflict, Patch, VcsAdapter, WorkspaceHandle};
 use spl_vcs_git::GitAdapter;
 use spl_vcs_jj::JjAdapter;
 
-use crate::{doctor::doctor, util::now_unix, Config};
+use crate::{
+    doctor::doctor, evidence_role, index_tree, run_smoke, run_validate, stub_gate, util::now_unix, validate_unindexed, with_heartbeat,
+    Config, GateRun, LEASE_TTL_SECS,
+};
 
 pub struct Runner {
     pub repo_root: PathBuf,
     pub cfg: Config,
-    pub storage: SqliteStorage,
+    /// Built-in and configured profiles, loaded with `cfg`.
+    pub profiles: ProfileSet,
+    pub storage: Box<dyn Storage>,
     pub artifacts: FsArtifactStore,
     pub vcs: Box<dyn VcsAdapter>,
     pub worker_id: String,
+    pub lease_ttl_secs: i64,
+    pub locks: Arc<RepoLocks>,
+}
+
+/// Process-wide locks shared by forked runners. Worktree bookkeeping mutates the repo
+/// root's VCS metadata, which git/jj do not guard against concurrent use. (Landing is
+/// serialized across processes by the storage `land` lock instead.)
+#[derive(Default)]
+pub struct RepoLocks {
+    pub workspaces: Mutex<()>,
+}
+
+impl RepoLocks {
+    fn hold(lock: &Mutex<()>) -> MutexGuard<'_, ()> {
+        lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// How the execute lane ended inside the workspace, short of a crash.
+enum Staged {
+    /// Artifact ref of the exported patch.
+    Exported(String),
+    Failed(GateName, GateRun),
+}
+
+/// Storage resource lock serializing landing per project (one db per project), so
+/// concurrent workers never race on `apply_patch_to_repo_root`.
+const LAND_LOCK: &str = "land";
+
+/// Open (and migrate) the storage backend selected by `[storage]` in spl.toml.
+pub fn open_storage(repo_root: &Path, cfg: &Config) -> Result<Box<dyn Storage>> {
+    match cfg.storage.backend.as_str() {
+        "sqlite" => Ok(Box::new(SqliteStorage::open(&Config::db_path(repo_root))?)),
+        "postgres" => Ok(Box::new(PostgresStorage::connect(&cfg.postgres_url()?)?)),
+        other => Err(anyhow!("unknown storage.backend {:?} in spl.toml (expected \"sqlite\" or \"postgres\")", other)),
+    }
+}
+
+/// Pending schema migrations of the configured storage backend; changes nothing.
+pub fn plan_storage_migrations(repo_root: &Path, cfg: &Config) -> Result<MigrationPlan> {
+    match cfg.storage.backend.as_str() {
+        "sqlite" => spl_storage_sqlite::plan_migrations_at(&Config::db_path(repo_root)),
+        "postgres" => spl_storage_postgres::plan_migrations_at(&cfg.postgres_url()?),
+        other => Err(anyhow!("unknown storage.backend {:?} in spl.toml (expected \"sqlite\" or \"postgres\")", other)),
+    }
 }
 
 impl Runner {
     pub fn open(repo_root: PathBuf) -> Result<Self> {
-        let cfg_path = Config::config_path(&repo_root);
-        let cfg = if cfg_path.exists() {
-            Config::load_from(&cfg_path)?
-        } else {
-            let project_id = repo_root.file_name().and_then(|s| s.to_str()).unwrap_or("repo");
-            let cfg = Config::default_for_repo(project_id);
-            cfg.save_to(&cfg_path)?;
-            cfg
-        };
+        let cfg = Config::load_for_repo(&repo_root)?;
+        Self::with_config(repo_root, cfg)
+    }
 
-        let db_path = Config::db_path(&repo_root);
-        let storage = SqliteStorage::open(&db_path)?;
+    /// Build a runner from an already-loaded config (own db connection and VCS adapter).
+    pub fn with_config(repo_root: PathBuf, cfg: Config) -> Result<Self> {
+        let profiles = cfg.profiles(&repo_root)?;
+        let storage = open_storage(&repo_root, &cfg)?;
         let artifacts_root = shellexpand::tilde(&cfg.project.artifact_root).to_string();
         let artifacts = FsArtifactStore::new(PathBuf::from(artifacts_root));
 
@@ -53,22 +107,31 @@ impl Runner {
         Ok(Self {
             repo_root,
             cfg,
+            profiles,
             storage,
             artifacts,
             vcs,
             worker_id: format!("worker-{}", std::process::id()),
+            lease_ttl_secs: LEASE_TTL_SECS,
+            locks: Arc::default(),
         })
     }
 
+    /// Sibling runner for worker slot `n`: same config and repo locks, its own storage
+    /// connection and worker id.
+    pub fn fork(&self, n: usize) -> Result<Self> {
+        let mut runner = Self::with_config(self.repo_root.clone(), self.cfg.clone())?;
+        runner.worker_id = format!("{}-{}", self.worker_id, n);
+        runner.lease_ttl_secs = self.lease_ttl_secs;
+        runner.locks = self.locks.clone();
+        Ok(runner)
+    }
+
     pub fn init_repo(repo_root: &Path) -> Result<()> {
         std::fs::create_dir_all(repo_root.join(".spl").join("reviews")).ok();
-        let cfg_path = Config::config_path(repo_root);
-        if !cfg_path.exists() {
-            let project_id = repo_root.file_name().and_then(|s| s.to_str()).unwrap_or("repo");
-            Config::default_for_repo(project_id).save_to(&cfg_path)?;
-        }
-        // create db
-        let _ = SqliteStorage::open(&Config::db_path(repo_root))?;
+        let cfg = Config::load_for_repo(repo_root)?;
+        // create (or migrate) the db
+        open_storage(repo_root, &cfg)?;
         Ok(())
     }
 
@@ -76,8 +139,15 @@ impl Runner {
         doctor(&self.repo_root, &self.cfg)
     }
 
-    /// Enqueue a dummy execute item (v0 helper).
-    pub fn enqueue_execute(&self, task_id: &str, revision_id: &str) -> Result<()> {
+    /// Queue the execute lane for a task revision at the task's priority; enqueueing the
+    /// same revision again returns the item already queued for it.
+    pub fn enqueue_execute(&self, task_id: &str, revision_id: &str) -> Result<EnqueueResult> {
+        self.enqueue_execute_as(task_id, revision_id, format!("{}:{}:execute", task_id, revision_id))
+    }
+
+    /// `enqueue_execute` under an explicit idempotency key, so a revision can run again.
+    pub(crate) fn enqueue_execute_as(&self, task_id: &str, revision_id: &str, idempotency_key: String) -> Result<EnqueueResult> {
+        let task = self.task(&TaskId::from_str(task_id))?;
         let qi = QueueItem {
             id: QueueId::new(),
             task_id: TaskId::from_str(task_id),
@@ -86,44 +156,221 @@ impl Runner {
             visible_at_unix: now_unix(),
             attempts: 0,
             max_attempts: 3,
-            priority: 0,
-            idempotency_key: format!("{}:{}:execute", task_id, revision_id),
+            priority: task.priority,
+            idempotency_key,
+            artifact_ref: None,
         };
-        self.storage.enqueue(qi)?;
-        Ok(())
+        self.storage.enqueue(qi)
     }
 
-    /// Run a single visible execute-lane queue item and, if successful, run land lane.
+    /// Run one visible queue item: a land item if any is waiting, otherwise an execute item.
+    /// A passing execute run exports its patch and enqueues a land item for a later call.
+    ///
+    /// Gates come from the revision's `required_gates`, in order; the first FAIL stops
+    /// the run. Gates without a real executor yet are stubbed, but evidence is real.
     ///
-    /// v0 implementation is intentionally small: gates are stubbed, but evidence is real.
+    /// Gate FAILs and runner crashes are classified and settled through
+    /// `spl_queue::decide_retry`: the item is rescheduled with backoff, dead-lettered
+    /// once it runs out of attempts, or the task is blocked (with an ASK for HITL).
+    /// The lease is renewed by a heartbeat while the run is in flight.
     pub fn run_once(&self, dry_run: bool) -> Result<()> {
         self.doctor()?;
+        if !dry_run {
+            self.recover_expired_leases()?;
+        }
 
-        let now = now_unix();
-        let snap = self.storage.load_snapshot(now)?;
-        let mut items = snap.queue.clone();
-        items.sort_by(|a,b| b.priority.cmp(&a.priority).then(a.visible_at_unix.cmp(&b.visible_at_unix)));
-        let item = items.into_iter().find(|q| q.lane == Lane::Execute).ok_or_else(|| anyhow!("no visible execute items"))?;
+        // land first: finish what already passed before starting new work
+        let item = self
+            .visible_items(Lane::Land)?
+            .into_iter()
+            .chain(self.visible_items(Lane::Execute)?)
+            .next()
+            .ok_or_else(|| anyhow!("no visible queue items"))?;
 
         if dry_run {
             println!("DRY RUN: would attempt lease for queue_id={}", item.id.as_str());
             return Ok(());
         }
 
-        let lease = self.storage.try_acquire_lease(&item.id, &self.worker_id, 300)?;
+        if !self.run_item(&item)? {
+            return Err(anyhow!("could not acquire lease or land lock (another worker?)"));
+        }
+        Ok(())
+    }
+
+    /// Visible items of `lane`, highest priority first, then oldest `visible_at`.
+    pub fn visible_items(&self, lane: Lane) -> Result<Vec<QueueItem>> {
+        let snap = self.storage.load_snapshot(now_unix())?;
+        let mut items: Vec<QueueItem> = snap.queue.into_iter().filter(|q| q.lane == lane).collect();
+        items.sort_by(|a,b| b.priority.cmp(&a.priority).then(a.visible_at_unix.cmp(&b.visible_at_unix)));
+        Ok(items)
+    }
+
+    /// Lease `item` and run it to a settled state through its lane's path. Returns false if
+    /// another worker holds the lease (or, for land items, the project's land lock).
+    pub fn run_item(&self, item: &QueueItem) -> Result<bool> {
+        if item.lane == Lane::Execute {
+            return self.with_lease(item, || self.execute_leased(item));
+        }
+
+        if !self.storage.try_lock_resource(LAND_LOCK, &self.worker_id, self.lease_ttl_secs)? {
+            return Ok(false);
+        }
+        let ran = self.with_lease(item, || self.land_leased(item));
+        let released = self.storage.release_resource(LAND_LOCK, &self.worker_id);
+        let ran = ran?;
+        released?;
+        Ok(ran)
+    }
+
+    /// Hold the lease on `item` while `run` executes; released on every path once acquired.
+    fn with_lease(&self, item: &QueueItem, run: impl FnOnce() -> Result<()>) -> Result<bool> {
+        let lease = self.storage.try_acquire_lease(&item.id, &self.worker_id, self.lease_ttl_secs)?;
         if lease.is_none() {
-            return Err(anyhow!("could not acquire lease (another worker?)"));
+            return Ok(false);
         }
+        let ran = run();
+        // no-op if the lease was lost: it is keyed by our worker id
+        let released = self.storage.release_lease(&item.id, &self.worker_id);
+        ran?;
+        released?;
+        Ok(true)
+    }
+
+    fn lost_lease(item: &QueueItem, run_id: &RunId) -> anyhow::Error {
+        anyhow!(
+            "lease on queue item {} was lost during run {}; leaving the item to its new owner",
+            item.id.as_str(),
+            run_id.as_str()
+        )
+    }
 
+    fn execute_leased(&self, item: &QueueItem) -> Result<()> {
         let run_id = RunId::new();
         self.storage.create_run(&item.id, run_id.clone())?;
 
-        let run_dir = self.artifacts.create_run_dir(&self.cfg.project.id, &run_id)?;
-        self.artifacts.append_worklog(&run_dir, &format!("## Run {} for task {}", run_id.as_str(), item.task_id.as_str()))?;
+        let renew = || self.storage.renew_lease(&item.id, &self.worker_id, self.lease_ttl_secs);
+        let (outcome, lease_lost) = with_heartbeat(self.lease_ttl_secs, renew, || self.execute_item(item, &run_id));
+        if lease_lost {
+            self.storage.finish_run(&run_id, RunResult::Crash, now_unix())?;
+            return Err(Self::lost_lease(item, &run_id));
+        }
 
-        // Create workspace and run stub gates
-        let ws_root = self.cfg.workspace_root(&self.repo_root);
-        let ws = self.vcs.create_workspace(&self.repo_root, &ws_root, &item.task_id)?;
+        let (result, gate, class, remediation) = match outcome {
+            Ok(None) => {
+                self.storage.finish_run(&run_id, RunResult::Pass, now_unix())?;
+                return self.storage.complete_queue_item(&item.id, now_unix());
+            }
+            Ok(Some((gate, run))) => {
+                let evidence = String::from_utf8_lossy(&run.bytes);
+                let flakes = &self.cfg.flakes;
+                let mut class = classify_gate_failure(&gate, &evidence, &flakes.known_signatures);
+                if class == FailureClass::Flake && item.attempts >= flakes.auto_retry_count {
+                    class = FailureClass::Deterministic;
+                }
+                let remediation = run.remediation.clone().unwrap_or_else(|| format!("{:?} failed", gate));
+                (RunResult::FailGate, gate, class, remediation)
+            }
+            // Anything before the first gate (revision lookup, workspace setup) counts as spec_compile.
+            Err(err) => {
+                let gate = self.current_gate(&run_id)?.unwrap_or(GateName::SpecCompile);
+                (RunResult::Crash, gate, FailureClass::Crash, format!("runner crashed: {:#}", err))
+            }
+        };
+        // A failure that raises an ASK is recorded as such, like a land run blocked on a conflict.
+        let result = match Self::retry_decision(item, &gate, &class) {
+            RetryDecision::BlockHitl { .. } => RunResult::BlockedHitl,
+            _ => result,
+        };
+        self.storage.finish_run(&run_id, result, now_unix())?;
+        self.settle_failure(item, gate, class, &remediation)
+    }
+
+    /// Land-lane path; the caller holds the project's land lock as well as the lease.
+    /// Gate FAILs block the task (the patch may already be on mainline, so no blind retry);
+    /// crashes go through the retry policy.
+    fn land_leased(&self, item: &QueueItem) -> Result<()> {
+        let run_id = RunId::new();
+        self.storage.create_run(&item.id, run_id.clone())?;
+
+        let renew = || {
+            Ok(self.storage.renew_lease(&item.id, &self.worker_id, self.lease_ttl_secs)?
+                && self.storage.try_lock_resource(LAND_LOCK, &self.worker_id, self.lease_ttl_secs)?)
+        };
+        let (outcome, lost) = with_heartbeat(self.lease_ttl_secs, renew, || self.land_item(item, &run_id));
+        if lost {
+            self.storage.finish_run(&run_id, RunResult::Crash, now_unix())?;
+            return Err(Self::lost_lease(item, &run_id));
+        }
+
+        match outcome {
+            Ok(result) => {
+                self.storage.finish_run(&run_id, result.clone(), now_unix())?;
+                self.storage.complete_queue_item(&item.id, now_unix())?;
+                if result == RunResult::Pass {
+                    self.storage.set_task_status(&item.task_id, TaskStatus::Done)?;
+                }
+                Ok(())
+            }
+            Err(err) => {
+                self.storage.finish_run(&run_id, RunResult::Crash, now_unix())?;
+                self.settle_failure(item, GateName::Land, FailureClass::Crash, &format!("runner crashed: {:#}", err))
+            }
+        }
+    }
+
+    /// Load the patch the execute run exported and run the revision's land-lane gates.
+    fn land_item(&self, item: &QueueItem, run_id: &RunId) -> Result<RunResult> {
+        let revision = self
+            .storage
+            .get_revision(&item.revision_id)?
+            .ok_or_else(|| anyhow!("revision {} not found for queue item {}", item.revision_id.as_str(), item.id.as_str()))?;
+        let artifact_ref = item
+            .artifact_ref
+            .as_deref()
+            .ok_or_else(|| anyhow!("land item {} carries no patch artifact", item.id.as_str()))?;
+        let patch = Patch { bytes: self.artifacts.read_ref(artifact_ref)?, format: "git".into() };
+        self.run_land(&item.task_id, &revision, &patch, run_id)
+    }
+
+    /// Settle items whose worker stopped heartbeating: the abandoned attempt counts as a
+    /// crash, so the item is rescheduled with backoff (or dead-lettered) like any other.
+    pub(crate) fn recover_expired_leases(&self) -> Result<()> {
+        let now = now_unix();
+        let reaped = self.storage.reap_expired_leases(now)?;
+        if reaped.is_empty() {
+            return Ok(());
+        }
+        let snap = self.storage.load_snapshot(now)?;
+        for lease in reaped {
+            let Some(item) = snap.queue.iter().find(|q| q.id == lease.queue_id) else {
+                continue;
+            };
+            // the abandoned run is the one that never got a result
+            let abandoned = snap.runs.iter().find(|r| r.queue_id == item.id && r.result.is_none());
+            if let Some(run) = abandoned {
+                self.storage.finish_run(&run.id, RunResult::Crash, now)?;
+            }
+            let gate = abandoned.and_then(|r| r.current_gate.clone()).unwrap_or(GateName::SpecCompile);
+            let remediation = format!(
+                "worker {} stopped renewing its lease (expired at {}); the run was abandoned",
+                lease.worker_id, lease.expires_at_unix
+            );
+            self.settle_failure(item, gate, FailureClass::Crash, &remediation)?;
+        }
+        Ok(())
+    }
+
+    /// Execute lane for one item; on success the exported patch is handed to a land-lane
+    /// item by artifact ref. Returns the failing execute-lane gate, if any; `Err` is a runner crash.
+    fn execute_item(&self, item: &QueueItem, run_id: &RunId) -> Result<Option<(GateName, GateRun)>> {
+        let revision = self
+            .storage
+            .get_revision(&item.revision_id)?
+            .ok_or_else(|| anyhow!("revision {} not found for queue item {}", item.revision_id.as_str(), item.id.as_str()))?;
+
+        let run_dir = self.artifacts.create_run_dir(&self.cfg.project.id, run_id)?;
+        self.artifacts.append_worklog(&run_dir, &format!("## Run {} for task {}", run_id.as_str(), item.task_id.as_str()))?;
 
         let mut manifest = EvidenceManifest {
             run_id: run_id.as_str().to_string(),
@@ -131,35 +378,33 @@ impl Runner {
             revision_id: item.revision_id.as_str().to_string(),
             lane: "execute".into(),
             vcs_type: format!("{:?}", self.vcs.vcs_type()),
-            spec_hash: "stub".into(),
+            spec_hash: revision.spec_hash.clone(),
             base_rev: None,
             head_rev: None,
             gates: vec![],
         };
 
-        // pre_smoke stub
-        self.write_gate(&run_id, &run_dir, &mut manifest, GateName::PreSmoke, GateStatus::Pass, EvidenceRole::PreSmoke, b"pre_smoke: PASS (stub)")?;
-        // audit stub
-        self.write_gate(&run_id, &run_dir, &mut manifest, GateName::Audit, GateStatus::Pass, EvidenceRole::Audit, b"{\"result\":\"PASS\"}")?;
-        // review stub
-        self.write_gate(&run_id, &run_dir, &mut manifest, GateName::AdversarialReview, GateStatus::Pass, EvidenceRole::Review, b"{\"result\":\"PASS\"}")?;
-        // validate stub
-        self.write_gate(&run_id, &run_dir, &mut manifest, GateName::Validate, GateStatus::Pass, EvidenceRole::Validate, b"{\"result\":\"PASS\"}")?;
-
-        // Snapshot + patch (may be empty if no changes)
-        let base = self.vcs.get_base_rev(&self.repo_root)?;
-        let head = self.vcs.snapshot(&ws, "spl snapshot")?;
-        let patch = self.vcs.export_patch(&ws, &base, &head)?;
-        manifest.base_rev = Some(base.clone());
-        manifest.head_rev = Some(head.clone());
-        let _ = self.artifacts.write_role_bytes(&run_dir, EvidenceRole::Diff, "diff.patch", &patch.bytes)?;
-
-        self.artifacts.write_manifest(&run_dir, &manifest)?;
+        // Create workspace and run the revision's execute-lane gates; the workspace
+        // is cleaned up whether the gates pass, fail or crash.
+        let ws_root = self.cfg.workspace_root(&self.repo_root);
+        let ws = {
+            let _admin = RepoLocks::hold(&self.locks.workspaces);
+            self.vcs.create_workspace(&self.repo_root, &ws_root, &item.task_id)?
+        };
+        let staged = self.execute_in_workspace(&ws, run_id, &run_dir, &mut manifest, &revision);
+        let cleaned = {
+            let _admin = RepoLocks::hold(&self.locks.workspaces);
+            self.vcs.cleanup_workspace(&self.repo_root, ws)
+        };
+        let staged = staged?;
+        cleaned?;
 
-        // cleanup workspace
-        self.vcs.cleanup_workspace(&self.repo_root, ws)?;
+        let patch_ref = match staged {
+            Staged::Exported(patch_ref) => patch_ref,
+            Staged::Failed(gate, result) => return Ok(Some((gate, result))),
+        };
 
-        // enqueue land lane item (idempotency protected in db)
+        // one land item per execute item: a re-run of the same item finds it already queued
         let land_item = QueueItem {
             id: QueueId::new(),
             task_id: item.task_id.clone(),
@@ -169,73 +414,273 @@ impl Runner {
             attempts: 0,
             max_attempts: 3,
             priority: item.priority,
-            idempotency_key: format!("{}:{}:land", item.task_id.as_str(), item.revision_id.as_str()),
+            idempotency_key: land_key(&item.idempotency_key),
+            artifact_ref: Some(patch_ref),
+        };
+        if let EnqueueResult::Existing(existing) = self.storage.enqueue(land_item).context("enqueue land item")? {
+            self.artifacts.append_worklog(&run_dir, &format!("land item already queued as {}", existing.as_str()))?;
+        }
+        Ok(None)
+    }
+
+    /// `pre_smoke`/`post_smoke` commands for `revision`: its profile's, else `[commands]`.
+    /// A profile since removed from the config falls back to `[commands]` too.
+    fn smoke_commands(&self, revision: &Revision, gate: &GateName) -> &[String] {
+        let profile = self.profiles.get(&revision.profile).ok();
+        let (own, default) = match gate {
+            GateName::PostSmoke => (profile.and_then(|p| p.post_smoke.as_ref()), &self.cfg.commands.post_smoke),
+            _ => (profile.and_then(|p| p.pre_smoke.as_ref()), &self.cfg.commands.pre_smoke),
         };
-        let _ = self.storage.enqueue(land_item);
+        own.unwrap_or(default)
+    }
+
+    fn execute_in_workspace(
+        &self,
+        ws: &WorkspaceHandle,
+        run_id: &RunId,
+        run_dir: &Path,
+        manifest: &mut EvidenceManifest,
+        revision: &Revision,
+    ) -> Result<Staged> {
+        let gates = spl_core::execute_lane_gates(revision);
+        // validate diffs against the base rev the patch is exported against: the workspace as
+        // created, before any gate has touched it
+        let mut validate_base = Ok(Default::default());
+        if gates.contains(&GateName::Validate) {
+            validate_base = index_tree(&self.repo_root, &ws.path).map_err(|err| validate_unindexed(&err.context("index the base rev")));
+        }
+        for gate in gates {
+            let started_at = self.start_gate(run_id, &gate)?;
+            let result = match gate {
+                GateName::PreSmoke => run_smoke("pre_smoke", self.smoke_commands(revision, &GateName::PreSmoke), &ws.path)?,
+                GateName::Validate => match &validate_base {
+                    Ok(base) => run_validate(base, &ws.path, &revision.anchors),
+                    Err(unindexed) => unindexed.clone(),
+                },
+                _ => stub_gate(&gate),
+            };
+            let failed = result.status == GateStatus::Fail;
+            self.write_gate(run_id, run_dir, manifest, gate.clone(), &result, started_at)?;
+            if failed {
+                self.artifacts.append_worklog(run_dir, &format!("{:?}: FAIL; stopping run", gate))?;
+                self.artifacts.write_manifest(run_dir, manifest)?;
+                return Ok(Staged::Failed(gate, result));
+            }
+        }
+
+        // Snapshot + patch (may be empty if no changes)
+        let base = self.vcs.get_base_rev(&self.repo_root)?;
+        let head = self.vcs.snapshot(ws, "spl snapshot")?;
+        let patch = self.vcs.export_patch(ws, &base, &head)?;
+        manifest.base_rev = Some(base.clone());
+        manifest.head_rev = Some(head.clone());
+        let patch_path = self.artifacts.write_role_bytes(run_dir, EvidenceRole::Diff, "diff.patch", &patch.bytes)?;
 
-        // run land lane immediately (v0 convenience)
-        self.run_land(&item.task_id, &patch)?;
+        self.artifacts.write_manifest(run_dir, manifest)?;
+        Ok(Staged::Exported(self.artifacts.artifact_ref(&patch_path)?))
+    }
 
-        self.storage.set_task_status(&item.task_id, TaskStatus::Done)?;
-        self.storage.release_lease(&item.id, &self.worker_id)?;
+    /// Apply `decide_retry` for a failed execute item to storage. The caller holds the lease.
+    fn settle_failure(&self, item: &QueueItem, gate: GateName, class: FailureClass, remediation: &str) -> Result<()> {
+        let now = now_unix();
+        let attempt = item.attempts + 1;
+        match Self::retry_decision(item, &gate, &class) {
+            RetryDecision::RetryAfterSecs(secs) if attempt < item.max_attempts => {
+                self.storage.reschedule(&item.id, attempt, now + secs as i64)?;
+                let body = format!(
+                    "{:?}: {:?} on attempt {}/{}; retrying in {}s\n\n{}",
+                    gate, class, attempt, item.max_attempts, secs, remediation
+                );
+                self.storage.add_message(&item.task_id, MessageType::Update, &body, now)?;
+            }
+            RetryDecision::RetryAfterSecs(_) => {
+                let reason = format!("{:?}: {:?} on attempt {}/{}; dead-lettered", gate, class, attempt, item.max_attempts);
+                self.storage.reschedule(&item.id, attempt, item.visible_at_unix)?;
+                self.storage.dead_letter(&item.id, &reason, now)?;
+                self.storage.set_task_status(&item.task_id, TaskStatus::BlockedFailure)?;
+                self.storage.add_message(&item.task_id, MessageType::Update, &format!("{}\n\n{}", reason, remediation), now)?;
+            }
+            RetryDecision::BlockFailure { reason } => {
+                self.storage.complete_queue_item(&item.id, now)?;
+                self.storage.set_task_status(&item.task_id, TaskStatus::BlockedFailure)?;
+                let body = format!("{:?}: FAIL ({})\n\n{}", gate, reason, remediation);
+                self.storage.add_message(&item.task_id, MessageType::Update, &body, now)?;
+            }
+            RetryDecision::BlockHitl { ask_md } => {
+                self.storage.complete_queue_item(&item.id, now)?;
+                self.raise_ask(&AskPacket {
+                    task_id: item.task_id.clone(),
+                    decision: format!("{}: {:?} failed ({:?}). {}", ask_md, gate, class, remediation),
+                    options: vec![
+                        AskOption { label: "amend the spec and re-run".into(), action: NextAction::AmendSpec },
+                        AskOption { label: "reset and retry the current revision".into(), action: NextAction::RetryGate { gate } },
+                        AskOption { label: "abandon the task".into(), action: NextAction::Abandon },
+                    ],
+                    recommended: 0,
+                    details_md: String::new(),
+                })?;
+            }
+        }
         Ok(())
     }
 
-    fn run_land(&self, task_id: &TaskId, patch: &Patch) -> Result<()> {
-        let run_id = RunId::new();
-        let run_dir = self.artifacts.create_run_dir(&self.cfg.project.id, &run_id)?;
+    /// `decide_retry` for the attempt `item` just made.
+    fn retry_decision(item: &QueueItem, gate: &GateName, class: &FailureClass) -> RetryDecision {
+        decide_retry(gate.clone(), class.clone(), item.attempts + 1)
+    }
+
+    /// Run the revision's land-lane gates. Returns the run's result; unless it is `Pass`
+    /// the task is already blocked.
+    fn run_land(&self, task_id: &TaskId, revision: &Revision, patch: &Patch, run_id: &RunId) -> Result<RunResult> {
+        let run_dir = self.artifacts.create_run_dir(&self.cfg.project.id, run_id)?;
         self.artifacts.append_worklog(&run_dir, &format!("## Land run {} for task {}", run_id.as_str(), task_id.as_str()))?;
 
         let mut manifest = EvidenceManifest {
             run_id: run_id.as_str().to_string(),
             task_id: task_id.as_str().to_string(),
-            revision_id: "stub".into(),
+            revision_id: revision.id.as_str().to_string(),
             lane: "land".into(),
             vcs_type: format!("{:?}", self.vcs.vcs_type()),
-            spec_hash: "stub".into(),
+            spec_hash: revision.spec_hash.clone(),
             base_rev: None,
             head_rev: None,
             gates: vec![],
         };
 
-        // Apply patch (may be empty)
-        let landed = self.vcs.apply_patch_to_repo_root(&self.repo_root, patch, "spl land")?;
-        manifest.head_rev = Some(landed);
+        let mut run_result = RunResult::Pass;
+        let mut conflict = None;
+        for gate in spl_core::land_lane_gates(revision) {
+            let started_at = self.start_gate(run_id, &gate)?;
+            let result = match gate {
+                // Apply patch (may be empty); the adapter 3-way merges a stale one onto mainline
+                GateName::Land => match self.vcs.apply_patch_to_repo_root(&self.repo_root, patch, "spl land") {
+                    Ok(landed) => {
+                        manifest.head_rev = Some(landed.clone());
+                        GateRun::pass(format!("landed: {}", landed))
+                    }
+                    Err(err) => {
+                        let found = err.downcast::<LandConflict>()?;
+                        let result = GateRun::fail(found.to_string(), conflict_md(&found));
+                        conflict = Some(found);
+                        result
+                    }
+                },
+                // post_smoke checks the landed mainline, not the (already removed) workspace
+                GateName::PostSmoke => run_smoke("post_smoke", self.smoke_commands(revision, &GateName::PostSmoke), &self.repo_root)?,
+                _ => stub_gate(&gate),
+            };
+            let failed = result.status == GateStatus::Fail;
+            self.write_gate(run_id, &run_dir, &mut manifest, gate.clone(), &result, started_at)?;
+            if failed {
+                self.artifacts.append_worklog(&run_dir, &format!("{:?}: FAIL; stopping run", gate))?;
+                run_result = match &conflict {
+                    Some(conflict) => {
+                        self.ask_land_conflict(task_id, revision, conflict)?;
+                        RunResult::BlockedHitl
+                    }
+                    None => {
+                        self.block_failure(task_id, &gate, &result)?;
+                        RunResult::FailGate
+                    }
+                };
+                break;
+            }
+        }
 
-        // post_smoke stub
-        self.write_gate(&run_id, &run_dir, &mut manifest, GateName::PostSmoke, GateStatus::Pass, EvidenceRole::PostSmoke, b"post_smoke: PASS (stub)")?;
         self.artifacts.write_manifest(&run_dir, &manifest)?;
+        Ok(run_result)
+    }
+
+    /// A conflict the 3-way merge could not resolve is a human decision: block for HITL and
+    /// ask, listing the conflicting hunks.
+    fn ask_land_conflict(&self, task_id: &TaskId, revision: &Revision, conflict: &LandConflict) -> Result<()> {
+        self.raise_ask(&AskPacket {
+            task_id: task_id.clone(),
+            decision: format!(
+                "revision {} conflicts with current mainline even after a 3-way merge. How should it land?",
+                revision.id.as_str()
+            ),
+            options: vec![
+                AskOption {
+                    label: "reset and re-run the task on current mainline".into(),
+                    action: NextAction::RetryGate { gate: GateName::Land },
+                },
+                AskOption { label: "resolve the conflict by hand and mark the task done".into(), action: NextAction::MarkDone },
+                AskOption { label: "abandon the task".into(), action: NextAction::Abandon },
+            ],
+            recommended: 0,
+            details_md: conflict_md(conflict),
+        })
+    }
+
+    /// Block the task and leave the gate's remediation where operators will see it.
+    fn block_failure(&self, task_id: &TaskId, gate: &GateName, result: &GateRun) -> Result<()> {
+        let remediation = result.remediation.clone().unwrap_or_else(|| format!("{:?} failed", gate));
+        self.storage.set_task_status(task_id, TaskStatus::BlockedFailure)?;
+        self.storage.add_message(task_id, MessageType::Update, &format!("{:?}: FAIL\n\n{}", gate, remediation), now_unix())?;
         Ok(())
     }
 
+    /// Mark `gate` as the run's current gate; returns its start time.
+    fn start_gate(&self, run_id: &RunId, gate: &GateName) -> Result<i64> {
+        self.storage.start_gate(run_id, gate)?;
+        Ok(now_unix())
+    }
+
+    fn current_gate(&self, run_id: &RunId) -> Result<Option<GateName>> {
+        let snap = self.storage.load_snapshot(now_unix())?;
+        Ok(snap.runs.into_iter().find(|r| &r.id == run_id).and_then(|r| r.current_gate))
+    }
+
     fn write_gate(
         &self,
         run_id: &RunId,
         run_dir: &Path,
         manifest: &mut EvidenceManifest,
         gate: GateName,
-        status: GateStatus,
-        role: EvidenceRole,
-        bytes: &[u8],
+        result: &GateRun,
+        started_at: i64,
     ) -> Result<()> {
         let name = format!("{:?}.txt", gate);
-        let _path = self.artifacts.write_role_bytes(run_dir, role, &name, bytes)?;
-        self.storage.record_gate_outcome(
-            run_id,
-            &GateOutcome { gate: gate.clone(), status: status.clone(), remediation: None },
-        )?;
+        let path = self.artifacts.write_role_bytes(run_dir, evidence_role(&gate), &name, &result.bytes)?;
+        self.storage.record_gate_outcome(&GateOutcomeRecord {
+            run_id: run_id.clone(),
+            outcome: GateOutcome { gate: gate.clone(), status: result.status.clone(), remediation: result.remediation.clone() },
+            started_at_unix: started_at,
+            finished_at_unix: now_unix(),
+            artifacts: vec![self.artifacts.artifact_ref(&path)?],
+        })?;
         manifest.gates.push(GateRecord {
             gate: format!("{:?}", gate),
-            status: format!("{:?}", status),
+            status: format!("{:?}", result.status),
             artifacts: vec![name],
         });
         Ok(())
     }
 }
 
+/// Key of the land item an execute item enqueues: `{task}:{rev}:execute` lands as
+/// `{task}:{rev}:land`, and a reset's re-run under its own key lands under its own too.
+fn land_key(execute_key: &str) -> String {
+    format!("{}:land", execute_key.strip_suffix(":execute").unwrap_or(execute_key))
+}
+
+/// Markdown listing of a landing conflict: each file with its conflict-marked hunks.
+fn conflict_md(conflict: &LandConflict) -> String {
+    let mut s = String::from("## Conflicts\n");
+    for file in &conflict.files {
+        s.push_str(&format!("\n### {}\n", file.path));
+        for hunk in &file.hunks {
+            s.push_str(&format!("\n```\n{}\n```\n", hunk));
+        }
+    }
+    s
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tasks::tests::{add_task_with, insert_revision};
     use tempfile::tempdir;
 
     #[test]
@@ -257,6 +702,24 @@ mod tests {
         assert_eq!(runner.cfg.project.id, repo_root.file_name().unwrap().to_str().unwrap());
     }
 
+    #[test]
+    fn storage_backend_comes_from_spl_toml() {
+        let dir = tempdir().unwrap();
+        let repo_root = dir.path().to_path_buf();
+        let mut cfg = Config::default_for_repo("p");
+        assert_eq!(cfg.storage.backend, "sqlite");
+        open_storage(&repo_root, &cfg).unwrap();
+        assert!(Config::db_path(&repo_root).exists());
+
+        cfg.storage.backend = "postgres".into();
+        let err = open_storage(&repo_root, &cfg).err().unwrap().to_string();
+        assert!(err.contains("needs storage.url"), "{}", err);
+
+        cfg.storage.backend = "mysql".into();
+        let err = open_storage(&repo_root, &cfg).err().unwrap().to_string();
+        assert!(err.contains("unknown storage.backend"), "{}", err);
+    }
+
     #[test]
     fn test_enqueue_execute() {
         let dir = tempdir().unwrap();
@@ -271,11 +734,488 @@ mod tests {
             tags: vec![],
         };
         runner.storage.insert_task(task).unwrap();
-        runner.storage.insert_spec_revision("spec1", "task1", "hash", "path", 0).unwrap();
-        runner.storage.insert_revision_row("rev1", "task1", "spec1", "hash", "default", "[]", "[]", "[]", 0).unwrap();
-        runner.enqueue_execute("task1", "rev1").unwrap();
+        insert_revision(&runner, "task1", "rev1", "test", &[]);
+        let first = runner.enqueue_execute("task1", "rev1").unwrap();
+        assert!(matches!(first, EnqueueResult::Inserted(_)));
+        let again = runner.enqueue_execute("task1", "rev1").unwrap();
+        assert_eq!(again, EnqueueResult::Existing(first.queue_id().clone()));
         let snap = runner.storage.load_snapshot(i64::MAX).unwrap();
         assert_eq!(snap.queue.len(), 1);
         assert_eq!(snap.queue[0].task_id.as_str(), "task1");
     }
+
+    /// Git repo with SPL initialized: `.spl/` ignored, mainline set to the repo's
+    /// actual default branch, artifacts kept inside the temp dir.
+    fn git_fixture() -> (tempfile::TempDir, Runner) {
+        let dir = tempdir().unwrap();
+        let repo_root = dir.path().canonicalize().unwrap();
+        spl_vcs::init_git_repo(&repo_root).unwrap();
+        std::fs::write(repo_root.join(".git").join("info").join("exclude"), ".spl/\n").unwrap();
+        Runner::init_repo(&repo_root).unwrap();
+
+        let main = crate::util::run_cmd(&repo_root, "git", &["rev-parse", "--abbrev-ref", "HEAD"]).unwrap();
+        let cfg_path = Config::config_path(&repo_root);
+        let mut cfg = Config::load_from(&cfg_path).unwrap();
+        cfg.project.main_ref = main.clone();
+        cfg.vcs.git_main_branch = Some(main);
+        cfg.project.artifact_root = repo_root.join(".spl").join("artifacts").display().to_string();
+        cfg.save_to(&cfg_path).unwrap();
+
+        let runner = Runner::open(repo_root).unwrap();
+        (dir, runner)
+    }
+
+    fn profile_gates(profile: &str) -> Vec<GateName> {
+        ProfileSet::builtin().get(profile).unwrap().gates()
+    }
+
+    fn seed_task(runner: &Runner, task: &str, gates: &[GateName]) {
+        add_task_with(runner, task, 0, &[], "test", gates);
+    }
+
+    /// Gate names recorded in the evidence manifests of a lane, across all runs.
+    fn manifest_gates(runner: &Runner, lane: &str) -> Vec<(String, String)> {
+        let project_dir = runner.artifacts.root.join(&runner.cfg.project.id);
+        let mut gates = vec![];
+        for entry in std::fs::read_dir(project_dir).unwrap() {
+            let path = entry.unwrap().path().join("evidence_manifest.json");
+            let manifest: EvidenceManifest = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+            if manifest.lane == lane {
+                gates.extend(manifest.gates.into_iter().map(|g| (g.gate, g.status)));
+            }
+        }
+        gates
+    }
+
+    fn task_status(runner: &Runner, task: &str) -> TaskStatus {
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        snap.tasks.into_iter().find(|t| t.id.as_str() == task).unwrap().status
+    }
+
+    /// run_once until no item is visible: the execute item, then the land item it enqueues.
+    fn run_until_idle(runner: &Runner) {
+        for _ in 0..10 {
+            if runner.storage.load_snapshot(now_unix()).unwrap().queue.is_empty() {
+                return;
+            }
+            runner.run_once(false).unwrap();
+        }
+        panic!("queue did not drain");
+    }
+
+    fn names(gates: &[(String, String)]) -> Vec<&str> {
+        gates.iter().map(|(g, _)| g.as_str()).collect()
+    }
+
+    #[test]
+    fn run_once_executes_standard_gate_plan() {
+        let (_dir, runner) = git_fixture();
+        let gates = [GateName::PreSmoke, GateName::Audit, GateName::AdversarialReview, GateName::Validate, GateName::PostSmoke];
+        seed_task(&runner, "t-std", &gates);
+
+        run_until_idle(&runner);
+
+        assert_eq!(names(&manifest_gates(&runner, "execute")), vec!["PreSmoke", "Audit", "AdversarialReview", "Validate"]);
+        assert_eq!(names(&manifest_gates(&runner, "land")), vec!["Land", "PostSmoke"]);
+        assert_eq!(task_status(&runner, "t-std"), TaskStatus::Done);
+    }
+
+    fn set_smoke(runner: &mut Runner, pre: &[&str], post: &[&str]) {
+        runner.cfg.commands.pre_smoke = pre.iter().map(|c| c.to_string()).collect();
+        runner.cfg.commands.post_smoke = post.iter().map(|c| c.to_string()).collect();
+    }
+
+    fn smoke_evidence(runner: &Runner, role_dir: &str, file: &str) -> String {
+        let project_dir = runner.artifacts.root.join(&runner.cfg.project.id);
+        for entry in std::fs::read_dir(project_dir).unwrap() {
+            let path = entry.unwrap().path().join(role_dir).join(file);
+            if path.exists() {
+                return std::fs::read_to_string(path).unwrap();
+            }
+        }
+        panic!("no {}/{} artifact", role_dir, file);
+    }
+
+    #[test]
+    fn pre_smoke_failure_blocks_before_landing() {
+        let (_dir, mut runner) = git_fixture();
+        set_smoke(&mut runner, &["echo checking; exit 1"], &["true"]);
+        seed_task(&runner, "t-pre", &profile_gates("standard"));
+
+        runner.run_once(false).unwrap();
+
+        assert_eq!(manifest_gates(&runner, "execute"), vec![("PreSmoke".to_string(), "Fail".to_string())]);
+        assert!(manifest_gates(&runner, "land").is_empty());
+        assert_eq!(task_status(&runner, "t-pre"), TaskStatus::BlockedFailure);
+
+        let evidence = smoke_evidence(&runner, "pre_smoke", "PreSmoke.txt");
+        assert!(evidence.contains("exit: 1"));
+        assert!(evidence.contains("checking"));
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        assert!(snap.messages.iter().any(|m| m.body_md.contains("exited with 1")));
+
+        // the run row carries the real result and its gate history points at the evidence
+        assert_eq!(snap.runs.len(), 1);
+        assert_eq!(snap.runs[0].result, Some(RunResult::FailGate));
+        assert_eq!(snap.runs[0].current_gate, Some(GateName::PreSmoke));
+        let history = runner.storage.list_gate_outcomes(&snap.runs[0].id).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].outcome.status, GateStatus::Fail);
+        assert!(history[0].started_at_unix <= history[0].finished_at_unix);
+        let stored = runner.artifacts.read_ref(&history[0].artifacts[0]).unwrap();
+        assert!(String::from_utf8(stored).unwrap().contains("checking"));
+    }
+
+    #[test]
+    fn profile_smoke_commands_replace_the_configured_ones() {
+        let (_dir, mut runner) = git_fixture();
+        set_smoke(&mut runner, &["echo from-commands"], &["exit 1"]);
+        let mut profile = runner.profiles.get("standard").unwrap().clone();
+        profile.post_smoke = Some(vec!["echo from-profile".into()]);
+        runner.profiles.define("test", profile).unwrap();
+        seed_task(&runner, "t-profile", &profile_gates("standard"));
+
+        run_until_idle(&runner);
+
+        assert_eq!(task_status(&runner, "t-profile"), TaskStatus::Done);
+        assert!(smoke_evidence(&runner, "pre_smoke", "PreSmoke.txt").contains("from-commands"));
+        assert!(smoke_evidence(&runner, "post_smoke", "PostSmoke.txt").contains("from-profile"));
+    }
+
+    #[test]
+    fn post_smoke_runs_against_landed_mainline() {
+        let (_dir, mut runner) = git_fixture();
+        set_smoke(&mut runner, &["true"], &["test -f README.md && echo post-ok"]);
+        seed_task(&runner, "t-post", &profile_gates("standard"));
+
+        run_until_idle(&runner);
+
+        assert_eq!(task_status(&runner, "t-post"), TaskStatus::Done);
+        assert!(smoke_evidence(&runner, "post_smoke", "PostSmoke.txt").contains("post-ok"));
+    }
+
+    #[test]
+    fn post_smoke_failure_blocks_task() {
+        let (_dir, mut runner) = git_fixture();
+        set_smoke(&mut runner, &["true"], &["exit 4"]);
+        seed_task(&runner, "t-post-fail", &profile_gates("standard"));
+
+        run_until_idle(&runner);
+
+        assert_eq!(
+            manifest_gates(&runner, "land"),
+            vec![("Land".to_string(), "Pass".to_string()), ("PostSmoke".to_string(), "Fail".to_string())]
+        );
+        assert_eq!(task_status(&runner, "t-post-fail"), TaskStatus::BlockedFailure);
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        let land_run = snap.runs.iter().find(|r| r.lane == Lane::Land).unwrap();
+        assert_eq!(land_run.result, Some(RunResult::FailGate));
+        assert_eq!(land_run.current_gate, Some(GateName::PostSmoke));
+    }
+
+    fn only_item(runner: &Runner, at: i64) -> Option<QueueItem> {
+        let mut queue = runner.storage.load_snapshot(at).unwrap().queue;
+        assert!(queue.len() <= 1);
+        queue.pop()
+    }
+
+    #[test]
+    fn crash_is_retried_with_backoff_then_dead_lettered() {
+        let (_dir, mut runner) = git_fixture();
+        runner.vcs = Box::new(GitAdapter::new("no-such-branch".to_string()));
+        seed_task(&runner, "t-crash", &profile_gates("standard"));
+
+        // attempt 1: immediate retry
+        runner.run_once(false).unwrap();
+        let item = only_item(&runner, now_unix()).expect("still queued");
+        assert_eq!(item.attempts, 1);
+        assert_eq!(task_status(&runner, "t-crash"), TaskStatus::Ready);
+
+        // attempt 2: backed off
+        runner.run_once(false).unwrap();
+        assert!(only_item(&runner, now_unix()).is_none());
+        let item = only_item(&runner, now_unix() + 15 * 60).expect("visible after backoff");
+        assert_eq!(item.attempts, 2);
+
+        // attempt 3 == max_attempts: dead-lettered
+        runner.storage.reschedule(&item.id, item.attempts, now_unix()).unwrap();
+        runner.run_once(false).unwrap();
+        assert!(only_item(&runner, i64::MAX).is_none());
+        assert_eq!(task_status(&runner, "t-crash"), TaskStatus::BlockedFailure);
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        assert!(snap.messages.iter().any(|m| m.body_md.contains("Crash on attempt 3/3; dead-lettered")));
+    }
+
+    #[test]
+    fn known_flake_is_retried_up_to_auto_retry_count() {
+        let (_dir, mut runner) = git_fixture();
+        set_smoke(&mut runner, &["echo connection reset by peer; exit 1"], &["true"]);
+        runner.cfg.flakes.known_signatures = vec!["connection reset by peer".to_string()];
+        runner.cfg.flakes.auto_retry_count = 1;
+        seed_task(&runner, "t-flake", &profile_gates("standard"));
+
+        runner.run_once(false).unwrap();
+        assert!(only_item(&runner, now_unix()).is_none());
+        let item = only_item(&runner, now_unix() + 5).expect("flake retry scheduled");
+        assert_eq!(item.attempts, 1);
+        assert_eq!(task_status(&runner, "t-flake"), TaskStatus::Ready);
+
+        // retry budget spent: the same failure now blocks
+        runner.storage.reschedule(&item.id, item.attempts, now_unix()).unwrap();
+        runner.run_once(false).unwrap();
+        assert!(only_item(&runner, i64::MAX).is_none());
+        assert_eq!(task_status(&runner, "t-flake"), TaskStatus::BlockedFailure);
+    }
+
+    #[test]
+    fn spec_drift_blocks_for_hitl_with_ask() {
+        let (_dir, runner) = git_fixture();
+        seed_task(&runner, "t-drift", &profile_gates("standard"));
+        let item = only_item(&runner, now_unix()).unwrap();
+
+        runner
+            .settle_failure(&item, GateName::AdversarialReview, FailureClass::SpecDrift, "acceptance criteria are ambiguous")
+            .unwrap();
+
+        assert_eq!(task_status(&runner, "t-drift"), TaskStatus::BlockedHitl);
+        assert!(only_item(&runner, i64::MAX).is_none());
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        let ask = snap.messages.iter().find(|m| m.ty == MessageType::Ask).expect("ASK emitted");
+        assert!(ask.body_md.starts_with("# ASK: t-drift"));
+        assert!(ask.body_md.contains("acceptance criteria are ambiguous"));
+        let packet = runner.open_ask_packet(&TaskId::from_str("t-drift")).unwrap();
+        assert_eq!(packet.options[packet.recommended].action, NextAction::AmendSpec);
+        assert_eq!(packet.options[1].action, NextAction::RetryGate { gate: GateName::AdversarialReview });
+    }
+
+    #[test]
+    fn expired_lease_of_dead_worker_is_recovered() {
+        let (_dir, runner) = git_fixture();
+        seed_task(&runner, "t-dead", &profile_gates("standard"));
+        let item = only_item(&runner, now_unix()).unwrap();
+
+        // a worker took the item and died without releasing or renewing its lease
+        runner.storage.try_acquire_lease(&item.id, "worker-dead", -1).unwrap().unwrap();
+        let dead_run = RunId::new();
+        runner.storage.create_run(&item.id, dead_run.clone()).unwrap();
+        runner.storage.start_gate(&dead_run, &GateName::Audit).unwrap();
+
+        run_until_idle(&runner);
+
+        assert_eq!(task_status(&runner, "t-dead"), TaskStatus::Done);
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        assert!(snap.leases.is_empty());
+        assert!(snap.messages.iter().any(|m| m.body_md.contains("Audit: Crash on attempt 1/3")
+            && m.body_md.contains("worker worker-dead stopped renewing its lease")));
+        for run in &snap.runs {
+            let expected = if run.id == dead_run { RunResult::Crash } else { RunResult::Pass };
+            assert_eq!(run.result, Some(expected));
+        }
+        assert_eq!(snap.runs.len(), 3);
+    }
+
+    fn drain() -> crate::ServeOptions {
+        crate::ServeOptions { concurrency: 2, poll_interval: std::time::Duration::from_millis(50), exit_when_idle: true }
+    }
+
+    #[test]
+    fn serve_drains_queue_with_parallel_slots() {
+        let (_dir, runner) = git_fixture();
+        for task in ["t-a", "t-b", "t-c"] {
+            seed_task(&runner, task, &profile_gates("standard"));
+        }
+
+        let report = runner.serve(&drain(), &std::sync::atomic::AtomicBool::new(false)).unwrap();
+
+        // each task: one execute run, one land run
+        assert_eq!(report, crate::ServeReport { runs: 6, errors: 0 });
+        for task in ["t-a", "t-b", "t-c"] {
+            assert_eq!(task_status(&runner, task), TaskStatus::Done);
+        }
+        let snap = runner.storage.load_snapshot(i64::MAX).unwrap();
+        assert!(snap.queue.is_empty());
+        assert!(snap.leases.is_empty());
+    }
+
+    #[test]
+    fn serve_leaves_backed_off_items_and_stops_on_shutdown() {
+        let (_dir, runner) = git_fixture();
+        seed_task(&runner, "t-later", &profile_gates("standard"));
+        let item = only_item(&runner, now_unix()).unwrap();
+        runner.storage.reschedule(&item.id, 1, now_unix() + 600).unwrap();
+
+        let report = runner.serve(&drain(), &std::sync::atomic::AtomicBool::new(false)).unwrap();
+        assert_eq!(report.runs, 0);
+        assert_eq!(only_item(&runner, now_unix() + 600).unwrap().attempts, 1);
+
+        // shutdown already requested: nothing new is leased
+        runner.storage.reschedule(&item.id, 1, now_unix()).unwrap();
+        let opts = crate::ServeOptions { exit_when_idle: false, ..drain() };
+        let report = runner.serve(&opts, &std::sync::atomic::AtomicBool::new(true)).unwrap();
+        assert_eq!(report.runs, 0);
+        assert_eq!(task_status(&runner, "t-later"), TaskStatus::Ready);
+    }
+
+    #[test]
+    fn land_item_carries_exported_patch_by_artifact_ref() {
+        let (_dir, mut runner) = git_fixture();
+        // pre_smoke runs in the workspace, so its output becomes the run's change
+        set_smoke(&mut runner, &["echo hello > hello.txt"], &["grep -q hello hello.txt"]);
+        seed_task(&runner, "t-land", &profile_gates("standard"));
+
+        runner.run_once(false).unwrap();
+        assert_eq!(task_status(&runner, "t-land"), TaskStatus::Ready);
+        assert!(!runner.repo_root.join("hello.txt").exists());
+        let land = only_item(&runner, now_unix()).expect("land item queued");
+        assert_eq!(land.lane, Lane::Land);
+        let patch = runner.artifacts.read_ref(land.artifact_ref.as_deref().unwrap()).unwrap();
+        assert!(String::from_utf8(patch).unwrap().contains("hello.txt"));
+
+        runner.run_once(false).unwrap();
+        assert_eq!(task_status(&runner, "t-land"), TaskStatus::Done);
+        assert_eq!(std::fs::read_to_string(runner.repo_root.join("hello.txt")).unwrap(), "hello\n");
+        assert!(only_item(&runner, i64::MAX).is_none());
+    }
+
+    #[test]
+    fn landing_waits_for_the_project_land_lock() {
+        let (_dir, runner) = git_fixture();
+        seed_task(&runner, "t-lock", &profile_gates("standard"));
+        runner.run_once(false).unwrap();
+
+        // another worker (or process) is landing right now
+        assert!(runner.storage.try_lock_resource("land", "worker-elsewhere", 60).unwrap());
+        assert!(runner.run_once(false).is_err());
+        let land = only_item(&runner, now_unix()).expect("land item untouched");
+        assert_eq!(land.attempts, 0);
+        assert_eq!(task_status(&runner, "t-lock"), TaskStatus::Ready);
+
+        runner.storage.release_resource("land", "worker-elsewhere").unwrap();
+        runner.run_once(false).unwrap();
+        assert_eq!(task_status(&runner, "t-lock"), TaskStatus::Done);
+    }
+
+    /// Commit `content` as `file` on the repo root's mainline.
+    fn commit_on_main(runner: &Runner, file: &str, content: &str) {
+        std::fs::write(runner.repo_root.join(file), content).unwrap();
+        crate::util::run_cmd(&runner.repo_root, "git", &["add", file]).unwrap();
+        crate::util::run_cmd(&runner.repo_root, "git", &["commit", "-m", "mainline moved"]).unwrap();
+    }
+
+    #[test]
+    fn stale_patch_is_merged_and_post_smoke_checks_the_merge() {
+        let (_dir, mut runner) = git_fixture();
+        commit_on_main(&runner, "notes.txt", "a\nb\nc\nd\ne\n");
+        set_smoke(&mut runner, &["sed -i s/^b$/B/ notes.txt"], &["grep -qx B notes.txt", "grep -qx D notes.txt"]);
+        seed_task(&runner, "t-merge", &profile_gates("standard"));
+        runner.run_once(false).unwrap();
+
+        // mainline moves inside the patch's context before the land item runs
+        commit_on_main(&runner, "notes.txt", "a\nb\nc\nD\ne\n");
+        runner.run_once(false).unwrap();
+
+        assert_eq!(task_status(&runner, "t-merge"), TaskStatus::Done);
+        assert_eq!(std::fs::read_to_string(runner.repo_root.join("notes.txt")).unwrap(), "a\nB\nc\nD\ne\n");
+        assert_eq!(manifest_gates(&runner, "land"), vec![("Land".into(), "Pass".into()), ("PostSmoke".into(), "Pass".into())]);
+    }
+
+    #[test]
+    fn unresolvable_land_conflict_blocks_hitl_with_conflict_ask() {
+        let (_dir, mut runner) = git_fixture();
+        set_smoke(&mut runner, &["echo from-task > README.md"], &[]);
+        seed_task(&runner, "t-conflict", &profile_gates("standard"));
+        runner.run_once(false).unwrap();
+
+        commit_on_main(&runner, "README.md", "from-main\n");
+        runner.run_once(false).unwrap();
+
+        assert_eq!(task_status(&runner, "t-conflict"), TaskStatus::BlockedHitl);
+        assert!(only_item(&runner, i64::MAX).is_none());
+        assert_eq!(names(&manifest_gates(&runner, "land")), vec!["Land"]);
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        let ask = snap.messages.iter().find(|m| m.ty == MessageType::Ask).expect("ASK emitted");
+        assert!(ask.body_md.starts_with("# ASK: t-conflict"));
+        assert!(ask.body_md.contains("### README.md"));
+        assert!(ask.body_md.contains("<<<<<<<") && ask.body_md.contains("from-task") && ask.body_md.contains("from-main"));
+        // the failed merge left nothing behind on mainline
+        assert_eq!(std::fs::read_to_string(runner.repo_root.join("README.md")).unwrap(), "from-main\n");
+        assert!(runner.vcs.repo_root_is_clean(&runner.repo_root).unwrap());
+    }
+
+    #[test]
+    fn validate_blocks_hitl_naming_the_changed_export() {
+        let (_dir, mut runner) = git_fixture();
+        std::fs::create_dir_all(runner.repo_root.join("src")).unwrap();
+        commit_on_main(&runner, "Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\n");
+        commit_on_main(&runner, "src/lib.rs", "pub fn greet() {}\n");
+        set_smoke(&mut runner, &["sed -i 's/greet()/greet(name: \\&str)/' src/lib.rs"], &[]);
+        seed_task(&runner, "t-api", &profile_gates("standard"));
+        runner.run_once(false).unwrap();
+
+        assert_eq!(task_status(&runner, "t-api"), TaskStatus::BlockedHitl);
+        assert_eq!(manifest_gates(&runner, "execute").last(), Some(&("Validate".into(), "Fail".into())));
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        let ask = snap.messages.iter().find(|m| m.ty == MessageType::Ask).expect("ASK emitted");
+        assert!(ask.body_md.contains("exported rust://app::greet signature changed"), "{}", ask.body_md);
+        let evidence = smoke_evidence(&runner, "validate", "Validate.txt");
+        assert!(evidence.contains("\"anchor\": \"rust://app::greet\""), "{}", evidence);
+        assert_eq!(snap.runs.iter().map(|r| r.result.clone()).collect::<Vec<_>>(), vec![Some(RunResult::BlockedHitl)]);
+    }
+
+    #[test]
+    fn validate_diffs_against_the_base_rev_not_the_live_checkout() {
+        let (_dir, mut runner) = git_fixture();
+        std::fs::create_dir_all(runner.repo_root.join("src")).unwrap();
+        commit_on_main(&runner, "Cargo.toml", "[package]\nname = \"app\"\nversion = \"0.1.0\"\n");
+        commit_on_main(&runner, "src/lib.rs", "pub fn greet() {}\n");
+        // mainline changes the export while the run is in flight; the task's base does not
+        let root = runner.repo_root.display().to_string();
+        let advance = format!("echo 'pub fn greet(name: &str) {{}}' > {}/src/lib.rs && git -C {} commit -qam 'mainline moved'", root, root);
+        set_smoke(&mut runner, &[&advance], &[]);
+        seed_task(&runner, "t-base", &[GateName::PreSmoke, GateName::Validate]);
+        runner.run_once(false).unwrap();
+
+        assert_eq!(manifest_gates(&runner, "execute"), vec![("PreSmoke".into(), "Pass".into()), ("Validate".into(), "Pass".into())]);
+    }
+
+    #[test]
+    fn reset_after_land_conflict_reruns_on_current_mainline_and_lands() {
+        let (_dir, mut runner) = git_fixture();
+        set_smoke(&mut runner, &["echo from-task > README.md"], &[]);
+        seed_task(&runner, "t-reset", &profile_gates("standard"));
+        runner.run_once(false).unwrap();
+        commit_on_main(&runner, "README.md", "from-main\n");
+        runner.run_once(false).unwrap();
+        assert_eq!(task_status(&runner, "t-reset"), TaskStatus::BlockedHitl);
+
+        runner.reset_task(&TaskId::from_str("t-reset"), "re-run on current mainline").unwrap().expect("execute re-queued");
+        run_until_idle(&runner);
+
+        // the re-run lands under its own key instead of finding the conflicted land item
+        assert_eq!(task_status(&runner, "t-reset"), TaskStatus::Done);
+        assert_eq!(std::fs::read_to_string(runner.repo_root.join("README.md")).unwrap(), "from-task\n");
+        let keys: Vec<String> = runner
+            .storage
+            .list_queue_items_for_task(&TaskId::from_str("t-reset"))
+            .unwrap()
+            .into_iter()
+            .map(|r| r.item.idempotency_key)
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["t-reset:t-reset-rev:execute", "t-reset:t-reset-rev:land", "t-reset:t-reset-rev:reset1:execute", "t-reset:t-reset-rev:reset1:land"]
+        );
+    }
+
+    #[test]
+    fn run_once_runs_only_the_docs_profile_gates() {
+        let (_dir, runner) = git_fixture();
+        seed_task(&runner, "t-docs", &profile_gates("docs"));
+
+        run_until_idle(&runner);
+
+        assert_eq!(names(&manifest_gates(&runner, "execute")), vec!["Audit", "AdversarialReview"]);
+        assert_eq!(names(&manifest_gates(&runner, "land")), vec!["Land"]);
+        assert_eq!(task_status(&runner, "t-docs"), TaskStatus::Done);
+    }
 }
@@ -52,8 +52,10 @@ pub fn load_expected(dir: &Path) -> Result<ScenarioExpected> {
 /// These scenario tests are about SPL semantics and fixture ingestion.
 pub fn simulate(dir: &Path, _vcs: VcsType) -> Result<ScenarioResult> {
     let spec_path = dir.join("spec_pack.yaml");
-    let pack = spl_spec::load_spec_pack(&spec_path)?;
-    let draft = spl_spec::compile_revision_draft(&pack);
+    // fixtures only use the built-in profiles and name no anchors
+    let profiles = spl_spec::ProfileSet::builtin();
+    let pack = spl_spec::load_spec_pack(&spec_path, &profiles)?;
+    let draft = spl_spec::compile_revision_draft(&pack, &profiles, &spl_index::SimpleIndex::default())?;
 
     // Gate list comes from spec pack required list when present; else profile default.
     // Use canonical spec names for the fallback (not Debug formatting).
@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use spl_core::Lane;
+
+use crate::Runner;
+
+#[derive(Clone, Debug)]
+pub struct ServeOptions {
+    /// Execute-lane slots; each runs one item at a time in its own workspace.
+    /// A single land-lane slot always runs alongside them.
+    pub concurrency: usize,
+    /// How long an idle slot waits before polling the queue again.
+    pub poll_interval: Duration,
+    /// Return once nothing is visible and nothing is in flight (drain mode, CI).
+    pub exit_when_idle: bool,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self { concurrency: 1, poll_interval: Duration::from_secs(5), exit_when_idle: false }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServeReport {
+    /// Items leased and run to a settled state (pass, retry, block or dead-letter).
+    pub runs: usize,
+    /// Poll or run attempts that returned an error; the slot keeps going.
+    pub errors: usize,
+}
+
+struct Counters {
+    in_flight: AtomicUsize,
+    runs: AtomicUsize,
+    errors: AtomicUsize,
+}
+
+impl Runner {
+    /// Long-running worker: poll the queue and run up to `concurrency` execute items in
+    /// parallel, plus one land slot, until `shutdown` is set (or the queue drains, with
+    /// `exit_when_idle`). Landing is further serialized per project by the storage land lock.
+    ///
+    /// Only visible items are picked, so backoff set by retries is respected. After
+    /// shutdown is requested no new item is leased; in-flight runs finish and release
+    /// their leases before this returns.
+    pub fn serve(&self, opts: &ServeOptions, shutdown: &AtomicBool) -> Result<ServeReport> {
+        self.doctor()?;
+        let concurrency = opts.concurrency.max(1);
+        let mut slots = (0..concurrency).map(|n| Ok((self.fork(n)?, Lane::Execute))).collect::<Result<Vec<_>>>()?;
+        slots.push((self.fork(concurrency)?, Lane::Land));
+        let counters = Counters { in_flight: AtomicUsize::new(0), runs: AtomicUsize::new(0), errors: AtomicUsize::new(0) };
+
+        std::thread::scope(|scope| {
+            for (slot, lane) in &slots {
+                let counters = &counters;
+                scope.spawn(move || slot.serve_slot(lane, opts, shutdown, counters));
+            }
+        });
+
+        Ok(ServeReport { runs: counters.runs.into_inner(), errors: counters.errors.into_inner() })
+    }
+
+    fn serve_slot(&self, lane: &Lane, opts: &ServeOptions, shutdown: &AtomicBool, counters: &Counters) {
+        while !shutdown.load(Ordering::SeqCst) {
+            let ran = match self.serve_tick(lane, counters) {
+                Ok(ran) => ran,
+                Err(err) => {
+                    counters.errors.fetch_add(1, Ordering::SeqCst);
+                    eprintln!("{}: {:#}", self.worker_id, err);
+                    false
+                }
+            };
+            if ran {
+                counters.runs.fetch_add(1, Ordering::SeqCst);
+                continue;
+            }
+            // in_flight first: a run enqueues follow-up work (its land item) before it stops counting
+            if opts.exit_when_idle && counters.in_flight.load(Ordering::SeqCst) == 0 && self.queue_is_idle() {
+                return;
+            }
+            sleep_unless_shutdown(opts.poll_interval, shutdown);
+        }
+    }
+
+    fn queue_is_idle(&self) -> bool {
+        self.storage.load_snapshot(crate::now_unix()).map(|snap| snap.queue.is_empty()).unwrap_or(false)
+    }
+
+    /// One poll: reclaim expired leases, then run the first visible `lane` item nobody holds.
+    /// Returns whether an item was run.
+    fn serve_tick(&self, lane: &Lane, counters: &Counters) -> Result<bool> {
+        self.recover_expired_leases()?;
+        for item in self.visible_items(lane.clone())? {
+            counters.in_flight.fetch_add(1, Ordering::SeqCst);
+            let ran = self.run_item(&item);
+            counters.in_flight.fetch_sub(1, Ordering::SeqCst);
+            if ran? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn sleep_unless_shutdown(total: Duration, shutdown: &AtomicBool) {
+    let deadline = Instant::now() + total;
+    while !shutdown.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        std::thread::sleep((deadline - now).min(Duration::from_millis(100)));
+    }
+}
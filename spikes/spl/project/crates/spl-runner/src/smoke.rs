@@ -0,0 +1,86 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+
+use crate::gates::GateRun;
+
+/// Run smoke commands (`sh -c <cmd>`) in `dir`, in order, stopping at the first
+/// non-zero exit. Every executed command contributes its exit code, duration and
+/// captured stdout/stderr to the evidence bytes.
+///
+/// Each command runs in its own process group, so a Ctrl-C at the worker's terminal (which
+/// `worker-serve` answers by letting in-flight runs finish) does not kill it mid-gate.
+///
+/// Failing to spawn the shell at all is an `Err` (runner crash), not a gate FAIL.
+pub fn run_smoke(gate_label: &str, commands: &[String], dir: &Path) -> Result<GateRun> {
+    let mut evidence = String::new();
+    if commands.is_empty() {
+        evidence.push_str(&format!("{}: no commands configured\n", gate_label));
+        return Ok(GateRun::pass(evidence));
+    }
+
+    for cmd in commands {
+        let started = Instant::now();
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]).current_dir(dir);
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let out = command.output().with_context(|| format!("spawn {} command `{}`", gate_label, cmd))?;
+        let elapsed = started.elapsed();
+
+        let exit = out.status.code().map(|c| c.to_string()).unwrap_or_else(|| "signal".to_string());
+        evidence.push_str(&format!("$ {}\n", cmd));
+        evidence.push_str(&format!("exit: {}\n", exit));
+        evidence.push_str(&format!("duration_ms: {}\n", elapsed.as_millis()));
+        evidence.push_str("--- stdout ---\n");
+        evidence.push_str(&String::from_utf8_lossy(&out.stdout));
+        evidence.push_str("--- stderr ---\n");
+        evidence.push_str(&String::from_utf8_lossy(&out.stderr));
+        evidence.push('\n');
+
+        if !out.status.success() {
+            let remediation = format!(
+                "{} command `{}` exited with {} in {}; fix the failure shown in the {} evidence and re-run",
+                gate_label,
+                cmd,
+                exit,
+                dir.display(),
+                gate_label
+            );
+            return Ok(GateRun::fail(remediation, evidence));
+        }
+    }
+
+    Ok(GateRun::pass(evidence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_core::GateStatus;
+    use tempfile::tempdir;
+
+    #[test]
+    fn captures_output_and_stops_at_first_failure() {
+        let dir = tempdir().unwrap();
+        let cmds = vec!["echo ok".to_string(), "echo boom >&2; exit 3".to_string(), "echo never".to_string()];
+        let res = run_smoke("pre_smoke", &cmds, dir.path()).unwrap();
+
+        assert_eq!(res.status, GateStatus::Fail);
+        assert!(res.remediation.unwrap().contains("exited with 3"));
+        let text = String::from_utf8(res.bytes).unwrap();
+        assert!(text.contains("$ echo ok\nexit: 0"));
+        assert!(text.contains("boom"));
+        assert!(!text.contains("never"));
+    }
+
+    #[test]
+    fn runs_in_given_directory() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("marker.txt"), "x").unwrap();
+        let res = run_smoke("post_smoke", &["test -f marker.txt".to_string()], dir.path()).unwrap();
+        assert_eq!(res.status, GateStatus::Pass);
+    }
+}
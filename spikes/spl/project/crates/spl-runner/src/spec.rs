@@ -0,0 +1,249 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use spl_core::{MessageType, QueueId, Revision, RevisionId, SpecRevId, SpecRevision, TaskId};
+use spl_index::{default_index_path, index_repo, SimpleIndex};
+use spl_spec::{compile_revision_draft, diff_spec_packs, load_spec_pack, validate_spec_pack, SpecDiff, SpecPack};
+
+use crate::{util::now_unix, Config, Runner};
+
+/// A spec revision and the revision compiled from it.
+#[derive(Clone, Debug)]
+pub struct CompiledSpec {
+    pub spec_rev: SpecRevision,
+    pub revision: Revision,
+}
+
+/// What `spl spec-amend` did.
+#[derive(Clone, Debug)]
+pub struct SpecAmendment {
+    pub compiled: CompiledSpec,
+    /// From the superseded spec revision to the new one.
+    pub diff: SpecDiff,
+    /// Queued items of superseded revisions that were dropped.
+    pub cancelled: Vec<QueueId>,
+}
+
+impl Runner {
+    /// Compile the spec pack at `path` for `task_id` into a new spec revision and revision.
+    /// `profile` overrides the pack's own; either must be one of `self.profiles`. The pack is
+    /// kept under `.spl/specs/<task>/` named by its hash, and the new spec revision is chained
+    /// to the task's latest one.
+    pub fn compile_spec(&self, task_id: &TaskId, path: &Path, profile: Option<&str>) -> Result<CompiledSpec> {
+        let mut pack = load_spec_pack(path, &self.profiles)?;
+        if let Some(profile) = profile {
+            pack.profile = Some(profile.to_string());
+        }
+        let parent = self.storage.list_spec_revisions_for_task(task_id)?.pop();
+        self.store_compiled(task_id, &pack, parent.map(|p| p.id))
+    }
+
+    /// Replace the task's spec with the pack at `path`: compile it as a child of the latest
+    /// spec revision, diff it against that one and drop the work still queued for older
+    /// revisions. Without `profile` (or one in the pack) the superseded revision's is kept.
+    /// The new revision is not enqueued.
+    pub fn amend_spec(&self, task_id: &TaskId, path: &Path, profile: Option<&str>) -> Result<SpecAmendment> {
+        let parent = self
+            .storage
+            .list_spec_revisions_for_task(task_id)?
+            .pop()
+            .ok_or_else(|| anyhow!("task {} has no compiled spec to amend; use spec-compile", task_id.as_str()))?;
+        // checked when it was compiled; its profile may have been dropped from the config since
+        let old_pack: SpecPack = std::fs::read_to_string(&parent.spec_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|s| Ok(serde_yaml::from_str(&s)?))
+            .with_context(|| format!("load spec revision {} of task {}", parent.id.as_str(), task_id.as_str()))?;
+
+        let mut pack = load_spec_pack(path, &self.profiles)?;
+        if let Some(profile) = profile {
+            pack.profile = Some(profile.to_string());
+        }
+        if pack.profile.is_none() {
+            let revisions = self.storage.list_revisions_for_task(task_id)?;
+            pack.profile = revisions.iter().rev().find(|r| r.spec_rev_id == parent.id).map(|r| r.profile.clone());
+        }
+        if spl_spec::spec_hash(&pack) == parent.spec_hash {
+            bail!("spec of task {} is unchanged (spec revision {})", task_id.as_str(), parent.id.as_str());
+        }
+
+        let superseded = self.unfinished_items_unless_leased(task_id)?;
+        let compiled = self.store_compiled(task_id, &pack, Some(parent.id.clone()))?;
+        let now = now_unix();
+        let reason = format!("superseded by {}", compiled.spec_rev.id.as_str());
+        for record in &superseded {
+            self.storage.cancel_queue_item(&record.item.id, &reason, now)?;
+        }
+
+        let diff = diff_spec_packs(&old_pack, &pack);
+        let mut body = format!(
+            "Spec amended: {} -> {} (revision {}).\n\n{}",
+            parent.id.as_str(),
+            compiled.spec_rev.id.as_str(),
+            compiled.revision.id.as_str(),
+            diff.to_markdown()
+        );
+        if !superseded.is_empty() {
+            let ids: Vec<&str> = superseded.iter().map(|r| r.item.id.as_str()).collect();
+            body.push_str(&format!("\nDropped superseded queue items: {}.\n", ids.join(", ")));
+        }
+        self.storage.add_message_with_payload(task_id, MessageType::Update, &body, Some(&serde_json::to_string(&diff)?), now)?;
+
+        Ok(SpecAmendment { compiled, diff, cancelled: superseded.into_iter().map(|r| r.item.id).collect() })
+    }
+
+    fn store_compiled(&self, task_id: &TaskId, pack: &SpecPack, parent_id: Option<SpecRevId>) -> Result<CompiledSpec> {
+        validate_spec_pack(pack, &self.profiles)?;
+        let cache = SimpleIndex::load(&default_index_path(&self.repo_root)).unwrap_or_default();
+        let index = index_repo(&self.repo_root, &cache).context("index the repo to resolve spec anchors")?;
+        let draft = compile_revision_draft(pack, &self.profiles, &index)?;
+
+        // one file per spec version, never rewritten: earlier revisions keep pointing at theirs
+        let specs_dir = Config::specs_dir(&self.repo_root, task_id.as_str());
+        std::fs::create_dir_all(&specs_dir)?;
+        let dest = specs_dir.join(format!("{}.yaml", draft.spec_hash));
+        if !dest.exists() {
+            std::fs::write(&dest, serde_yaml::to_string(pack)?).with_context(|| format!("write {}", dest.display()))?;
+        }
+
+        let now = now_unix();
+        let spec_rev = SpecRevision {
+            id: SpecRevId::new(),
+            task_id: task_id.clone(),
+            spec_hash: draft.spec_hash.clone(),
+            spec_path: dest.display().to_string(),
+            parent_id,
+        };
+        self.storage.insert_spec_revision(&spec_rev, now)?;
+        let revision = Revision {
+            id: RevisionId::new(),
+            task_id: task_id.clone(),
+            spec_rev_id: spec_rev.id.clone(),
+            spec_hash: draft.spec_hash,
+            profile: draft.profile,
+            required_gates: draft.required_gates,
+            required_validations: draft.required_validations,
+            anchors: draft.anchors,
+        };
+        self.storage.insert_revision(&revision, now)?;
+        Ok(CompiledSpec { spec_rev, revision })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::tests::{insert_task, runner};
+    use spl_storage::QueueFinish;
+
+    fn write_spec(dir: &Path, use_cases: &[&str]) -> std::path::PathBuf {
+        let mut yaml = String::from("task: t\nintent: do thing\nacceptance:\n  tests: [\"cargo test\"]\nuse_cases:\n");
+        for id in use_cases {
+            yaml.push_str(&format!("  - id: {}\n    actor: Owner\n    steps: [\"run it\"]\n", id));
+        }
+        let path = dir.join("spec.yaml");
+        std::fs::write(&path, yaml).unwrap();
+        path
+    }
+
+    #[test]
+    fn compiles_chain_to_the_previous_spec_revision() {
+        let (dir, runner) = runner();
+        let task_id = insert_task(&runner, "t", 0, &[]);
+        let path = write_spec(dir.path(), &["UC-1"]);
+
+        let first = runner.compile_spec(&task_id, &path, Some("standard")).unwrap();
+        let second = runner.compile_spec(&task_id, &path, Some("docs")).unwrap();
+
+        assert_eq!(first.spec_rev.parent_id, None);
+        assert_eq!(second.spec_rev.parent_id, Some(first.spec_rev.id.clone()));
+        assert_eq!(second.revision.profile, "docs");
+        let stored = runner.storage.list_spec_revisions_for_task(&task_id).unwrap();
+        assert_eq!(stored.iter().map(|s| s.parent_id.clone()).collect::<Vec<_>>(), vec![None, Some(first.spec_rev.id)]);
+    }
+
+    #[test]
+    fn amend_diffs_keeps_both_versions_and_drops_superseded_work() {
+        let (dir, runner) = runner();
+        let task_id = insert_task(&runner, "t", 0, &[]);
+        let old = runner.compile_spec(&task_id, &write_spec(dir.path(), &["UC-1"]), Some("docs")).unwrap();
+        let queued = runner.enqueue_execute("t", old.revision.id.as_str()).unwrap();
+
+        let amended = runner.amend_spec(&task_id, &write_spec(dir.path(), &["UC-1", "UC-2"]), None).unwrap();
+
+        assert_eq!(amended.compiled.spec_rev.parent_id, Some(old.spec_rev.id.clone()));
+        assert_eq!(amended.compiled.revision.profile, "docs");
+        assert_eq!(amended.diff.use_cases.added, vec!["UC-2"]);
+        assert_eq!(amended.cancelled, vec![queued.queue_id().clone()]);
+        let reason = format!("superseded by {}", amended.compiled.spec_rev.id.as_str());
+        let finishes: Vec<_> = runner.storage.list_queue_items_for_task(&task_id).unwrap().into_iter().map(|r| r.finished).collect();
+        assert!(matches!(&finishes[..], [Some(QueueFinish::Cancelled { reason: r, .. })] if *r == reason), "{:?}", finishes);
+        for spec in [&old.spec_rev, &amended.compiled.spec_rev] {
+            assert_eq!(spl_spec::spec_hash(&load_spec_pack(Path::new(&spec.spec_path), &runner.profiles).unwrap()), spec.spec_hash);
+        }
+        let snap = runner.storage.load_snapshot(now_unix()).unwrap();
+        let update = snap.messages.iter().rfind(|m| m.ty == MessageType::Update).unwrap();
+        assert!(update.body_md.contains("use_cases: added UC-2"), "{}", update.body_md);
+        let payload: SpecDiff = serde_json::from_str(update.payload_json.as_deref().unwrap()).unwrap();
+        assert_eq!(payload, amended.diff);
+    }
+
+    #[test]
+    fn revisions_take_gates_and_validations_from_configured_profiles() {
+        let (dir, runner) = runner();
+        let task_id = insert_task(&runner, "t", 0, &[]);
+        let path = write_spec(dir.path(), &["UC-1"]);
+        let profiles_dir = Config::profiles_dir(dir.path());
+        std::fs::create_dir_all(&profiles_dir).unwrap();
+        std::fs::write(profiles_dir.join("lite.toml"), "required_gates = [\"audit\"]\nrequired_validations = [\"cargo test\"]\n").unwrap();
+        let runner = Runner::open(dir.path().to_path_buf()).unwrap();
+
+        let compiled = runner.compile_spec(&task_id, &path, Some("lite")).unwrap();
+        assert_eq!(compiled.revision.required_gates, vec![spl_core::GateName::Audit]);
+        assert_eq!(compiled.revision.required_validations, vec!["cargo test"]);
+        let err = runner.compile_spec(&task_id, &path, Some("standrad")).unwrap_err().to_string();
+        assert!(err.contains("unknown profile \"standrad\""), "{}", err);
+
+        let mut cfg = runner.cfg.clone();
+        cfg.profiles.insert("lite".into(), runner.profiles.get("lite").unwrap().clone());
+        let err = cfg.profiles(dir.path()).unwrap_err().to_string();
+        assert!(err.contains("defined in both"), "{}", err);
+    }
+
+    #[test]
+    fn compile_pins_anchors_in_the_repo_and_rejects_missing_ones() {
+        let (dir, runner) = runner();
+        let task_id = insert_task(&runner, "t", 0, &[]);
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\n").unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "pub fn login(user: &str) -> bool { !user.is_empty() }\n").unwrap();
+        let path = write_spec(dir.path(), &["UC-1"]);
+        let with_anchor = |anchor: &str| {
+            let yaml = std::fs::read_to_string(&path).unwrap();
+            std::fs::write(&path, format!("{}behavior_contracts:\n  - id: BC-1\n    anchor: \"{}\"\n", yaml, anchor)).unwrap();
+        };
+
+        with_anchor("rust://crate::login#fn(user: &str) -> bool");
+        let compiled = runner.compile_spec(&task_id, &path, None).unwrap();
+        let hash = spl_index::index_rust_sources(dir.path()).unwrap().anchors["rust://app::login"].clone();
+        assert_eq!(compiled.revision.anchors, vec![spl_core::AnchorId::from_str(format!("rust://app::login#{}", hash))]);
+
+        write_spec(dir.path(), &["UC-1"]);
+        with_anchor("rust://crate::logout");
+        let err = runner.compile_spec(&task_id, &path, None).unwrap_err().to_string();
+        assert!(err.contains("\"rust://crate::logout\" of \"BC-1\" is not in the index"), "{}", err);
+        assert_eq!(runner.storage.list_spec_revisions_for_task(&task_id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn amend_needs_a_compiled_and_changed_spec() {
+        let (dir, runner) = runner();
+        let task_id = insert_task(&runner, "t", 0, &[]);
+        let path = write_spec(dir.path(), &["UC-1"]);
+
+        let err = runner.amend_spec(&task_id, &path, None).unwrap_err().to_string();
+        assert!(err.contains("use spec-compile"), "{}", err);
+        runner.compile_spec(&task_id, &path, Some("standard")).unwrap();
+        let err = runner.amend_spec(&task_id, &path, None).unwrap_err().to_string();
+        assert!(err.contains("unchanged"), "{}", err);
+    }
+}
@@ -0,0 +1,303 @@
+use anyhow::{anyhow, bail, Result};
+use spl_core::{
+    task_is_cancellable, task_is_resettable, GateOutcomeRecord, Lane, Message, MessageType, Revision, Run, Task, TaskId, TaskStatus,
+};
+use spl_storage::QueueItemRecord;
+
+use crate::{util::now_unix, Runner};
+
+/// Everything recorded about one task, for `spl task-show`.
+#[derive(Clone, Debug)]
+pub struct TaskDetail {
+    pub task: Task,
+    /// Oldest first.
+    pub revisions: Vec<Revision>,
+    /// Finished items included, oldest first.
+    pub queue: Vec<QueueItemRecord>,
+    /// Each run with its gate history.
+    pub runs: Vec<(Run, Vec<GateOutcomeRecord>)>,
+    pub messages: Vec<Message>,
+}
+
+impl Runner {
+    pub fn task(&self, task_id: &TaskId) -> Result<Task> {
+        let snap = self.storage.load_snapshot(now_unix())?;
+        snap.tasks.into_iter().find(|t| &t.id == task_id).ok_or_else(|| anyhow!("no task {}", task_id.as_str()))
+    }
+
+    /// Tasks with `status` and `tag` (when given), highest priority first, then by id.
+    pub fn list_tasks(&self, status: Option<&TaskStatus>, tag: Option<&str>) -> Result<Vec<Task>> {
+        let snap = self.storage.load_snapshot(now_unix())?;
+        let mut tasks: Vec<Task> = snap
+            .tasks
+            .into_iter()
+            .filter(|t| status.is_none_or(|s| &t.status == s))
+            .filter(|t| tag.is_none_or(|tag| t.tags.iter().any(|t| t == tag)))
+            .collect();
+        tasks.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.id.as_str().cmp(b.id.as_str())));
+        Ok(tasks)
+    }
+
+    pub fn task_detail(&self, task_id: &TaskId) -> Result<TaskDetail> {
+        let snap = self.storage.load_snapshot(now_unix())?;
+        let task = snap.tasks.into_iter().find(|t| &t.id == task_id).ok_or_else(|| anyhow!("no task {}", task_id.as_str()))?;
+        let runs = snap
+            .runs
+            .into_iter()
+            .filter(|r| &r.task_id == task_id)
+            .map(|r| {
+                let gates = self.storage.list_gate_outcomes(&r.id)?;
+                Ok((r, gates))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(TaskDetail {
+            task,
+            revisions: self.storage.list_revisions_for_task(task_id)?,
+            queue: self.storage.list_queue_items_for_task(task_id)?,
+            runs,
+            messages: snap.messages.into_iter().filter(|m| &m.task_id == task_id).collect(),
+        })
+    }
+
+    /// New priority for the task and for its work still waiting in the queue.
+    pub fn set_task_priority(&self, task_id: &TaskId, priority: i32) -> Result<()> {
+        self.task(task_id)?;
+        self.storage.set_task_priority(task_id, priority)
+    }
+
+    /// Add and remove tags; returns the task's tags afterwards, in the order they were added.
+    pub fn tag_task(&self, task_id: &TaskId, add: &[String], remove: &[String]) -> Result<Vec<String>> {
+        let mut tags = self.task(task_id)?.tags;
+        tags.retain(|t| !remove.contains(t));
+        for tag in add {
+            if !tags.contains(tag) && !remove.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        self.storage.set_task_tags(task_id, &tags)?;
+        Ok(tags)
+    }
+
+    /// Move a blocked task back to `Ready` and record why. When nothing of the task is left
+    /// in the queue (the usual case after a block), its latest revision's execute lane is
+    /// queued again under a fresh key. Returns the re-queued item's id, if any.
+    pub fn reset_task(&self, task_id: &TaskId, note: &str) -> Result<Option<String>> {
+        let task = self.task(task_id)?;
+        if !task_is_resettable(&task.status) {
+            bail!("task {} is {:?}; only blocked tasks can be reset", task_id.as_str(), task.status);
+        }
+        let requeued = self.requeue_latest_revision(task_id)?;
+        self.storage.set_task_status(task_id, TaskStatus::Ready)?;
+        let mut body = format!("Reset from {:?} to Ready.", task.status);
+        if let Some(id) = &requeued {
+            body.push_str(&format!(" Re-queued execute as {}.", id));
+        }
+        if !note.is_empty() {
+            body.push_str(&format!("\n\n{}", note));
+        }
+        self.storage.add_message(task_id, MessageType::Reset, &body, now_unix())?;
+        Ok(requeued)
+    }
+
+    /// Queue the latest revision's execute lane again under a fresh key, unless some of the
+    /// task's work is still queued. Returns the new item's id.
+    pub(crate) fn requeue_latest_revision(&self, task_id: &TaskId) -> Result<Option<String>> {
+        let items = self.storage.list_queue_items_for_task(task_id)?;
+        match self.storage.list_revisions_for_task(task_id)?.last() {
+            Some(rev) if items.iter().all(|r| r.finished.is_some()) => {
+                let runs = items.iter().filter(|r| r.item.revision_id == rev.id && r.item.lane == Lane::Execute).count();
+                let key = format!("{}:{}:reset{}:execute", task_id.as_str(), rev.id.as_str(), runs);
+                Ok(Some(self.enqueue_execute_as(task_id.as_str(), rev.id.as_str(), key)?.queue_id().as_str().to_string()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The task's items still in the queue; errors if a worker holds a live lease on one, so
+    /// callers about to drop them never pull work out from under a run.
+    pub(crate) fn unfinished_items_unless_leased(&self, task_id: &TaskId) -> Result<Vec<QueueItemRecord>> {
+        let now = now_unix();
+        let queued: Vec<_> = self.storage.list_queue_items_for_task(task_id)?.into_iter().filter(|r| r.finished.is_none()).collect();
+        let leases = self.storage.load_snapshot(now)?.leases;
+        if let Some(lease) = leases.iter().find(|l| l.expires_at_unix > now && queued.iter().any(|r| r.item.id == l.queue_id)) {
+            bail!(
+                "task {} is running ({} holds queue item {}); retry once it settles",
+                task_id.as_str(),
+                lease.worker_id,
+                lease.queue_id.as_str()
+            );
+        }
+        Ok(queued)
+    }
+
+    /// Cancel a task that is not finished: its queued items leave the queue and it becomes
+    /// `Cancelled`. Refused while a worker holds a lease on one of its items.
+    pub fn cancel_task(&self, task_id: &TaskId, note: &str) -> Result<()> {
+        let task = self.task(task_id)?;
+        if !task_is_cancellable(&task.status) {
+            bail!("task {} is already {:?}", task_id.as_str(), task.status);
+        }
+        let now = now_unix();
+        let queued = self.unfinished_items_unless_leased(task_id)?;
+        for record in &queued {
+            self.storage.cancel_queue_item(&record.item.id, "task cancelled", now)?;
+        }
+        self.storage.set_task_status(task_id, TaskStatus::Cancelled)?;
+        let mut body = format!("Cancelled from {:?}.", task.status);
+        if !note.is_empty() {
+            body.push_str(&format!("\n\n{}", note));
+        }
+        self.storage.add_message(task_id, MessageType::Update, &body, now)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use spl_core::{GateName, QueueId, RevisionId, SpecRevId, SpecRevision};
+    use spl_storage::QueueFinish;
+    use tempfile::tempdir;
+
+    pub(crate) fn runner() -> (tempfile::TempDir, Runner) {
+        let dir = tempdir().unwrap();
+        Runner::init_repo(dir.path()).unwrap();
+        let runner = Runner::open(dir.path().to_path_buf()).unwrap();
+        (dir, runner)
+    }
+
+    /// Task `id` with revision `{id}-rev` (no gates), its execute item queued.
+    pub(crate) fn add_task(runner: &Runner, id: &str, priority: i32, tags: &[&str]) -> QueueId {
+        add_task_with(runner, id, priority, tags, "standard", &[])
+    }
+
+    /// `add_task` whose revision has `profile` and requires `gates`.
+    pub(crate) fn add_task_with(runner: &Runner, id: &str, priority: i32, tags: &[&str], profile: &str, gates: &[GateName]) -> QueueId {
+        insert_task(runner, id, priority, tags);
+        let rev = format!("{}-rev", id);
+        insert_revision(runner, id, &rev, profile, gates);
+        runner.enqueue_execute(id, &rev).unwrap().queue_id().clone()
+    }
+
+    /// Ready task `id`, with nothing compiled or queued for it.
+    pub(crate) fn insert_task(runner: &Runner, id: &str, priority: i32, tags: &[&str]) -> TaskId {
+        let task_id = TaskId::from_str(id);
+        runner
+            .storage
+            .insert_task(Task {
+                id: task_id.clone(),
+                title: id.to_string(),
+                status: TaskStatus::Ready,
+                priority,
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+            })
+            .unwrap();
+        task_id
+    }
+
+    /// Revision `rev` of `task` and its spec revision `{rev}-spec`.
+    pub(crate) fn insert_revision(runner: &Runner, task: &str, rev: &str, profile: &str, gates: &[GateName]) {
+        let spec_rev = SpecRevision {
+            id: SpecRevId::from_str(format!("{}-spec", rev)),
+            task_id: TaskId::from_str(task),
+            spec_hash: "hash".into(),
+            spec_path: "path".into(),
+            parent_id: None,
+        };
+        runner.storage.insert_spec_revision(&spec_rev, 0).unwrap();
+        let revision = Revision {
+            id: RevisionId::from_str(rev),
+            task_id: TaskId::from_str(task),
+            spec_rev_id: spec_rev.id,
+            spec_hash: "hash".into(),
+            profile: profile.into(),
+            required_gates: gates.to_vec(),
+            required_validations: vec![],
+            anchors: vec![],
+        };
+        runner.storage.insert_revision(&revision, 0).unwrap();
+    }
+
+    fn ids(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|t| t.id.as_str()).collect()
+    }
+
+    #[test]
+    fn list_filters_by_status_and_tag_and_sorts_by_priority() {
+        let (_dir, runner) = runner();
+        add_task(&runner, "b", 1, &["ui"]);
+        add_task(&runner, "a", 1, &[]);
+        add_task(&runner, "c", 5, &["ui", "db"]);
+        runner.storage.set_task_status(&TaskId::from_str("a"), TaskStatus::BlockedFailure).unwrap();
+
+        assert_eq!(ids(&runner.list_tasks(None, None).unwrap()), vec!["c", "a", "b"]);
+        assert_eq!(ids(&runner.list_tasks(Some(&TaskStatus::Ready), None).unwrap()), vec!["c", "b"]);
+        assert_eq!(ids(&runner.list_tasks(None, Some("ui")).unwrap()), vec!["c", "b"]);
+        assert_eq!(ids(&runner.list_tasks(Some(&TaskStatus::Ready), Some("db")).unwrap()), vec!["c"]);
+    }
+
+    #[test]
+    fn priority_reaches_queued_work_and_tags_edit_in_place() {
+        let (_dir, runner) = runner();
+        add_task(&runner, "t", 3, &["a", "b"]);
+        let task_id = TaskId::from_str("t");
+        assert_eq!(runner.storage.list_queue_items_for_task(&task_id).unwrap()[0].item.priority, 3);
+
+        runner.set_task_priority(&task_id, 9).unwrap();
+        assert_eq!(runner.task(&task_id).unwrap().priority, 9);
+        assert_eq!(runner.storage.list_queue_items_for_task(&task_id).unwrap()[0].item.priority, 9);
+
+        let tags = runner.tag_task(&task_id, &["c".into(), "a".into()], &["b".into()]).unwrap();
+        assert_eq!(tags, vec!["a", "c"]);
+        assert_eq!(runner.task(&task_id).unwrap().tags, vec!["a", "c"]);
+        assert!(runner.set_task_priority(&TaskId::from_str("nope"), 1).is_err());
+    }
+
+    #[test]
+    fn reset_requeues_a_blocked_task_and_records_it() {
+        let (_dir, runner) = runner();
+        let first = add_task(&runner, "t", 0, &[]);
+        let task_id = TaskId::from_str("t");
+        let err = runner.reset_task(&task_id, "").unwrap_err().to_string();
+        assert!(err.contains("only blocked tasks"), "{}", err);
+
+        runner.storage.complete_queue_item(&first, now_unix()).unwrap();
+        runner.storage.set_task_status(&task_id, TaskStatus::BlockedFailure).unwrap();
+        let requeued = runner.reset_task(&task_id, "flaky runner fixed").unwrap().expect("execute re-queued");
+
+        assert_eq!(runner.task(&task_id).unwrap().status, TaskStatus::Ready);
+        let queue = runner.storage.list_queue_items_for_task(&task_id).unwrap();
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue[1].item.id.as_str(), requeued);
+        assert_eq!(queue[1].item.idempotency_key, "t:t-rev:reset1:execute");
+        assert!(queue[1].finished.is_none());
+        let detail = runner.task_detail(&task_id).unwrap();
+        let reset = detail.messages.iter().find(|m| m.ty == MessageType::Reset).expect("reset message");
+        assert!(reset.body_md.contains("BlockedFailure") && reset.body_md.contains("flaky runner fixed"));
+
+        // still queued: a second block and reset does not queue it twice
+        runner.storage.set_task_status(&task_id, TaskStatus::BlockedHitl).unwrap();
+        assert_eq!(runner.reset_task(&task_id, "").unwrap(), None);
+        assert_eq!(runner.storage.list_queue_items_for_task(&task_id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn cancel_drops_queued_work_unless_it_is_leased() {
+        let (_dir, runner) = runner();
+        let item = add_task(&runner, "t", 0, &[]);
+        let task_id = TaskId::from_str("t");
+
+        runner.storage.try_acquire_lease(&item, "w1", 60).unwrap().unwrap();
+        let err = runner.cancel_task(&task_id, "").unwrap_err().to_string();
+        assert!(err.contains("w1 holds queue item"), "{}", err);
+        runner.storage.release_lease(&item, "w1").unwrap();
+
+        runner.cancel_task(&task_id, "superseded").unwrap();
+        assert_eq!(runner.task(&task_id).unwrap().status, TaskStatus::Cancelled);
+        assert!(runner.storage.load_snapshot(i64::MAX).unwrap().queue.is_empty());
+        let finished = runner.storage.list_queue_items_for_task(&task_id).unwrap()[0].finished.clone();
+        assert!(matches!(finished, Some(QueueFinish::Cancelled { reason, .. }) if reason == "task cancelled"));
+        assert!(runner.cancel_task(&task_id, "").is_err());
+        assert!(runner.reset_task(&task_id, "").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::json;
+use spl_core::AnchorId;
+use spl_index::{default_index_path, diff_indexes, index_repo, SimpleIndex};
+use spl_validate::{builtin_rules, Severity, ValidateInput};
+
+use crate::gates::GateRun;
+
+/// Index the tree at `root` with every indexer, reusing the parses saved in `repo_root`'s
+/// index for files that did not change.
+pub fn index_tree(repo_root: &Path, root: &Path) -> Result<SimpleIndex> {
+    // a stale or missing saved index only costs parse time
+    let cache = SimpleIndex::load(&default_index_path(repo_root)).unwrap_or_default();
+    index_repo(root, &cache)
+}
+
+/// The `validate` gate: index the public surface of `head_root` (the task's workspace), diff
+/// it against `base` (`index_tree` of the workspace as created, i.e. the base rev) and run the
+/// validate rules over the diff. FAILs when any rule reports a `Fail` finding; the remediation
+/// names the anchors. Changes to the revision's pinned `declared` anchors are expected and
+/// only warn.
+///
+/// A head that cannot be indexed (e.g. a syntax error in the workspace) is a FAIL too.
+pub fn run_validate(base: &SimpleIndex, head_root: &Path, declared: &[AnchorId]) -> GateRun {
+    let head = match index_repo(head_root, base) {
+        Ok(head) => head,
+        Err(err) => return validate_unindexed(&err),
+    };
+
+    let input = ValidateInput {
+        exported_changes: diff_indexes(base, &head),
+        declared_anchors: declared.iter().map(|a| a.as_str().split('#').next().unwrap_or_default().to_string()).collect(),
+        details: vec![],
+    };
+    let findings: Vec<_> = builtin_rules().iter().flat_map(|rule| rule.eval(&input)).collect();
+    let failed: Vec<_> = findings.iter().filter(|f| f.severity == Severity::Fail).collect();
+    let evidence = json!({
+        "result": if failed.is_empty() { "PASS" } else { "FAIL" },
+        "findings": findings,
+        "exported_changes": input.exported_changes,
+    });
+    let evidence = serde_json::to_string_pretty(&evidence).unwrap_or_default();
+    if failed.is_empty() {
+        return GateRun::pass(evidence);
+    }
+    let messages: Vec<&str> = failed.iter().map(|f| f.message.as_str()).collect();
+    GateRun::fail(messages.join("\n"), evidence)
+}
+
+/// The `validate` FAIL for a tree (base or head) that could not be indexed.
+pub fn validate_unindexed(err: &anyhow::Error) -> GateRun {
+    let evidence = json!({ "result": "FAIL", "error": format!("{:#}", err) });
+    GateRun::fail(format!("validate could not index the public API: {:#}", err), evidence.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_core::GateStatus;
+    use tempfile::tempdir;
+
+    fn write_crate(root: &Path, lib_rs: &str) {
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\n").unwrap();
+        std::fs::write(root.join("src/lib.rs"), lib_rs).unwrap();
+    }
+
+    fn validate(base: &Path, head: &Path, declared: &[AnchorId]) -> GateRun {
+        run_validate(&index_tree(base, base).unwrap(), head, declared)
+    }
+
+    #[test]
+    fn fails_on_changed_or_removed_exports_only() {
+        let (base, head) = (tempdir().unwrap(), tempdir().unwrap());
+        write_crate(base.path(), "pub fn f() {}\npub fn g() {}\n");
+
+        write_crate(head.path(), "pub fn f() { println!(\"body\"); }\npub fn g() {}\npub fn added() {}\n");
+        assert_eq!(validate(base.path(), head.path(), &[]).status, GateStatus::Pass);
+
+        write_crate(head.path(), "pub fn f(x: u8) {}\n");
+        let run = validate(base.path(), head.path(), &[]);
+        assert_eq!(run.status, GateStatus::Fail);
+        let remediation = run.remediation.unwrap();
+        assert!(remediation.contains("exported rust://app::g removed"), "{}", remediation);
+        assert!(remediation.contains("exported rust://app::f signature changed"), "{}", remediation);
+        let evidence: serde_json::Value = serde_json::from_slice(&run.bytes).unwrap();
+        assert_eq!(evidence["result"], "FAIL");
+        assert_eq!(evidence["findings"][0]["anchor"], "rust://app::g");
+
+        write_crate(head.path(), "pub fn f( {}\n");
+        let run = validate(base.path(), head.path(), &[]);
+        assert_eq!(run.status, GateStatus::Fail);
+        assert!(run.remediation.unwrap().contains("could not index"));
+    }
+
+    #[test]
+    fn changes_to_declared_anchors_only_warn() {
+        let (base, head) = (tempdir().unwrap(), tempdir().unwrap());
+        write_crate(base.path(), "pub fn f() {}\npub fn g() {}\n");
+        write_crate(head.path(), "pub fn f(x: u8) {}\npub fn g() {}\n");
+
+        let run = validate(base.path(), head.path(), &[AnchorId::from_str("rust://app::f#0123")]);
+        assert_eq!(run.status, GateStatus::Pass);
+        let evidence: serde_json::Value = serde_json::from_slice(&run.bytes).unwrap();
+        assert_eq!(evidence["findings"][0]["severity"], "Warn");
+        assert_eq!(validate(base.path(), head.path(), &[AnchorId::from_str("rust://app::g")]).status, GateStatus::Fail);
+    }
+}
@@ -0,0 +1,30 @@
+//! Own test binary: it moves itself into a new process group and sends that group SIGINT,
+//! which must not disturb other tests.
+
+use std::time::Duration;
+
+use spl_core::GateStatus;
+use spl_runner::run_smoke;
+
+extern "C" fn on_sigint(_: libc::c_int) {}
+
+#[test]
+fn sigint_to_the_worker_group_does_not_kill_smoke_commands() {
+    // SAFETY: plain libc calls; the handler does nothing, and this binary has no other tests
+    unsafe {
+        assert_eq!(libc::setpgid(0, 0), 0);
+        libc::signal(libc::SIGINT, on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t);
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().to_path_buf();
+    let smoke = std::thread::spawn(move || run_smoke("pre_smoke", &["sleep 0.5; echo survived".to_string()], &path).unwrap());
+
+    std::thread::sleep(Duration::from_millis(200));
+    // what a terminal Ctrl-C does to a foreground `worker-serve`
+    unsafe { assert_eq!(libc::killpg(libc::getpgrp(), libc::SIGINT), 0) };
+
+    let run = smoke.join().unwrap();
+    let evidence = String::from_utf8_lossy(&run.bytes).to_string();
+    assert_eq!(run.status, GateStatus::Pass, "{}", evidence);
+    assert!(evidence.contains("survived"), "{}", evidence);
+}
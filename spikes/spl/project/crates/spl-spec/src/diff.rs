@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::pack::SpecPack;
+
+/// Entries of one spec pack list: matched by id, or by text for plain strings (which can
+/// only be added or removed).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl ListDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Structural difference between two spec packs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecDiff {
+    /// Other top-level fields that changed (intent, profile, scope, policy, gates).
+    pub fields: Vec<String>,
+    /// By use case id.
+    pub use_cases: ListDiff,
+    /// By contract id.
+    pub behavior_contracts: ListDiff,
+    /// `acceptance.tests` entries, then `acceptance.manual` ones prefixed `manual: `.
+    pub acceptance: ListDiff,
+}
+
+impl SpecDiff {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.use_cases.is_empty() && self.behavior_contracts.is_empty() && self.acceptance.is_empty()
+    }
+
+    /// One bullet per changed part of the pack.
+    pub fn to_markdown(&self) -> String {
+        if self.is_empty() {
+            return "- no changes\n".to_string();
+        }
+        let mut md = String::new();
+        if !self.fields.is_empty() {
+            md.push_str(&format!("- changed: {}\n", self.fields.join(", ")));
+        }
+        for (name, list) in [("use_cases", &self.use_cases), ("behavior_contracts", &self.behavior_contracts), ("acceptance", &self.acceptance)]
+        {
+            let parts: Vec<String> = [("added", &list.added), ("removed", &list.removed), ("changed", &list.changed)]
+                .iter()
+                .filter(|(_, items)| !items.is_empty())
+                .map(|(what, items)| format!("{} {}", what, items.join(", ")))
+                .collect();
+            if !parts.is_empty() {
+                md.push_str(&format!("- {}: {}\n", name, parts.join("; ")));
+            }
+        }
+        md
+    }
+}
+
+pub fn diff_spec_packs(old: &SpecPack, new: &SpecPack) -> SpecDiff {
+    let mut fields = vec![];
+    let mut field = |name: &str, old: serde_json::Value, new: serde_json::Value| {
+        if old != new {
+            fields.push(name.to_string());
+        }
+    };
+    field("intent", json(&old.intent), json(&new.intent));
+    field("profile", json(&old.profile), json(&new.profile));
+    field("scope", json(&old.scope), json(&new.scope));
+    field("policy", json(&old.policy), json(&new.policy));
+    field("gates", json(&old.gates), json(&new.gates));
+
+    let acceptance = |pack: &SpecPack| -> Vec<String> {
+        let manual = pack.acceptance.manual.iter().map(|m| format!("manual: {}", m));
+        pack.acceptance.tests.iter().cloned().chain(manual).collect()
+    };
+    SpecDiff {
+        fields,
+        use_cases: diff_by_key(&old.use_cases, &new.use_cases, |u| u.id.clone()),
+        behavior_contracts: diff_by_key(&old.behavior_contracts, &new.behavior_contracts, |c| c.id.clone()),
+        acceptance: diff_by_key(&acceptance(old), &acceptance(new), |s| s.clone()),
+    }
+}
+
+fn json<T: Serialize>(v: &T) -> serde_json::Value {
+    serde_json::to_value(v).expect("spec pack parts serialize")
+}
+
+/// Added and changed in `new`'s order, removed in `old`'s.
+fn diff_by_key<T: Serialize>(old: &[T], new: &[T], key: impl Fn(&T) -> String) -> ListDiff {
+    let mut diff = ListDiff::default();
+    for n in new {
+        match old.iter().find(|o| key(o) == key(n)) {
+            None => diff.added.push(key(n)),
+            Some(o) if json(o) != json(n) => diff.changed.push(key(n)),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old.iter().filter(|o| !new.iter().any(|n| key(n) == key(o))).map(&key).collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pack::{Acceptance, UseCase};
+
+    fn use_case(id: &str, step: &str) -> UseCase {
+        UseCase { id: id.into(), actor: "Owner".into(), preconditions: vec![], steps: vec![step.into()], postconditions: vec![] }
+    }
+
+    fn pack(use_cases: Vec<UseCase>, tests: &[&str]) -> SpecPack {
+        SpecPack {
+            task: "t".into(),
+            intent: "do thing".into(),
+            profile: None,
+            scope: None,
+            use_cases,
+            behavior_contracts: vec![],
+            acceptance: Acceptance { tests: tests.iter().map(|t| t.to_string()).collect(), manual: vec![] },
+            policy: None,
+            gates: None,
+        }
+    }
+
+    #[test]
+    fn diff_matches_entries_by_id() {
+        let old = pack(vec![use_case("UC-1", "a"), use_case("UC-2", "b")], &["cargo test", "true"]);
+        let mut new = pack(vec![use_case("UC-2", "b2"), use_case("UC-3", "c")], &["cargo test", "just e2e"]);
+        new.intent = "do the thing".into();
+        new.acceptance.manual = vec!["click it".into()];
+
+        let diff = diff_spec_packs(&old, &new);
+        assert_eq!(diff.fields, vec!["intent"]);
+        assert_eq!(diff.use_cases, ListDiff { added: vec!["UC-3".into()], removed: vec!["UC-1".into()], changed: vec!["UC-2".into()] });
+        assert!(diff.behavior_contracts.is_empty());
+        assert_eq!(diff.acceptance.added, vec!["just e2e", "manual: click it"]);
+        assert_eq!(diff.acceptance.removed, vec!["true"]);
+        assert_eq!(
+            diff.to_markdown(),
+            "- changed: intent\n- use_cases: added UC-3; removed UC-1; changed UC-2\n- acceptance: added just e2e, manual: click it; removed true\n"
+        );
+    }
+
+    #[test]
+    fn identical_packs_have_empty_diff() {
+        let p = pack(vec![use_case("UC-1", "a")], &["true"]);
+        let diff = diff_spec_packs(&p, &p.clone());
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_markdown(), "- no changes\n");
+    }
+}
@@ -1,5 +1,9 @@
+pub mod diff;
+pub mod lint;
 pub mod pack;
 pub mod profile;
 
+pub use diff::*;
+pub use lint::*;
 pub use pack::*;
 pub use profile::*;
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+use spl_core::AnchorId;
+use spl_index::{AnchorResolution, SimpleIndex};
+
+use crate::pack::SpecPack;
+use crate::profile::{parse_gate_name, ProfileSet, GATE_NAMES};
+
+/// Values allowed for `policy.network`.
+pub const NETWORK_POLICIES: &[&str] = &["deny", "allow_readonly", "allow"];
+
+/// One problem in a spec pack. `path` names the offending value (`use_cases[1].id`);
+/// `location` is its 1-based line and column in the YAML source, when known.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub path: String,
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn at(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic { path: path.into(), location: None, message: message.into() }
+    }
+
+    /// `file:line:col: error: message`, the way compilers print it.
+    pub fn render(&self, file: &str) -> String {
+        match self.location {
+            Some((line, col)) => format!("{}:{}:{}: error: {}", file, line, col, self.message),
+            None => format!("{}: error: {}", file, self.message),
+        }
+    }
+}
+
+/// JSON Schema of the spec pack format.
+pub fn spec_pack_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(SpecPack)).expect("schema serializes")
+}
+
+/// Parse a spec pack from YAML and check it. Malformed YAML or a value that does not fit the
+/// format (unknown fields included) stops at the first error; otherwise every problem
+/// `check_spec_pack` finds is reported, located in `yaml`.
+pub fn parse_spec_pack(yaml: &str, profiles: &ProfileSet) -> Result<SpecPack, Vec<Diagnostic>> {
+    let pack: SpecPack = serde_yaml::from_str(yaml).map_err(|e| {
+        let message = e.to_string();
+        match e.location() {
+            Some(loc) => {
+                let suffix = format!(" at line {} column {}", loc.line(), loc.column());
+                let message = message.strip_suffix(&suffix).unwrap_or(&message).to_string();
+                vec![Diagnostic { path: String::new(), location: Some((loc.line(), loc.column())), message }]
+            }
+            None => vec![Diagnostic::at("", message)],
+        }
+    })?;
+
+    let mut diagnostics = check_spec_pack(&pack, profiles);
+    if diagnostics.is_empty() {
+        return Ok(pack);
+    }
+    let positions = YamlPositions::index(yaml);
+    for d in &mut diagnostics {
+        d.location = positions.locate(&d.path);
+    }
+    Err(diagnostics)
+}
+
+/// Every semantic problem of `pack`, in field order, located by path only. What the pack must
+/// contain follows its profile; an unknown profile is checked like `standard`.
+pub fn check_spec_pack(pack: &SpecPack, profiles: &ProfileSet) -> Vec<Diagnostic> {
+    let mut out = vec![];
+    if pack.task.trim().is_empty() {
+        out.push(Diagnostic::at("task", "spec pack missing task"));
+    }
+    if pack.intent.trim().is_empty() {
+        out.push(Diagnostic::at("intent", "spec pack missing intent"));
+    }
+
+    let profile = pack.profile.as_deref().unwrap_or("standard");
+    let definition = match profiles.get(profile) {
+        Ok(definition) => definition.clone(),
+        Err(err) => {
+            out.push(Diagnostic::at("profile", err.to_string()));
+            ProfileSet::builtin().get("standard").expect("built-in standard profile").clone()
+        }
+    };
+    if definition.require_use_cases && pack.use_cases.is_empty() {
+        out.push(Diagnostic::at("use_cases", format!("spec pack must include at least one use_case (profile={})", profile)));
+    }
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for (i, uc) in pack.use_cases.iter().enumerate() {
+        let first = *seen.entry(&uc.id).or_insert(i);
+        if first != i {
+            out.push(Diagnostic::at(format!("use_cases[{}].id", i), format!("duplicate use case id {:?} (first at use_cases[{}])", uc.id, first)));
+        }
+    }
+
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for (i, bc) in pack.behavior_contracts.iter().enumerate() {
+        let first = *seen.entry(&bc.id).or_insert(i);
+        if first != i {
+            out.push(Diagnostic::at(
+                format!("behavior_contracts[{}].id", i),
+                format!("duplicate behavior contract id {:?} (first at behavior_contracts[{}])", bc.id, first),
+            ));
+        }
+        if definition.require_anchors && bc.anchor.trim().is_empty() {
+            out.push(Diagnostic::at(format!("behavior_contracts[{}].anchor", i), format!("behavior contract {:?} has an empty anchor", bc.id)));
+        }
+    }
+
+    if pack.acceptance.tests.is_empty() {
+        out.push(Diagnostic::at("acceptance.tests", "spec pack must include acceptance.tests"));
+    }
+
+    if let Some(network) = pack.policy.as_ref().and_then(|p| p.network.as_deref()) {
+        if !NETWORK_POLICIES.contains(&network) {
+            out.push(Diagnostic::at(
+                "policy.network",
+                format!("invalid network policy {:?} (expected one of {})", network, NETWORK_POLICIES.join(", ")),
+            ));
+        }
+    }
+
+    for (i, gate) in pack.gates.iter().flat_map(|g| g.required.iter()).enumerate() {
+        if parse_gate_name(gate).is_none() {
+            out.push(Diagnostic::at(format!("gates.required[{}]", i), format!("unknown gate {:?} (expected one of {})", gate, GATE_NAMES.join(", "))));
+        }
+    }
+    out
+}
+
+/// Resolve each behavior contract's anchor in `index`, pinned to its current signature hash
+/// (`rust://app::auth::login#<sig_hash>`), or report every anchor that is missing or ambiguous.
+/// Empty anchors (allowed by some profiles) are skipped.
+pub fn resolve_spec_anchors(pack: &SpecPack, index: &SimpleIndex) -> Result<Vec<AnchorId>, Vec<Diagnostic>> {
+    let mut pinned = vec![];
+    let mut out = vec![];
+    for (i, bc) in pack.behavior_contracts.iter().enumerate() {
+        if bc.anchor.trim().is_empty() {
+            continue;
+        }
+        let path = format!("behavior_contracts[{}].anchor", i);
+        let resolution = index.resolve_anchor(&bc.anchor);
+        match &resolution {
+            AnchorResolution::Resolved { .. } => pinned.extend(resolution.pinned()),
+            AnchorResolution::Missing => out.push(Diagnostic::at(path, format!("anchor {:?} of {:?} is not in the index", bc.anchor, bc.id))),
+            AnchorResolution::Ambiguous(candidates) => out.push(Diagnostic::at(
+                path,
+                format!("anchor {:?} of {:?} is ambiguous; name the crate: {}", bc.anchor, bc.id, candidates.join(", ")),
+            )),
+        }
+    }
+    if out.is_empty() {
+        Ok(pinned)
+    } else {
+        Err(out)
+    }
+}
+
+/// Where each value of a YAML document starts, by path (`acceptance.tests[0]`).
+#[derive(Default)]
+struct YamlPositions {
+    starts: HashMap<String, (usize, usize)>,
+    stack: Vec<Frame>,
+}
+
+enum Frame {
+    /// `key` is set between a key and its value.
+    Mapping { path: String, key: Option<String> },
+    Sequence { path: String, next: usize },
+}
+
+impl YamlPositions {
+    /// Positions of `yaml`'s first document; empty if it does not parse.
+    fn index(yaml: &str) -> Self {
+        let mut positions = YamlPositions::default();
+        if Parser::new_from_str(yaml).load(&mut positions, false).is_err() {
+            positions.starts.clear();
+        }
+        positions
+    }
+
+    /// `path`'s position, else that of its closest enclosing value.
+    fn locate(&self, path: &str) -> Option<(usize, usize)> {
+        let mut path = path;
+        loop {
+            if let Some(at) = self.starts.get(path) {
+                return Some(*at);
+            }
+            if path.is_empty() {
+                return None;
+            }
+            path = &path[..path.rfind(['.', '[']).unwrap_or(0)];
+        }
+    }
+
+    /// Path of the node starting now, or None if it is a mapping key (recorded as pending).
+    fn node_path(&mut self, key: Option<&str>) -> Option<String> {
+        match self.stack.last_mut() {
+            None => Some(String::new()),
+            Some(Frame::Mapping { path, key: pending }) => match pending.take() {
+                Some(k) if path.is_empty() => Some(k),
+                Some(k) => Some(format!("{}.{}", path, k)),
+                None => {
+                    *pending = Some(key.unwrap_or_default().to_string());
+                    None
+                }
+            },
+            Some(Frame::Sequence { path, next }) => {
+                *next += 1;
+                Some(format!("{}[{}]", path, *next - 1))
+            }
+        }
+    }
+}
+
+impl MarkedEventReceiver for YamlPositions {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        let at = (mark.line(), mark.col() + 1);
+        match ev {
+            Event::Scalar(value, ..) => {
+                if let Some(path) = self.node_path(Some(&value)) {
+                    self.starts.entry(path).or_insert(at);
+                }
+            }
+            Event::Alias(_) => {
+                if let Some(path) = self.node_path(None) {
+                    self.starts.entry(path).or_insert(at);
+                }
+            }
+            Event::MappingStart(..) | Event::SequenceStart(..) => {
+                // a collection used as a mapping key gets a placeholder path; nothing under it is reported
+                let path = self.node_path(None).unwrap_or_else(|| "?".to_string());
+                self.starts.entry(path.clone()).or_insert(at);
+                self.stack.push(match ev {
+                    Event::MappingStart(..) => Frame::Mapping { path, key: None },
+                    _ => Frame::Sequence { path, next: 0 },
+                });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "\
+task: t
+intent: do thing
+use_cases:
+  - id: UC-1
+    actor: Owner
+    steps: [\"run it\"]
+acceptance:
+  tests: [\"cargo test\"]
+";
+
+    #[test]
+    fn valid_pack_parses() {
+        let pack = parse_spec_pack(VALID, &ProfileSet::builtin()).unwrap();
+        assert_eq!(pack.use_cases[0].id, "UC-1");
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_its_line() {
+        let yaml = "\
+task: t
+intent: do thing
+use_cases:
+  - id: UC-1
+    actor: Owner
+  - id: UC-1
+    actor: Owner
+behavior_contracts:
+  - id: BC-1
+    anchor: \"\"
+acceptance:
+  tests: [\"cargo test\"]
+policy:
+  network: sometimes
+gates:
+  required:
+    - audit
+    - smoke
+";
+        let diagnostics = parse_spec_pack(yaml, &ProfileSet::builtin()).unwrap_err();
+        let rendered: Vec<String> = diagnostics.iter().map(|d| d.render("spec.yaml")).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "spec.yaml:6:9: error: duplicate use case id \"UC-1\" (first at use_cases[0])",
+                "spec.yaml:10:13: error: behavior contract \"BC-1\" has an empty anchor",
+                "spec.yaml:14:12: error: invalid network policy \"sometimes\" (expected one of deny, allow_readonly, allow)",
+                "spec.yaml:18:7: error: unknown gate \"smoke\" (expected one of spec_compile, ctx_pack, pre_smoke, delegate, audit, \
+                 adversarial_review, validate, post_smoke, land)",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected_with_their_location() {
+        let yaml = VALID.replace("    actor: Owner\n", "    actor: Owner\n    actors: [Admin]\n");
+        let diagnostics = parse_spec_pack(&yaml, &ProfileSet::builtin()).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].location, Some((6, 5)));
+        assert!(diagnostics[0].message.contains("unknown field `actors`"), "{}", diagnostics[0].message);
+    }
+
+    #[test]
+    fn missing_values_point_at_the_enclosing_one() {
+        let yaml = VALID.replace("  tests: [\"cargo test\"]", "  tests: []").replace("task: t", "task: \"\"");
+        let diagnostics = parse_spec_pack(&yaml, &ProfileSet::builtin()).unwrap_err();
+        let located: Vec<_> = diagnostics.iter().map(|d| (d.path.as_str(), d.location)).collect();
+        assert_eq!(located, vec![("task", Some((1, 7))), ("acceptance.tests", Some((8, 10)))]);
+    }
+
+    #[test]
+    fn requirements_follow_the_profile() {
+        let bare = "task: t\nintent: x\nacceptance:\n  tests: [\"true\"]\nprofile: ";
+        let diagnostics = parse_spec_pack(&format!("{}standrad\n", bare), &ProfileSet::builtin()).unwrap_err();
+        let messages: Vec<_> = diagnostics.iter().map(|d| (d.location, d.message.as_str())).collect();
+        assert_eq!(messages[0], (Some((5, 10)), "unknown profile \"standrad\" (known: backfill_spec, docs, hotfix, standard)"));
+        assert!(messages[1].1.contains("at least one use_case"), "{:?}", messages);
+
+        assert!(parse_spec_pack(&format!("{}backfill_spec\n", bare), &ProfileSet::builtin()).is_ok());
+    }
+
+    #[test]
+    fn schema_describes_the_pack_and_rejects_unknown_fields() {
+        let schema = spec_pack_schema();
+        assert_eq!(schema["additionalProperties"], false);
+        assert!(schema["properties"]["use_cases"].is_object());
+        assert_eq!(schema["required"], serde_json::json!(["task", "intent", "acceptance"]));
+    }
+
+    #[test]
+    fn example_spec_packs_lint_clean() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/spec-packs");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "yaml") {
+                let yaml = std::fs::read_to_string(&path).unwrap();
+                assert_eq!(parse_spec_pack(&yaml, &ProfileSet::builtin()).err(), None, "{}", path.display());
+            }
+        }
+    }
+}
@@ -1,11 +1,15 @@
 use anyhow::{anyhow, Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use spl_core::{AnchorId, GateName};
+use spl_index::SimpleIndex;
 
-use crate::profile::required_gates_for_profile;
+use crate::lint::{check_spec_pack, parse_spec_pack, resolve_spec_anchors};
+use crate::profile::ProfileSet;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct SpecPack {
     pub task: String,
     pub intent: String,
@@ -24,7 +28,8 @@ pub struct SpecPack {
     pub gates: Option<Gates>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Scope {
     #[serde(default)]
     pub r#in: Vec<String>,
@@ -32,7 +37,8 @@ pub struct Scope {
     pub out: Vec<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UseCase {
     pub id: String,
     pub actor: String,
@@ -44,7 +50,8 @@ pub struct UseCase {
     pub postconditions: Vec<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct BehaviorContract {
     pub id: String,
     pub anchor: String,
@@ -54,22 +61,26 @@ pub struct BehaviorContract {
     pub invariants: Vec<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Acceptance {
     pub tests: Vec<String>,
     #[serde(default)]
     pub manual: Vec<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Policy {
     #[serde(default)]
-    pub network: Option<String>, // deny | allow_readonly | allow
+    #[schemars(extend("enum" = ["deny", "allow_readonly", "allow", null]))]
+    pub network: Option<String>,
     #[serde(default)]
     pub allow_domains: Vec<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Gates {
     #[serde(default)]
     pub required: Vec<String>,
@@ -80,32 +91,28 @@ pub struct RevisionDraft {
     pub spec_hash: String,
     pub profile: String,
     pub required_gates: Vec<GateName>,
+    pub required_validations: Vec<String>,
+    /// Behavior contract anchors pinned to their signature hash at compile time.
     pub anchors: Vec<AnchorId>,
 }
 
-pub fn load_spec_pack(path: &std::path::Path) -> Result<SpecPack> {
+pub fn load_spec_pack(path: &std::path::Path, profiles: &ProfileSet) -> Result<SpecPack> {
     let s = std::fs::read_to_string(path).with_context(|| format!("read spec pack: {}", path.display()))?;
-    let pack: SpecPack = serde_yaml::from_str(&s).with_context(|| "parse spec pack yaml")?;
-    validate_spec_pack(&pack)?;
-    Ok(pack)
+    parse_spec_pack(&s, profiles).map_err(|diagnostics| {
+        let file = path.display().to_string();
+        let lines: Vec<String> = diagnostics.iter().map(|d| d.render(&file)).collect();
+        anyhow!("invalid spec pack {}:\n{}", file, lines.join("\n"))
+    })
 }
 
-pub fn validate_spec_pack(pack: &SpecPack) -> Result<()> {
-    if pack.task.trim().is_empty() {
-        return Err(anyhow!("spec pack missing task"));
+/// Every problem `check_spec_pack` finds, as one error.
+pub fn validate_spec_pack(pack: &SpecPack, profiles: &ProfileSet) -> Result<()> {
+    let diagnostics = check_spec_pack(pack, profiles);
+    if diagnostics.is_empty() {
+        return Ok(());
     }
-    if pack.intent.trim().is_empty() {
-        return Err(anyhow!("spec pack missing intent"));
-    }
-    if pack.acceptance.tests.is_empty() {
-        return Err(anyhow!("spec pack must include acceptance.tests"));
-    }
-    // v0: require at least one use case for standard/docs/hotfix (backfill_spec may relax later)
-    let profile = pack.profile.as_deref().unwrap_or("standard");
-    if profile != "backfill_spec" && pack.use_cases.is_empty() {
-        return Err(anyhow!("spec pack must include at least one use_case (profile={})", profile));
-    }
-    Ok(())
+    let messages: Vec<String> = diagnostics.iter().map(|d| format!("{}: {}", d.path, d.message)).collect();
+    Err(anyhow!("invalid spec pack: {}", messages.join("; ")))
 }
 
 pub fn canonical_json(pack: &SpecPack) -> serde_json::Value {
@@ -140,31 +147,32 @@ pub fn spec_hash(pack: &SpecPack) -> String {
     hex::encode(digest)
 }
 
-pub fn compile_revision_draft(pack: &SpecPack) -> RevisionDraft {
+/// Errors if the pack's profile (default `standard`) is not in `profiles`, or if any behavior
+/// contract anchor does not resolve in `index`.
+pub fn compile_revision_draft(pack: &SpecPack, profiles: &ProfileSet, index: &SimpleIndex) -> Result<RevisionDraft> {
     let profile = pack.profile.clone().unwrap_or_else(|| "standard".to_string());
-    let required_gates = required_gates_for_profile(&profile);
+    let definition = profiles.get(&profile)?;
 
-    let anchors = pack
-        .behavior_contracts
-        .iter()
-        .map(|bc| AnchorId::from_str(bc.anchor.clone()))
-        .collect::<Vec<_>>();
+    let anchors = resolve_spec_anchors(pack, index).map_err(|diagnostics| {
+        let messages: Vec<String> = diagnostics.iter().map(|d| format!("{}: {}", d.path, d.message)).collect();
+        anyhow!("unresolved anchors: {}", messages.join("; "))
+    })?;
 
-    RevisionDraft {
+    Ok(RevisionDraft {
         spec_hash: spec_hash(pack),
+        required_gates: definition.gates(),
+        required_validations: definition.required_validations.clone(),
         profile,
-        required_gates,
         anchors,
-    }
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn hash_is_stable() {
-        let pack = SpecPack {
+    fn pack() -> SpecPack {
+        SpecPack {
             task: "pt-1".into(),
             intent: "do thing".into(),
             profile: Some("standard".into()),
@@ -180,11 +188,32 @@ mod tests {
             acceptance: Acceptance { tests: vec!["true".into()], manual: vec![] },
             policy: None,
             gates: None,
-        };
+        }
+    }
 
+    #[test]
+    fn hash_is_stable() {
+        let pack = pack();
         let h1 = spec_hash(&pack);
         let h2 = spec_hash(&pack);
         assert_eq!(h1, h2);
         assert_eq!(h1.len(), 64);
     }
+
+    #[test]
+    fn compile_pins_anchors_and_rejects_unknown_ones() {
+        let mut pack = pack();
+        let contract = |id: &str, anchor: &str| BehaviorContract { id: id.into(), anchor: anchor.into(), examples: vec![], invariants: vec![] };
+        pack.behavior_contracts = vec![contract("BC-1", "rust://crate::auth::login#fn(user: &str)")];
+        let mut index = SimpleIndex::default();
+        index.anchors.insert("rust://app::auth::login".into(), "abc123".into());
+        let profiles = ProfileSet::builtin();
+
+        let draft = compile_revision_draft(&pack, &profiles, &index).unwrap();
+        assert_eq!(draft.anchors, vec![AnchorId::from_str("rust://app::auth::login#abc123")]);
+
+        pack.behavior_contracts.push(contract("BC-2", "rust://crate::auth::logout"));
+        let err = compile_revision_draft(&pack, &profiles, &index).unwrap_err().to_string();
+        assert!(err.contains("behavior_contracts[1].anchor: anchor \"rust://crate::auth::logout\" of \"BC-2\" is not in the index"), "{}", err);
+    }
 }
@@ -1,22 +1,131 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
 use spl_core::GateName;
 
-/// Built-in profile -> required gate mapping (v0 KISS).
-pub fn required_gates_for_profile(profile: &str) -> Vec<GateName> {
-    match profile {
-        "docs" => vec![GateName::Audit, GateName::AdversarialReview],
-        "hotfix" => vec![
-            GateName::PreSmoke,
-            GateName::Audit,
-            GateName::Validate,
-            GateName::PostSmoke,
-        ],
-        // standard default
-        _ => vec![
-            GateName::PreSmoke,
-            GateName::Audit,
-            GateName::AdversarialReview,
-            GateName::Validate,
-            GateName::PostSmoke,
-        ],
+/// What a profile asks of a revision compiled under it, as written in `spl.toml`
+/// (`[profiles.<name>]`) or `.spl/profiles/<name>.toml`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Gate names, run in this order.
+    #[serde(default)]
+    pub required_gates: Vec<String>,
+    #[serde(default)]
+    pub required_validations: Vec<String>,
+    /// Replace `commands.pre_smoke` / `commands.post_smoke` for revisions of this profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_smoke: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_smoke: Option<Vec<String>>,
+    /// Spec packs must have at least one use case.
+    #[serde(default = "yes")]
+    pub require_use_cases: bool,
+    /// Behavior contracts must name an anchor.
+    #[serde(default = "yes")]
+    pub require_anchors: bool,
+}
+
+fn yes() -> bool {
+    true
+}
+
+impl Profile {
+    fn with_gates(gates: &[&str]) -> Self {
+        Profile {
+            required_gates: gates.iter().map(|g| g.to_string()).collect(),
+            required_validations: vec![],
+            pre_smoke: None,
+            post_smoke: None,
+            require_use_cases: true,
+            require_anchors: true,
+        }
+    }
+
+    /// `required_gates` parsed; `ProfileSet::define` has checked every name.
+    pub fn gates(&self) -> Vec<GateName> {
+        self.required_gates.iter().filter_map(|g| parse_gate_name(g)).collect()
+    }
+}
+
+/// The profiles a project can compile revisions under, by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileSet {
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl ProfileSet {
+    /// The profiles SPL ships with; a project's own definitions replace them by name.
+    pub fn builtin() -> Self {
+        let mut backfill = Profile::with_gates(&["pre_smoke", "audit", "validate", "post_smoke"]);
+        backfill.require_use_cases = false;
+        backfill.require_anchors = false;
+        let profiles = BTreeMap::from([
+            ("standard".to_string(), Profile::with_gates(&["pre_smoke", "audit", "adversarial_review", "validate", "post_smoke"])),
+            ("docs".to_string(), Profile::with_gates(&["audit", "adversarial_review"])),
+            ("hotfix".to_string(), Profile::with_gates(&["pre_smoke", "audit", "validate", "post_smoke"])),
+            ("backfill_spec".to_string(), backfill),
+        ]);
+        ProfileSet { profiles }
+    }
+
+    /// Add `profile` as `name`, replacing any profile already called that.
+    pub fn define(&mut self, name: &str, profile: Profile) -> Result<()> {
+        if let Some(gate) = profile.required_gates.iter().find(|g| parse_gate_name(g).is_none()) {
+            bail!("profile {:?}: unknown gate {:?} (expected one of {})", name, gate, GATE_NAMES.join(", "));
+        }
+        self.profiles.insert(name.to_string(), profile);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Profile> {
+        self.profiles.get(name).ok_or_else(|| anyhow!("unknown profile {:?} (known: {})", name, self.names().join(", ")))
+    }
+
+    /// Sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Profile)> {
+        self.profiles.iter().map(|(name, p)| (name.as_str(), p))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.profiles.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// Gate names as written in a spec pack's `gates.required`.
+pub const GATE_NAMES: &[&str] =
+    &["spec_compile", "ctx_pack", "pre_smoke", "delegate", "audit", "adversarial_review", "validate", "post_smoke", "land"];
+
+pub fn parse_gate_name(name: &str) -> Option<GateName> {
+    match name {
+        "spec_compile" => Some(GateName::SpecCompile),
+        "ctx_pack" => Some(GateName::CtxPack),
+        "pre_smoke" => Some(GateName::PreSmoke),
+        "delegate" => Some(GateName::Delegate),
+        "audit" => Some(GateName::Audit),
+        "adversarial_review" => Some(GateName::AdversarialReview),
+        "validate" => Some(GateName::Validate),
+        "post_smoke" => Some(GateName::PostSmoke),
+        "land" => Some(GateName::Land),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_profiles_and_gates_are_errors() {
+        let mut profiles = ProfileSet::builtin();
+        assert_eq!(profiles.get("standard").unwrap().gates().len(), 5);
+        assert!(!profiles.get("backfill_spec").unwrap().require_use_cases);
+        let err = profiles.get("standrad").unwrap_err().to_string();
+        assert!(err.contains("known: backfill_spec, docs, hotfix, standard"), "{}", err);
+
+        assert!(profiles.define("lite", Profile::with_gates(&["audit", "smoke"])).is_err());
+        profiles.define("docs", Profile::with_gates(&["audit"])).unwrap();
+        assert_eq!(profiles.get("docs").unwrap().gates(), vec![GateName::Audit]);
     }
 }
@@ -0,0 +1,113 @@
+-- Same tables as the sqlite backend at its schema version 4, created in one step.
+-- `seq` columns give insertion order, the role rowid plays in sqlite.
+
+CREATE TABLE meta (
+  key TEXT PRIMARY KEY,
+  value TEXT NOT NULL
+);
+
+CREATE TABLE tasks (
+  id TEXT PRIMARY KEY,
+  title TEXT NOT NULL,
+  status TEXT NOT NULL,
+  priority INTEGER NOT NULL,
+  tags_json TEXT NOT NULL
+);
+
+CREATE TABLE spec_revisions (
+  id TEXT PRIMARY KEY,
+  task_id TEXT NOT NULL REFERENCES tasks(id),
+  spec_hash TEXT NOT NULL,
+  spec_path TEXT NOT NULL,
+  created_at BIGINT NOT NULL
+);
+
+CREATE TABLE revisions (
+  id TEXT PRIMARY KEY,
+  seq BIGSERIAL NOT NULL,
+  task_id TEXT NOT NULL REFERENCES tasks(id),
+  spec_rev_id TEXT NOT NULL REFERENCES spec_revisions(id),
+  spec_hash TEXT NOT NULL,
+  profile TEXT NOT NULL,
+  required_gates_json TEXT NOT NULL,
+  required_validations_json TEXT NOT NULL,
+  anchors_json TEXT NOT NULL,
+  created_at BIGINT NOT NULL
+);
+
+CREATE TABLE queue_items (
+  id TEXT PRIMARY KEY,
+  task_id TEXT NOT NULL REFERENCES tasks(id),
+  revision_id TEXT NOT NULL REFERENCES revisions(id),
+  lane TEXT NOT NULL,
+  idempotency_key TEXT NOT NULL,
+  priority INTEGER NOT NULL,
+  enqueued_at BIGINT NOT NULL,
+  visible_at BIGINT NOT NULL,
+  attempts BIGINT NOT NULL,
+  max_attempts BIGINT NOT NULL,
+  artifact_ref TEXT
+);
+
+CREATE UNIQUE INDEX queue_items_idem_uq ON queue_items(lane, idempotency_key);
+
+-- Items leave the queue here: completed normally, or dead-lettered after max_attempts.
+CREATE TABLE queue_finished (
+  queue_id TEXT PRIMARY KEY REFERENCES queue_items(id),
+  state TEXT NOT NULL,
+  reason TEXT,
+  finished_at BIGINT NOT NULL
+);
+
+CREATE TABLE leases (
+  id TEXT PRIMARY KEY,
+  queue_id TEXT NOT NULL UNIQUE REFERENCES queue_items(id),
+  worker_id TEXT NOT NULL,
+  acquired_at BIGINT NOT NULL,
+  expires_at BIGINT NOT NULL
+);
+
+CREATE INDEX leases_expires_idx ON leases(expires_at);
+
+-- Project-wide named mutexes (e.g. "land": one landing at a time across workers).
+CREATE TABLE resource_locks (
+  name TEXT PRIMARY KEY,
+  holder TEXT NOT NULL,
+  acquired_at BIGINT NOT NULL,
+  expires_at BIGINT NOT NULL
+);
+
+CREATE TABLE runs (
+  id TEXT PRIMARY KEY,
+  queue_id TEXT NOT NULL REFERENCES queue_items(id),
+  task_id TEXT NOT NULL,
+  revision_id TEXT NOT NULL,
+  lane TEXT NOT NULL,
+  started_at BIGINT NOT NULL,
+  finished_at BIGINT,
+  result TEXT,
+  current_gate TEXT
+);
+
+-- Per-run gate history; id orders gates by completion.
+CREATE TABLE gate_outcomes (
+  id BIGSERIAL PRIMARY KEY,
+  run_id TEXT NOT NULL REFERENCES runs(id),
+  gate TEXT NOT NULL,
+  status TEXT NOT NULL,
+  remediation TEXT,
+  started_at BIGINT NOT NULL,
+  finished_at BIGINT NOT NULL,
+  artifacts_json TEXT NOT NULL
+);
+
+CREATE INDEX gate_outcomes_run_idx ON gate_outcomes(run_id);
+
+CREATE TABLE messages (
+  id TEXT PRIMARY KEY,
+  seq BIGSERIAL NOT NULL,
+  task_id TEXT NOT NULL REFERENCES tasks(id),
+  ty TEXT NOT NULL,
+  body_md TEXT NOT NULL,
+  created_at BIGINT NOT NULL
+);
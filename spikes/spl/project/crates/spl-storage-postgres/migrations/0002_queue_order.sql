@@ -0,0 +1,3 @@
+-- Insertion order for queue items, so a task's queue history lists same-second items
+-- the way sqlite's rowid does.
+ALTER TABLE queue_items ADD COLUMN seq BIGSERIAL;
@@ -0,0 +1,4 @@
+-- An amended spec revision points at the one it supersedes; `seq` orders a task's
+-- spec revisions like sqlite's rowid.
+ALTER TABLE spec_revisions ADD COLUMN parent_id TEXT REFERENCES spec_revisions(id);
+ALTER TABLE spec_revisions ADD COLUMN seq BIGSERIAL;
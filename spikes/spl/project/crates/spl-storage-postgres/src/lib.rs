@@ -0,0 +1,7 @@
+pub mod migrate;
+pub mod storage;
+#[cfg(test)]
+mod testdb;
+
+pub use migrate::*;
+pub use storage::*;
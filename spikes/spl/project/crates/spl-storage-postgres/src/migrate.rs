@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Context, Result};
+use postgres::{Client, Config, GenericClient, NoTls};
+
+pub use spl_storage::{Migration, MigrationPlan};
+
+/// Every schema step, oldest first. Append only: a shipped migration is never edited.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "init", sql: include_str!("../migrations/0001_init.sql") },
+    Migration { version: 2, name: "queue_order", sql: include_str!("../migrations/0002_queue_order.sql") },
+    Migration { version: 3, name: "message_payload", sql: include_str!("../migrations/0003_message_payload.sql") },
+    Migration { version: 4, name: "spec_parent", sql: include_str!("../migrations/0004_spec_parent.sql") },
+];
+
+/// `meta` key holding the applied schema version.
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Advisory lock key taken by every migration step, so workers starting together on
+/// different hosts apply each step once.
+const MIGRATION_LOCK_KEY: i64 = 0x5350_4c5f_6d69_6772; // "SPL_migr"
+
+pub fn latest_schema_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Plan migrations for `client` without changing the database. Errors if it was written
+/// by a newer schema than this build knows.
+pub fn plan_migrations(client: &mut impl GenericClient) -> Result<MigrationPlan> {
+    let current = recorded_version(client)?.unwrap_or(0);
+    let latest = latest_schema_version();
+    if current > latest {
+        return Err(anyhow!(
+            "database schema version {} is newer than this build supports ({}); upgrade spl before opening it",
+            current,
+            latest
+        ));
+    }
+    let pending = MIGRATIONS.iter().filter(|m| m.version > current).copied().collect();
+    Ok(MigrationPlan { current, legacy: false, pending })
+}
+
+/// Plan migrations for the database at `url` without changing it.
+pub fn plan_migrations_at(url: &str) -> Result<MigrationPlan> {
+    let config: Config = url.parse().context("parse postgres url")?;
+    let mut client = config.connect(NoTls).context("connect to postgres")?;
+    plan_migrations(&mut client)
+}
+
+/// Bring the database up to `latest_schema_version`. Safe for several workers connecting
+/// at once: each step re-reads the version under an advisory lock and skips if already applied.
+pub fn migrate(client: &mut Client) -> Result<MigrationPlan> {
+    let plan = plan_migrations(client)?;
+    for m in &plan.pending {
+        let mut tx = client.transaction()?;
+        tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_KEY])?;
+        if recorded_version(&mut tx)?.unwrap_or(0) >= m.version {
+            continue;
+        }
+        tx.batch_execute(m.sql).with_context(|| format!("apply migration {:04}_{}", m.version, m.name))?;
+        tx.execute(
+            "INSERT INTO meta(key, value) VALUES ($1, $2) ON CONFLICT(key) DO UPDATE SET value=EXCLUDED.value",
+            &[&SCHEMA_VERSION_KEY, &m.version.to_string()],
+        )?;
+        tx.commit()?;
+    }
+    Ok(plan)
+}
+
+fn recorded_version(client: &mut impl GenericClient) -> Result<Option<u32>> {
+    // a plain catalog query, not to_regclass: its cached lookup can miss a table created
+    // by the worker we just waited for on the advisory lock
+    let has_meta: bool = client
+        .query_one("SELECT EXISTS (SELECT 1 FROM pg_tables WHERE schemaname = current_schema() AND tablename = 'meta')", &[])?
+        .get(0);
+    if !has_meta {
+        return Ok(None);
+    }
+    let row = client.query_opt("SELECT value FROM meta WHERE key=$1", &[&SCHEMA_VERSION_KEY])?;
+    row.map(|r| {
+        let v: String = r.get(0);
+        v.parse::<u32>().with_context(|| format!("bad {} in meta: {:?}", SCHEMA_VERSION_KEY, v))
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdb::TestDb;
+
+    #[test]
+    fn new_db_gets_every_migration_once() {
+        let Some(db) = TestDb::create() else { return };
+        let mut client = db.client();
+
+        let plan = migrate(&mut client).unwrap();
+        assert_eq!((plan.current, plan.pending.len()), (0, MIGRATIONS.len()));
+        assert_eq!(recorded_version(&mut client).unwrap(), Some(latest_schema_version()));
+
+        let again = migrate(&mut client).unwrap();
+        assert!(again.pending.is_empty());
+    }
+
+    #[test]
+    fn workers_migrating_together_apply_each_step_once() {
+        let Some(db) = TestDb::create() else { return };
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4).map(|_| scope.spawn(|| migrate(&mut db.client()))).collect();
+            for w in workers {
+                w.join().unwrap().unwrap();
+            }
+        });
+        assert_eq!(recorded_version(&mut db.client()).unwrap(), Some(latest_schema_version()));
+    }
+
+    #[test]
+    fn refuses_db_from_newer_schema() {
+        let Some(db) = TestDb::create() else { return };
+        let mut client = db.client();
+        migrate(&mut client).unwrap();
+        client.execute("UPDATE meta SET value=$1 WHERE key=$2", &[&(latest_schema_version() + 1).to_string(), &SCHEMA_VERSION_KEY]).unwrap();
+
+        let err = migrate(&mut client).unwrap_err().to_string();
+        assert!(err.contains("newer than this build supports"), "{}", err);
+    }
+}
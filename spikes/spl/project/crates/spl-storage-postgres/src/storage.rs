@@ -0,0 +1,601 @@
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use postgres::types::ToSql;
+use postgres::{Client, Config, GenericClient, IsolationLevel, NoTls, Row};
+use spl_core::{
+    AnchorId, GateName, GateOutcome, GateOutcomeRecord, Lease, Message, MessageType, QueueId, QueueItem, Revision, RevisionId, Run,
+    RunId, RunResult, Snapshot, SpecRevId, SpecRevision, Task, TaskId, TaskStatus,
+};
+use spl_storage::{codec, EnqueueResult, QueueItemRecord, Storage};
+
+/// Storage shared by workers on several hosts through one postgres database.
+pub struct PostgresStorage {
+    client: Mutex<Client>,
+}
+
+impl PostgresStorage {
+    /// Connect to `url` (`postgres://...` or `host=... user=...`) and migrate the database.
+    pub fn connect(url: &str) -> Result<Self> {
+        let config: Config = url.parse().context("parse postgres url")?;
+        Self::connect_with(&config)
+    }
+
+    pub fn connect_with(config: &Config) -> Result<Self> {
+        let mut client = config.connect(NoTls).context("connect to postgres")?;
+        crate::migrate(&mut client).context("migrate postgres db")?;
+        Ok(Self { client: Mutex::new(client) })
+    }
+
+    /// Revisions matching `filter` (a WHERE clause over `revisions`), oldest first.
+    fn query_revisions(client: &mut impl GenericClient, filter: &str, args: &[&(dyn ToSql + Sync)]) -> Result<Vec<Revision>> {
+        let rows = client.query(
+            &format!(
+                "SELECT id, task_id, spec_rev_id, spec_hash, profile, required_gates_json, required_validations_json, anchors_json
+                 FROM revisions WHERE {} ORDER BY created_at, seq",
+                filter
+            ),
+            args,
+        )?;
+
+        let mut revisions = vec![];
+        for r in rows {
+            let id: String = r.get(0);
+            let required_gates: Vec<GateName> =
+                serde_json::from_str(r.get(5)).with_context(|| format!("parse required_gates_json for revision {}", id))?;
+            let required_validations: Vec<String> = serde_json::from_str(r.get(6)).unwrap_or_default();
+            let anchors: Vec<String> = serde_json::from_str(r.get(7)).unwrap_or_default();
+            revisions.push(Revision {
+                id: RevisionId::from_str(id),
+                task_id: TaskId::from_str(r.get::<_, String>(1)),
+                spec_rev_id: SpecRevId::from_str(r.get::<_, String>(2)),
+                spec_hash: r.get(3),
+                profile: r.get(4),
+                required_gates,
+                required_validations,
+                anchors: anchors.into_iter().map(AnchorId::from_str).collect(),
+            });
+        }
+        Ok(revisions)
+    }
+}
+
+/// Columns `queue_item_from_row` expects, in order.
+const QUEUE_ITEM_COLUMNS: &str =
+    "q.id, q.task_id, q.revision_id, q.lane, q.visible_at, q.attempts, q.max_attempts, q.priority, q.idempotency_key, q.artifact_ref";
+
+fn queue_item_from_row(r: &Row) -> QueueItem {
+    QueueItem {
+        id: QueueId::from_str(r.get::<_, String>(0)),
+        task_id: TaskId::from_str(r.get::<_, String>(1)),
+        revision_id: RevisionId::from_str(r.get::<_, String>(2)),
+        lane: codec::str_to_lane(r.get(3)),
+        visible_at_unix: r.get(4),
+        attempts: r.get::<_, i64>(5) as u32,
+        max_attempts: r.get::<_, i64>(6) as u32,
+        priority: r.get(7),
+        idempotency_key: r.get(8),
+        artifact_ref: r.get(9),
+    }
+}
+
+fn lease_from_row(r: &Row) -> Lease {
+    Lease {
+        id: spl_core::LeaseId::from_str(r.get::<_, String>(0)),
+        queue_id: QueueId::from_str(r.get::<_, String>(1)),
+        worker_id: r.get(2),
+        acquired_at_unix: r.get(3),
+        expires_at_unix: r.get(4),
+    }
+}
+
+impl Storage for PostgresStorage {
+    fn load_snapshot(&self, now_unix: i64) -> Result<Snapshot> {
+        let mut client = self.client.lock().unwrap();
+        // one consistent view across the tables, even while other hosts write
+        let mut tx = client.build_transaction().isolation_level(IsolationLevel::RepeatableRead).read_only(true).start()?;
+
+        let tasks = tx
+            .query("SELECT id, title, status, priority, tags_json FROM tasks", &[])?
+            .iter()
+            .map(|r| Task {
+                id: TaskId::from_str(r.get::<_, String>(0)),
+                title: r.get(1),
+                status: codec::str_to_task_status(r.get(2)),
+                priority: r.get(3),
+                tags: serde_json::from_str(r.get(4)).unwrap_or_default(),
+            })
+            .collect();
+
+        let queue = tx
+            .query(
+                &format!(
+                    "SELECT {QUEUE_ITEM_COLUMNS} FROM queue_items q
+                     WHERE visible_at <= $1 AND NOT EXISTS (SELECT 1 FROM queue_finished f WHERE f.queue_id = q.id)"
+                ),
+                &[&now_unix],
+            )?
+            .iter()
+            .map(queue_item_from_row)
+            .collect();
+
+        let leases =
+            tx.query("SELECT id, queue_id, worker_id, acquired_at, expires_at FROM leases", &[])?.iter().map(lease_from_row).collect();
+
+        let revisions = Self::query_revisions(&mut tx, "TRUE", &[])?;
+
+        let runs = tx
+            .query("SELECT id, queue_id, task_id, revision_id, lane, result, current_gate FROM runs", &[])?
+            .iter()
+            .map(|r| Run {
+                id: RunId::from_str(r.get::<_, String>(0)),
+                queue_id: QueueId::from_str(r.get::<_, String>(1)),
+                task_id: TaskId::from_str(r.get::<_, String>(2)),
+                revision_id: RevisionId::from_str(r.get::<_, String>(3)),
+                lane: codec::str_to_lane(r.get(4)),
+                result: r.get::<_, Option<&str>>(5).and_then(codec::str_to_result),
+                current_gate: r.get::<_, Option<&str>>(6).and_then(codec::str_to_gate),
+            })
+            .collect();
+
+        let messages = tx
+            .query("SELECT id, task_id, ty, body_md, created_at, payload_json FROM messages ORDER BY created_at, seq", &[])?
+            .iter()
+            .map(|r| Message {
+                id: r.get(0),
+                task_id: TaskId::from_str(r.get::<_, String>(1)),
+                ty: codec::str_to_message_type(r.get(2)),
+                body_md: r.get(3),
+                payload_json: r.get(5),
+                created_at_unix: r.get(4),
+            })
+            .collect();
+        tx.commit()?;
+
+        Ok(Snapshot { now_unix, vcs: Default::default(), tasks, revisions, queue, leases, runs, messages })
+    }
+
+    fn insert_task(&self, task: Task) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        let tags_json = serde_json::to_string(&task.tags).unwrap_or_else(|_| "[]".to_string());
+        client.execute(
+            "INSERT INTO tasks(id, title, status, priority, tags_json) VALUES ($1, $2, $3, $4, $5)",
+            &[&task.id.0, &task.title, &codec::task_status_to_str(&task.status), &task.priority, &tags_json],
+        )?;
+        Ok(())
+    }
+
+    fn set_task_status(&self, task_id: &TaskId, status: TaskStatus) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute("UPDATE tasks SET status=$1 WHERE id=$2", &[&codec::task_status_to_str(&status), &task_id.0])?;
+        Ok(())
+    }
+
+    fn set_task_priority(&self, task_id: &TaskId, priority: i32) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        let mut tx = client.transaction()?;
+        tx.execute("UPDATE tasks SET priority=$1 WHERE id=$2", &[&priority, &task_id.0])?;
+        tx.execute(
+            "UPDATE queue_items q SET priority=$1
+             WHERE task_id=$2 AND NOT EXISTS (SELECT 1 FROM queue_finished f WHERE f.queue_id = q.id)",
+            &[&priority, &task_id.0],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_tags(&self, task_id: &TaskId, tags: &[String]) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute("UPDATE tasks SET tags_json=$1 WHERE id=$2", &[&serde_json::to_string(tags)?, &task_id.0])?;
+        Ok(())
+    }
+
+    fn insert_spec_revision(&self, spec_rev: &SpecRevision, now_unix: i64) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO spec_revisions(id, task_id, spec_hash, spec_path, parent_id, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
+            &[
+                &spec_rev.id.0,
+                &spec_rev.task_id.0,
+                &spec_rev.spec_hash,
+                &spec_rev.spec_path,
+                &spec_rev.parent_id.as_ref().map(|p| p.as_str()),
+                &now_unix,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn list_spec_revisions_for_task(&self, task_id: &TaskId) -> Result<Vec<SpecRevision>> {
+        let mut client = self.client.lock().unwrap();
+        let rows = client.query(
+            "SELECT id, task_id, spec_hash, spec_path, parent_id FROM spec_revisions WHERE task_id=$1 ORDER BY created_at, seq",
+            &[&task_id.0],
+        )?;
+        Ok(rows
+            .iter()
+            .map(|r| SpecRevision {
+                id: SpecRevId::from_str(r.get::<_, String>(0)),
+                task_id: TaskId::from_str(r.get::<_, String>(1)),
+                spec_hash: r.get(2),
+                spec_path: r.get(3),
+                parent_id: r.get::<_, Option<String>>(4).map(SpecRevId::from_str),
+            })
+            .collect())
+    }
+
+    fn insert_revision(&self, revision: &Revision, now_unix: i64) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        let anchors: Vec<&str> = revision.anchors.iter().map(|a| a.as_str()).collect();
+        client.execute(
+            "INSERT INTO revisions(id, task_id, spec_rev_id, spec_hash, profile, required_gates_json, required_validations_json, anchors_json, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            &[
+                &revision.id.0,
+                &revision.task_id.0,
+                &revision.spec_rev_id.0,
+                &revision.spec_hash,
+                &revision.profile,
+                &serde_json::to_string(&revision.required_gates)?,
+                &serde_json::to_string(&revision.required_validations)?,
+                &serde_json::to_string(&anchors)?,
+                &now_unix,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_revision(&self, revision_id: &RevisionId) -> Result<Option<Revision>> {
+        let mut client = self.client.lock().unwrap();
+        Ok(Self::query_revisions(&mut *client, "id=$1", &[&revision_id.0])?.pop())
+    }
+
+    fn list_revisions_for_task(&self, task_id: &TaskId) -> Result<Vec<Revision>> {
+        let mut client = self.client.lock().unwrap();
+        Self::query_revisions(&mut *client, "task_id=$1", &[&task_id.0])
+    }
+
+    fn enqueue(&self, item: QueueItem) -> Result<EnqueueResult> {
+        let mut client = self.client.lock().unwrap();
+        let now = crate::now_unix();
+        let lane = codec::lane_to_str(&item.lane);
+        let inserted = client.execute(
+            "INSERT INTO queue_items(id, task_id, revision_id, lane, idempotency_key, priority, enqueued_at, visible_at, attempts, max_attempts, artifact_ref)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+             ON CONFLICT(lane, idempotency_key) DO NOTHING",
+            &[
+                &item.id.0,
+                &item.task_id.0,
+                &item.revision_id.0,
+                &lane,
+                &item.idempotency_key,
+                &item.priority,
+                &now,
+                &item.visible_at_unix,
+                &(item.attempts as i64),
+                &(item.max_attempts as i64),
+                &item.artifact_ref,
+            ],
+        )?;
+        if inserted == 1 {
+            return Ok(EnqueueResult::Inserted(item.id));
+        }
+        // rows are never deleted, so the conflicting item is still there
+        let existing: String = client
+            .query_one("SELECT id FROM queue_items WHERE lane=$1 AND idempotency_key=$2", &[&lane, &item.idempotency_key])?
+            .get(0);
+        Ok(EnqueueResult::Existing(QueueId::from_str(existing)))
+    }
+
+    fn list_queue_items_for_task(&self, task_id: &TaskId) -> Result<Vec<QueueItemRecord>> {
+        let mut client = self.client.lock().unwrap();
+        let rows = client.query(
+            &format!(
+                "SELECT {QUEUE_ITEM_COLUMNS}, f.state, f.reason, f.finished_at FROM queue_items q
+                 LEFT JOIN queue_finished f ON f.queue_id = q.id
+                 WHERE q.task_id=$1 ORDER BY q.enqueued_at, q.seq"
+            ),
+            &[&task_id.0],
+        )?;
+        Ok(rows
+            .iter()
+            .map(|r| QueueItemRecord {
+                item: queue_item_from_row(r),
+                finished: r.get::<_, Option<&str>>(10).map(|state| codec::queue_finish(state, r.get(11), r.get(12))),
+            })
+            .collect())
+    }
+
+    fn reschedule(&self, queue_id: &QueueId, attempts: u32, visible_at_unix: i64) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "UPDATE queue_items SET attempts=$1, visible_at=$2 WHERE id=$3",
+            &[&(attempts as i64), &visible_at_unix, &queue_id.0],
+        )?;
+        Ok(())
+    }
+
+    fn complete_queue_item(&self, queue_id: &QueueId, now_unix: i64) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO queue_finished(queue_id, state, reason, finished_at) VALUES ($1, 'completed', NULL, $2)
+             ON CONFLICT(queue_id) DO NOTHING",
+            &[&queue_id.0, &now_unix],
+        )?;
+        Ok(())
+    }
+
+    fn dead_letter(&self, queue_id: &QueueId, reason: &str, now_unix: i64) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO queue_finished(queue_id, state, reason, finished_at) VALUES ($1, 'dead_letter', $2, $3)
+             ON CONFLICT(queue_id) DO UPDATE SET state=EXCLUDED.state, reason=EXCLUDED.reason, finished_at=EXCLUDED.finished_at",
+            &[&queue_id.0, &reason, &now_unix],
+        )?;
+        Ok(())
+    }
+
+    fn cancel_queue_item(&self, queue_id: &QueueId, reason: &str, now_unix: i64) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "INSERT INTO queue_finished(queue_id, state, reason, finished_at) VALUES ($1, 'cancelled', $2, $3)
+             ON CONFLICT(queue_id) DO NOTHING",
+            &[&queue_id.0, &reason, &now_unix],
+        )?;
+        Ok(())
+    }
+
+    fn try_acquire_lease(&self, queue_id: &QueueId, worker_id: &str, ttl_secs: i64) -> Result<Option<String>> {
+        let mut client = self.client.lock().unwrap();
+        let now = crate::now_unix();
+        let mut tx = client.transaction()?;
+
+        // Row-lock the item while leasing it. A worker on another host leasing it right now
+        // holds that lock: skip instead of queueing behind it, it is taken either way.
+        let leasable = tx.query_opt(
+            "SELECT id FROM queue_items q
+             WHERE id=$1 AND visible_at <= $2 AND NOT EXISTS (SELECT 1 FROM queue_finished f WHERE f.queue_id = q.id)
+             FOR UPDATE SKIP LOCKED",
+            &[&queue_id.0, &now],
+        )?;
+        if leasable.is_none() {
+            return Ok(None);
+        }
+
+        let lease_id = spl_core::LeaseId::new().0;
+        let inserted = tx.execute(
+            "INSERT INTO leases(id, queue_id, worker_id, acquired_at, expires_at) VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT(queue_id) DO NOTHING",
+            &[&lease_id, &queue_id.0, &worker_id, &now, &(now + ttl_secs)],
+        )?;
+        tx.commit()?;
+        Ok((inserted == 1).then_some(lease_id))
+    }
+
+    fn release_lease(&self, queue_id: &QueueId, worker_id: &str) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute("DELETE FROM leases WHERE queue_id=$1 AND worker_id=$2", &[&queue_id.0, &worker_id])?;
+        Ok(())
+    }
+
+    fn renew_lease(&self, queue_id: &QueueId, worker_id: &str, ttl_secs: i64) -> Result<bool> {
+        let mut client = self.client.lock().unwrap();
+        let expires = crate::now_unix() + ttl_secs;
+        let n = client.execute(
+            "UPDATE leases SET expires_at=$1 WHERE queue_id=$2 AND worker_id=$3",
+            &[&expires, &queue_id.0, &worker_id],
+        )?;
+        Ok(n == 1)
+    }
+
+    fn reap_expired_leases(&self, now_unix: i64) -> Result<Vec<Lease>> {
+        let mut client = self.client.lock().unwrap();
+        let rows = client.query(
+            "DELETE FROM leases WHERE expires_at <= $1 RETURNING id, queue_id, worker_id, acquired_at, expires_at",
+            &[&now_unix],
+        )?;
+        Ok(rows.iter().map(lease_from_row).collect())
+    }
+
+    fn try_lock_resource(&self, name: &str, holder: &str, ttl_secs: i64) -> Result<bool> {
+        let mut client = self.client.lock().unwrap();
+        let now = crate::now_unix();
+        // Take the lock if free or expired; re-locking by the same holder extends it.
+        let n = client.execute(
+            "INSERT INTO resource_locks(name, holder, acquired_at, expires_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT(name) DO UPDATE SET holder=EXCLUDED.holder, acquired_at=EXCLUDED.acquired_at, expires_at=EXCLUDED.expires_at
+             WHERE resource_locks.holder=EXCLUDED.holder OR resource_locks.expires_at <= $3",
+            &[&name, &holder, &now, &(now + ttl_secs)],
+        )?;
+        Ok(n == 1)
+    }
+
+    fn release_resource(&self, name: &str, holder: &str) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute("DELETE FROM resource_locks WHERE name=$1 AND holder=$2", &[&name, &holder])?;
+        Ok(())
+    }
+
+    fn create_run(&self, queue_id: &QueueId, run_id: RunId) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        let n = client.execute(
+            "INSERT INTO runs(id, queue_id, task_id, revision_id, lane, started_at, result, current_gate)
+             SELECT $1, id, task_id, revision_id, lane, $2, NULL, NULL FROM queue_items WHERE id=$3",
+            &[&run_id.0, &crate::now_unix(), &queue_id.0],
+        )?;
+        if n == 0 {
+            anyhow::bail!("queue item {} not found for run {}", queue_id.as_str(), run_id.as_str());
+        }
+        Ok(())
+    }
+
+    fn start_gate(&self, run_id: &RunId, gate: &GateName) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute("UPDATE runs SET current_gate=$1 WHERE id=$2", &[&codec::gate_to_str(gate), &run_id.0])?;
+        Ok(())
+    }
+
+    fn record_gate_outcome(&self, record: &GateOutcomeRecord) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        let outcome = &record.outcome;
+        let gate = codec::gate_to_str(&outcome.gate);
+        let mut tx = client.transaction()?;
+        tx.execute(
+            "INSERT INTO gate_outcomes(run_id, gate, status, remediation, started_at, finished_at, artifacts_json)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &record.run_id.0,
+                &gate,
+                &codec::gate_status_to_str(&outcome.status),
+                &outcome.remediation,
+                &record.started_at_unix,
+                &record.finished_at_unix,
+                &serde_json::to_string(&record.artifacts)?,
+            ],
+        )?;
+        tx.execute("UPDATE runs SET current_gate=$1 WHERE id=$2", &[&gate, &record.run_id.0])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn finish_run(&self, run_id: &RunId, result: RunResult, now_unix: i64) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        client.execute(
+            "UPDATE runs SET result=$1, finished_at=$2 WHERE id=$3",
+            &[&codec::result_to_str(&result), &now_unix, &run_id.0],
+        )?;
+        Ok(())
+    }
+
+    fn list_gate_outcomes(&self, run_id: &RunId) -> Result<Vec<GateOutcomeRecord>> {
+        let mut client = self.client.lock().unwrap();
+        let rows = client.query(
+            "SELECT gate, status, remediation, started_at, finished_at, artifacts_json FROM gate_outcomes WHERE run_id=$1 ORDER BY id",
+            &[&run_id.0],
+        )?;
+        let mut records = vec![];
+        for r in rows {
+            let gate: &str = r.get(0);
+            let gate = codec::str_to_gate(gate).with_context(|| format!("unknown gate {:?} in gate_outcomes", gate))?;
+            records.push(GateOutcomeRecord {
+                run_id: run_id.clone(),
+                outcome: GateOutcome { gate, status: codec::str_to_gate_status(r.get(1)), remediation: r.get(2) },
+                started_at_unix: r.get(3),
+                finished_at_unix: r.get(4),
+                artifacts: serde_json::from_str(r.get(5))?,
+            });
+        }
+        Ok(records)
+    }
+
+    fn add_message_with_payload(
+        &self,
+        task_id: &TaskId,
+        ty: MessageType,
+        body_md: &str,
+        payload_json: Option<&str>,
+        now_unix: i64,
+    ) -> Result<()> {
+        let mut client = self.client.lock().unwrap();
+        let id = spl_core::TaskId::new().0;
+        client.execute(
+            "INSERT INTO messages(id, task_id, ty, body_md, payload_json, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
+            &[&id, &task_id.0, &codec::message_type_to_str(&ty), &body_md, &payload_json, &now_unix],
+        )?;
+        Ok(())
+    }
+}
+
+pub fn now_unix() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let dur = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    dur.as_secs() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdb::TestDb;
+    use spl_core::Lane;
+
+    fn storage(db: &TestDb) -> PostgresStorage {
+        PostgresStorage::connect_with(&db.config).unwrap()
+    }
+
+    #[test]
+    fn postgres_storage_contract() {
+        let Some(db) = TestDb::create() else { return };
+        spl_storage::run_storage_contract_suite(&storage(&db)).unwrap();
+    }
+
+    #[test]
+    fn workers_on_separate_connections_never_share_a_lease() {
+        let Some(db) = TestDb::create() else { return };
+        let store = storage(&db);
+        store.insert_task(Task { id: TaskId::from_str("t1"), title: "t".into(), status: TaskStatus::Ready, priority: 0, tags: vec![] }).unwrap();
+        store
+            .insert_spec_revision(
+                &SpecRevision {
+                    id: SpecRevId::from_str("s1"),
+                    task_id: TaskId::from_str("t1"),
+                    spec_hash: "h".into(),
+                    spec_path: "p".into(),
+                    parent_id: None,
+                },
+                0,
+            )
+            .unwrap();
+        store
+            .insert_revision(
+                &Revision {
+                    id: RevisionId::from_str("r1"),
+                    task_id: TaskId::from_str("t1"),
+                    spec_rev_id: SpecRevId::from_str("s1"),
+                    spec_hash: "h".into(),
+                    profile: "standard".into(),
+                    required_gates: vec![],
+                    required_validations: vec![],
+                    anchors: vec![],
+                },
+                0,
+            )
+            .unwrap();
+        let items: Vec<QueueId> = (0..8).map(|n| QueueId::from_str(format!("q{}", n))).collect();
+        for id in &items {
+            store
+                .enqueue(QueueItem {
+                    id: id.clone(),
+                    task_id: TaskId::from_str("t1"),
+                    revision_id: RevisionId::from_str("r1"),
+                    lane: Lane::Execute,
+                    visible_at_unix: 0,
+                    attempts: 0,
+                    max_attempts: 3,
+                    priority: 0,
+                    idempotency_key: id.as_str().to_string(),
+                    artifact_ref: None,
+                })
+                .unwrap();
+        }
+
+        // every worker (own connection, as on another host) races for every item
+        let won: Vec<Vec<QueueId>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|w| {
+                    let (db, items) = (&db, &items);
+                    scope.spawn(move || {
+                        let store = storage(db);
+                        let worker = format!("w{}", w);
+                        items.iter().filter(|id| store.try_acquire_lease(id, &worker, 60).unwrap().is_some()).cloned().collect()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+
+        let mut leased: Vec<QueueId> = won.into_iter().flatten().collect();
+        leased.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        assert_eq!(leased, items, "each item leased exactly once");
+        assert_eq!(store.load_snapshot(now_unix()).unwrap().leases.len(), items.len());
+    }
+}
@@ -0,0 +1,93 @@
+//! Throwaway databases for the postgres tests.
+//!
+//! With `SPL_TEST_POSTGRES_URL` set, each test gets a fresh database on that server
+//! (the role needs CREATEDB). Otherwise a private cluster is started with `initdb` and
+//! `pg_ctl` in a temp dir, listening only on a unix socket there. When neither works
+//! (no postgres binaries, or running as root, which initdb refuses) the test is skipped.
+
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use postgres::{Client, Config, NoTls};
+use tempfile::TempDir;
+
+pub const TEST_URL_ENV: &str = "SPL_TEST_POSTGRES_URL";
+
+pub struct TestDb {
+    pub config: Config,
+    server: Server,
+}
+
+enum Server {
+    /// Database created on the `SPL_TEST_POSTGRES_URL` server; dropped afterwards.
+    External { admin: Box<Config> },
+    /// Private cluster; stopped (and its dir removed) afterwards.
+    Cluster { dir: TempDir },
+}
+
+impl TestDb {
+    pub fn create() -> Option<Self> {
+        let db = match std::env::var(TEST_URL_ENV) {
+            Ok(url) => Self::on_server(&url),
+            Err(_) => Self::in_temp_cluster(),
+        };
+        if let Err(err) = &db {
+            eprintln!("skipping postgres test: {:#}", err);
+        }
+        db.ok()
+    }
+
+    pub fn client(&self) -> Client {
+        self.config.connect(NoTls).expect("connect to test postgres")
+    }
+
+    fn on_server(url: &str) -> anyhow::Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let admin: Config = url.parse()?;
+        let name = format!("spl_test_{}_{}", std::process::id(), NEXT.fetch_add(1, Ordering::SeqCst));
+        admin.connect(NoTls)?.batch_execute(&format!("CREATE DATABASE {}", name))?;
+        let mut config = admin.clone();
+        config.dbname(&name);
+        Ok(Self { config, server: Server::External { admin: Box::new(admin) } })
+    }
+
+    fn in_temp_cluster() -> anyhow::Result<Self> {
+        let dir = tempfile::Builder::new().prefix("spl-pg").tempdir()?;
+        let data = dir.path().join("data");
+        run(Command::new("initdb").arg("-D").arg(&data).args(["-A", "trust", "-U", "spl", "--no-sync"]))?;
+        let opts = format!("-k {} -c listen_addresses='' -c fsync=off", dir.path().display());
+        run(Command::new("pg_ctl").arg("-D").arg(&data).args(["-o", &opts, "-w", "-l"]).arg(dir.path().join("log")).arg("start"))?;
+
+        let mut config = Config::new();
+        config.host_path(dir.path()).user("spl").dbname("postgres");
+        Ok(Self { config, server: Server::Cluster { dir } })
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        match &self.server {
+            Server::External { admin } => {
+                if let (Ok(mut client), Some(name)) = (admin.connect(NoTls), self.config.get_dbname()) {
+                    let _ = client.batch_execute(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name));
+                }
+            }
+            Server::Cluster { dir } => {
+                let _ = stop_cluster(dir.path());
+            }
+        }
+    }
+}
+
+fn stop_cluster(root: &Path) -> anyhow::Result<()> {
+    run(Command::new("pg_ctl").arg("-D").arg(root.join("data")).args(["-m", "immediate", "-w", "stop"]))
+}
+
+fn run(cmd: &mut Command) -> anyhow::Result<()> {
+    let out = cmd.output().map_err(|e| anyhow::anyhow!("run {:?}: {}", cmd.get_program(), e))?;
+    if !out.status.success() {
+        anyhow::bail!("{:?} failed: {}", cmd.get_program(), String::from_utf8_lossy(&out.stderr).trim());
+    }
+    Ok(())
+}
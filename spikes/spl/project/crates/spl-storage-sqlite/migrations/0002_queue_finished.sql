@@ -0,0 +1,8 @@
+-- Items leave the queue here: completed normally, or dead-lettered after max_attempts.
+CREATE TABLE queue_finished (
+  queue_id TEXT PRIMARY KEY,
+  state TEXT NOT NULL,
+  reason TEXT,
+  finished_at INTEGER NOT NULL,
+  FOREIGN KEY(queue_id) REFERENCES queue_items(id)
+);
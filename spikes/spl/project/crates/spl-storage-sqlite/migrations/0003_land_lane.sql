@@ -0,0 +1,10 @@
+-- Land items carry the exported patch by artifact ref.
+ALTER TABLE queue_items ADD COLUMN artifact_ref TEXT;
+
+-- Project-wide named mutexes (e.g. "land": one landing at a time across workers).
+CREATE TABLE resource_locks (
+  name TEXT PRIMARY KEY,
+  holder TEXT NOT NULL,
+  acquired_at INTEGER NOT NULL,
+  expires_at INTEGER NOT NULL
+);
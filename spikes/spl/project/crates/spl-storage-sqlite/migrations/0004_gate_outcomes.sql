@@ -0,0 +1,16 @@
+ALTER TABLE runs ADD COLUMN finished_at INTEGER;
+
+-- Per-run gate history; id orders gates by completion.
+CREATE TABLE gate_outcomes (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  run_id TEXT NOT NULL,
+  gate TEXT NOT NULL,
+  status TEXT NOT NULL,
+  remediation TEXT,
+  started_at INTEGER NOT NULL,
+  finished_at INTEGER NOT NULL,
+  artifacts_json TEXT NOT NULL,
+  FOREIGN KEY(run_id) REFERENCES runs(id)
+);
+
+CREATE INDEX gate_outcomes_run_idx ON gate_outcomes(run_id);
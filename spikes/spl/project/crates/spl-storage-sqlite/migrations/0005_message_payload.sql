@@ -0,0 +1,2 @@
+-- Machine-readable form of a message, e.g. an ASK packet's JSON.
+ALTER TABLE messages ADD COLUMN payload_json TEXT;
@@ -0,0 +1,2 @@
+-- An amended spec revision points at the one it supersedes.
+ALTER TABLE spec_revisions ADD COLUMN parent_id TEXT REFERENCES spec_revisions(id);
@@ -1,3 +1,5 @@
+pub mod migrate;
 pub mod storage;
 
+pub use migrate::*;
 pub use storage::*;
@@ -0,0 +1,188 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, TransactionBehavior};
+
+pub use spl_storage::{Migration, MigrationPlan};
+
+/// Every schema step, oldest first. Append only: a shipped migration is never edited.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "init", sql: include_str!("../migrations/0001_init.sql") },
+    Migration { version: 2, name: "queue_finished", sql: include_str!("../migrations/0002_queue_finished.sql") },
+    Migration { version: 3, name: "land_lane", sql: include_str!("../migrations/0003_land_lane.sql") },
+    Migration { version: 4, name: "gate_outcomes", sql: include_str!("../migrations/0004_gate_outcomes.sql") },
+    Migration { version: 5, name: "message_payload", sql: include_str!("../migrations/0005_message_payload.sql") },
+    Migration { version: 6, name: "spec_parent", sql: include_str!("../migrations/0006_spec_parent.sql") },
+];
+
+/// `meta` key holding the applied schema version.
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+pub fn latest_schema_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Plan migrations for `conn` without changing it. Errors if the database was written by a
+/// newer schema than this build knows.
+pub fn plan_migrations(conn: &Connection) -> Result<MigrationPlan> {
+    let (current, legacy) = match recorded_version(conn)? {
+        Some(version) => (version, false),
+        None => match legacy_version(conn)? {
+            Some(version) => (version, true),
+            None => (0, false),
+        },
+    };
+    let latest = latest_schema_version();
+    if current > latest {
+        return Err(anyhow!(
+            "database schema version {} is newer than this build supports ({}); upgrade spl before opening it",
+            current,
+            latest
+        ));
+    }
+    let pending = MIGRATIONS.iter().filter(|m| m.version > current).copied().collect();
+    Ok(MigrationPlan { current, legacy, pending })
+}
+
+/// Plan migrations for the database at `db_path` without creating or changing it.
+pub fn plan_migrations_at(db_path: &Path) -> Result<MigrationPlan> {
+    if !db_path.exists() {
+        return Ok(MigrationPlan { current: 0, legacy: false, pending: MIGRATIONS.to_vec() });
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("open sqlite db {}", db_path.display()))?;
+    plan_migrations(&conn)
+}
+
+/// Bring the database up to `latest_schema_version`. Safe for several processes opening the
+/// same file: each step re-reads the version under the write lock and skips if already applied.
+pub fn migrate(conn: &mut Connection) -> Result<MigrationPlan> {
+    let plan = plan_migrations(conn)?;
+    if plan.legacy && plan.pending.is_empty() {
+        set_version(conn, plan.current)?;
+    }
+    for m in &plan.pending {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let current = match recorded_version(&tx)? {
+            Some(version) => version,
+            None => legacy_version(&tx)?.unwrap_or(0),
+        };
+        if current >= m.version {
+            continue;
+        }
+        tx.execute_batch(m.sql).with_context(|| format!("apply migration {:04}_{}", m.version, m.name))?;
+        set_version(&tx, m.version)?;
+        tx.commit()?;
+    }
+    Ok(plan)
+}
+
+fn set_version(conn: &Connection, version: u32) -> Result<()> {
+    conn.execute(
+        "INSERT INTO meta(key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value=excluded.value",
+        params![SCHEMA_VERSION_KEY, version.to_string()],
+    )?;
+    Ok(())
+}
+
+fn recorded_version(conn: &Connection) -> Result<Option<u32>> {
+    if !table_exists(conn, "meta")? {
+        return Ok(None);
+    }
+    let value: Option<String> =
+        conn.query_row("SELECT value FROM meta WHERE key=?1", params![SCHEMA_VERSION_KEY], |r| r.get(0)).optional()?;
+    value
+        .map(|v| v.parse::<u32>().with_context(|| format!("bad {} in meta: {:?}", SCHEMA_VERSION_KEY, v)))
+        .transpose()
+}
+
+/// Databases from before versioning re-ran the whole schema on every open, so the newest
+/// table they have tells how far they got. None for a new (empty) database.
+fn legacy_version(conn: &Connection) -> Result<Option<u32>> {
+    if !table_exists(conn, "tasks")? {
+        return Ok(None);
+    }
+    for (table, version) in [("gate_outcomes", 4), ("resource_locks", 3), ("queue_finished", 2)] {
+        if table_exists(conn, table)? {
+            return Ok(Some(version));
+        }
+    }
+    Ok(Some(1))
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let n: i64 = conn.query_row("SELECT COUNT(1) FROM sqlite_master WHERE type='table' AND name=?1", params![table], |r| r.get(0))?;
+    Ok(n > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn version(conn: &Connection) -> Option<u32> {
+        recorded_version(conn).unwrap()
+    }
+
+    #[test]
+    fn new_db_gets_every_migration_once() {
+        let dir = tempdir().unwrap();
+        let mut conn = Connection::open(dir.path().join("spl.db")).unwrap();
+
+        let plan = migrate(&mut conn).unwrap();
+        assert_eq!((plan.current, plan.legacy, plan.pending.len()), (0, false, MIGRATIONS.len()));
+        assert_eq!(version(&conn), Some(latest_schema_version()));
+
+        let again = migrate(&mut conn).unwrap();
+        assert!(again.pending.is_empty());
+    }
+
+    #[test]
+    fn legacy_db_is_baselined_and_keeps_its_rows() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("spl.db");
+        {
+            // written by a build that ran 0001 on every open and knew nothing of versions
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+            conn.execute("INSERT INTO tasks(id, title, status, priority, tags_json) VALUES ('t1', 'old', 'ready', 0, '[]')", [])
+                .unwrap();
+        }
+
+        let dry = plan_migrations_at(&db_path).unwrap();
+        assert_eq!((dry.current, dry.legacy), (1, true));
+        assert_eq!(dry.pending.iter().map(|m| m.version).collect::<Vec<_>>(), vec![2, 3, 4, 5, 6]);
+        assert_eq!(version(&Connection::open(&db_path).unwrap()), None, "dry run changes nothing");
+
+        let mut conn = Connection::open(&db_path).unwrap();
+        migrate(&mut conn).unwrap();
+        assert_eq!(version(&conn), Some(latest_schema_version()));
+        let title: String = conn.query_row("SELECT title FROM tasks WHERE id='t1'", [], |r| r.get(0)).unwrap();
+        assert_eq!(title, "old");
+        assert!(table_exists(&conn, "gate_outcomes").unwrap());
+    }
+
+    #[test]
+    fn refuses_db_from_newer_schema() {
+        let dir = tempdir().unwrap();
+        let mut conn = Connection::open(dir.path().join("spl.db")).unwrap();
+        migrate(&mut conn).unwrap();
+        set_version(&conn, latest_schema_version() + 1).unwrap();
+
+        let err = migrate(&mut conn).unwrap_err().to_string();
+        assert!(err.contains("newer than this build supports"), "{}", err);
+    }
+
+    #[test]
+    fn failed_migration_rolls_back_its_step() {
+        let dir = tempdir().unwrap();
+        let mut conn = Connection::open(dir.path().join("spl.db")).unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        set_version(&conn, 1).unwrap();
+        // a stray table makes 0002's CREATE TABLE fail
+        conn.execute_batch("CREATE TABLE queue_finished (x INTEGER);").unwrap();
+
+        assert!(migrate(&mut conn).is_err());
+        assert_eq!(version(&conn), Some(1));
+    }
+}
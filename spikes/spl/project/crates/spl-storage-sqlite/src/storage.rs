@@ -2,10 +2,13 @@ use std::path::Path;
 use std::sync::Mutex;
 
 use anyhow::{Context, Result};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, Transaction, TransactionBehavior};
 use serde_json::json;
-use spl_core::{GateOutcome, Lane, Lease, Message, MessageType, QueueId, QueueItem, Run, RunId, Snapshot, Task, TaskId, TaskStatus};
-use spl_storage::Storage;
+use spl_core::{
+    AnchorId, GateName, GateOutcome, GateOutcomeRecord, Lease, Message, MessageType, QueueId, QueueItem,
+    Revision, RevisionId, Run, RunId, RunResult, Snapshot, SpecRevId, SpecRevision, Task, TaskId, TaskStatus,
+};
+use spl_storage::{codec, EnqueueResult, QueueItemRecord, Storage};
 
 pub struct SqliteStorage {
     conn: Mutex<Connection>,
@@ -16,87 +19,73 @@ impl SqliteStorage {
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent).ok();
         }
-        let conn = Connection::open(db_path).with_context(|| format!("open sqlite db {}", db_path.display()))?;
+        let mut conn = Connection::open(db_path).with_context(|| format!("open sqlite db {}", db_path.display()))?;
         conn.execute_batch("PRAGMA foreign_keys=ON;")?;
-        // init schema
-        let init_sql = include_str!("../migrations/0001_init.sql");
-        conn.execute_batch(init_sql)?;
+        // several workers (threads or processes) share the db file
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        crate::migrate(&mut conn).with_context(|| format!("migrate sqlite db {}", db_path.display()))?;
         Ok(Self { conn: Mutex::new(conn) })
     }
 
-    fn status_to_str(s: &TaskStatus) -> &'static str {
-        match s {
-            TaskStatus::Draft => "draft",
-            TaskStatus::Ready => "ready",
-            TaskStatus::BlockedHitl => "blocked_hitl",
-            TaskStatus::BlockedFailure => "blocked_failure",
-            TaskStatus::Done => "done",
-        }
-    }
-
-    fn str_to_status(s: &str) -> TaskStatus {
-        match s {
-            "draft" => TaskStatus::Draft,
-            "ready" => TaskStatus::Ready,
-            "blocked_hitl" => TaskStatus::BlockedHitl,
-            "blocked_failure" => TaskStatus::BlockedFailure,
-            "done" => TaskStatus::Done,
-            _ => TaskStatus::Draft,
-        }
-    }
-
-    fn lane_to_str(l: &Lane) -> &'static str {
-        match l {
-            Lane::Execute => "execute",
-            Lane::Land => "land",
-        }
-    }
-
-    fn str_to_lane(s: &str) -> Lane {
-        match s {
-            "land" => Lane::Land,
-            _ => Lane::Execute,
-        }
-    }
-
-    pub fn insert_spec_revision(&self, spec_rev_id: &str, task_id: &str, spec_hash: &str, spec_path: &str, created_at: i64) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO spec_revisions(id, task_id, spec_hash, spec_path, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![spec_rev_id, task_id, spec_hash, spec_path, created_at],
-        )?;
-        Ok(())
+    /// Columns `queue_item_from_row` expects, in order.
+    const QUEUE_ITEM_COLUMNS: &'static str =
+        "q.id, q.task_id, q.revision_id, q.lane, q.visible_at, q.attempts, q.max_attempts, q.priority, q.idempotency_key, q.artifact_ref";
+
+    fn queue_item_from_row(r: &rusqlite::Row) -> rusqlite::Result<QueueItem> {
+        Ok(QueueItem {
+            id: QueueId::from_str(r.get::<_, String>(0)?),
+            task_id: TaskId::from_str(r.get::<_, String>(1)?),
+            revision_id: spl_core::RevisionId::from_str(r.get::<_, String>(2)?),
+            lane: codec::str_to_lane(&r.get::<_, String>(3)?),
+            visible_at_unix: r.get(4)?,
+            attempts: r.get::<_, i64>(5)? as u32,
+            max_attempts: r.get::<_, i64>(6)? as u32,
+            priority: r.get(7)?,
+            idempotency_key: r.get(8)?,
+            artifact_ref: r.get(9)?,
+        })
     }
 
-    pub fn insert_revision_row(
-        &self,
-        revision_id: &str,
-        task_id: &str,
-        spec_rev_id: &str,
-        spec_hash: &str,
-        profile: &str,
-        required_gates_json: &str,
-        required_validations_json: &str,
-        anchors_json: &str,
-        created_at: i64,
-    ) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO revisions(id, task_id, spec_rev_id, spec_hash, profile, required_gates_json, required_validations_json, anchors_json, created_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            params![
-                revision_id,
-                task_id,
-                spec_rev_id,
+    /// Revisions matching `filter` (a WHERE clause over `revisions`), oldest first.
+    fn query_revisions(conn: &Connection, filter: &str, args: &[&dyn rusqlite::ToSql]) -> Result<Vec<Revision>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, task_id, spec_rev_id, spec_hash, profile, required_gates_json, required_validations_json, anchors_json
+             FROM revisions WHERE {} ORDER BY created_at, rowid",
+            filter
+        ))?;
+        let rows = stmt.query_map(args, |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, String>(2)?,
+                r.get::<_, String>(3)?,
+                r.get::<_, String>(4)?,
+                r.get::<_, String>(5)?,
+                r.get::<_, String>(6)?,
+                r.get::<_, String>(7)?,
+            ))
+        })?;
+
+        let mut revisions = vec![];
+        for row in rows {
+            let (id, task_id, spec_rev_id, spec_hash, profile, gates_json, validations_json, anchors_json) = row?;
+            let required_gates: Vec<GateName> =
+                serde_json::from_str(&gates_json).with_context(|| format!("parse required_gates_json for revision {}", id))?;
+            let required_validations: Vec<String> = serde_json::from_str(&validations_json).unwrap_or_default();
+            let anchors: Vec<String> = serde_json::from_str(&anchors_json).unwrap_or_default();
+            revisions.push(Revision {
+                id: RevisionId::from_str(id),
+                task_id: TaskId::from_str(task_id),
+                spec_rev_id: SpecRevId::from_str(spec_rev_id),
                 spec_hash,
                 profile,
-                required_gates_json,
-                required_validations_json,
-                anchors_json,
-                created_at
-            ],
-        )?;
-        Ok(())
+                required_gates,
+                required_validations,
+                anchors: anchors.into_iter().map(AnchorId::from_str).collect(),
+            });
+        }
+        Ok(revisions)
     }
 }
 
@@ -113,7 +102,7 @@ impl Storage for SqliteStorage {
                 Ok(Task {
                     id: TaskId::from_str(r.get::<_, String>(0)?),
                     title: r.get(1)?,
-                    status: Self::str_to_status(&r.get::<_, String>(2)?),
+                    status: codec::str_to_task_status(&r.get::<_, String>(2)?),
                     priority: r.get(3)?,
                     tags,
                 })
@@ -125,23 +114,12 @@ impl Storage for SqliteStorage {
 
         let mut queue = vec![];
         {
-            let mut stmt = conn.prepare(
-                "SELECT id, task_id, revision_id, lane, visible_at, attempts, max_attempts, priority, idempotency_key
-                 FROM queue_items WHERE visible_at <= ?1"
-            )?;
-            let rows = stmt.query_map([now_unix], |r| {
-                Ok(QueueItem {
-                    id: QueueId::from_str(r.get::<_, String>(0)?),
-                    task_id: TaskId::from_str(r.get::<_, String>(1)?),
-                    revision_id: spl_core::RevisionId::from_str(r.get::<_, String>(2)?),
-                    lane: Self::str_to_lane(&r.get::<_, String>(3)?),
-                    visible_at_unix: r.get(4)?,
-                    attempts: r.get::<_, i64>(5)? as u32,
-                    max_attempts: r.get::<_, i64>(6)? as u32,
-                    priority: r.get(7)?,
-                    idempotency_key: r.get(8)?,
-                })
-            })?;
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM queue_items q
+                 WHERE visible_at <= ?1 AND id NOT IN (SELECT queue_id FROM queue_finished)",
+                Self::QUEUE_ITEM_COLUMNS
+            ))?;
+            let rows = stmt.query_map([now_unix], Self::queue_item_from_row)?;
             for row in rows {
                 queue.push(row?);
             }
@@ -164,18 +142,22 @@ impl Storage for SqliteStorage {
             }
         }
 
+        let revisions = Self::query_revisions(&conn, "1=1", &[])?;
+
         let mut runs = vec![];
         {
-            let mut stmt = conn.prepare("SELECT id, task_id, revision_id, lane, result, current_gate FROM runs")?;
+            let mut stmt = conn.prepare("SELECT id, queue_id, task_id, revision_id, lane, result, current_gate FROM runs")?;
             let rows = stmt.query_map([], |r| {
-                let result: Option<String> = r.get(4)?;
+                let result: Option<String> = r.get(5)?;
+                let current_gate: Option<String> = r.get(6)?;
                 Ok(Run {
                     id: RunId::from_str(r.get::<_, String>(0)?),
-                    task_id: TaskId::from_str(r.get::<_, String>(1)?),
-                    revision_id: spl_core::RevisionId::from_str(r.get::<_, String>(2)?),
-                    lane: Self::str_to_lane(&r.get::<_, String>(3)?),
-                    result: result.map(|_| spl_core::RunResult::Pass), // placeholder mapping
-                    current_gate: None,
+                    queue_id: QueueId::from_str(r.get::<_, String>(1)?),
+                    task_id: TaskId::from_str(r.get::<_, String>(2)?),
+                    revision_id: spl_core::RevisionId::from_str(r.get::<_, String>(3)?),
+                    lane: codec::str_to_lane(&r.get::<_, String>(4)?),
+                    result: result.as_deref().and_then(codec::str_to_result),
+                    current_gate: current_gate.as_deref().and_then(codec::str_to_gate),
                 })
             })?;
             for row in rows {
@@ -185,19 +167,14 @@ impl Storage for SqliteStorage {
 
         let mut messages = vec![];
         {
-            let mut stmt = conn.prepare("SELECT id, task_id, ty, body_md, created_at FROM messages")?;
+            let mut stmt = conn.prepare("SELECT id, task_id, ty, body_md, created_at, payload_json FROM messages ORDER BY created_at, rowid")?;
             let rows = stmt.query_map([], |r| {
                 Ok(Message {
                     id: r.get(0)?,
                     task_id: TaskId::from_str(r.get::<_, String>(1)?),
-                    ty: match r.get::<_, String>(2)?.as_str() {
-                        "ask" => MessageType::Ask,
-                        "decision" => MessageType::Decision,
-                        "reset" => MessageType::Reset,
-                        "review" => MessageType::Review,
-                        _ => MessageType::Update,
-                    },
+                    ty: codec::str_to_message_type(&r.get::<_, String>(2)?),
                     body_md: r.get(3)?,
+                    payload_json: r.get(5)?,
                     created_at_unix: r.get(4)?,
                 })
             })?;
@@ -208,8 +185,9 @@ impl Storage for SqliteStorage {
 
         Ok(Snapshot {
             now_unix,
+            vcs: Default::default(),
             tasks,
-            revisions: vec![],
+            revisions,
             queue,
             leases,
             runs,
@@ -222,36 +200,185 @@ impl Storage for SqliteStorage {
         let tags_json = serde_json::to_string(&task.tags).unwrap_or_else(|_| "[]".to_string());
         conn.execute(
             "INSERT INTO tasks(id, title, status, priority, tags_json) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![task.id.0, task.title, Self::status_to_str(&task.status), task.priority, tags_json],
+            params![task.id.0, task.title, codec::task_status_to_str(&task.status), task.priority, tags_json],
         )?;
         Ok(())
     }
 
     fn set_task_status(&self, task_id: &TaskId, status: TaskStatus) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute("UPDATE tasks SET status=?1 WHERE id=?2", params![Self::status_to_str(&status), task_id.0])?;
+        conn.execute("UPDATE tasks SET status=?1 WHERE id=?2", params![codec::task_status_to_str(&status), task_id.0])?;
         Ok(())
     }
 
-    fn enqueue(&self, item: QueueItem) -> Result<()> {
+    fn set_task_priority(&self, task_id: &TaskId, priority: i32) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)?;
+        tx.execute("UPDATE tasks SET priority=?1 WHERE id=?2", params![priority, task_id.0])?;
+        tx.execute(
+            "UPDATE queue_items SET priority=?1 WHERE task_id=?2 AND id NOT IN (SELECT queue_id FROM queue_finished)",
+            params![priority, task_id.0],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_task_tags(&self, task_id: &TaskId, tags: &[String]) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE tasks SET tags_json=?1 WHERE id=?2", params![serde_json::to_string(tags)?, task_id.0])?;
+        Ok(())
+    }
+
+    fn insert_spec_revision(&self, spec_rev: &SpecRevision, now_unix: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        let now = crate::now_unix();
         conn.execute(
-            "INSERT INTO queue_items(id, task_id, revision_id, lane, idempotency_key, priority, enqueued_at, visible_at, attempts, max_attempts)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT INTO spec_revisions(id, task_id, spec_hash, spec_path, parent_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                spec_rev.id.0,
+                spec_rev.task_id.0,
+                spec_rev.spec_hash,
+                spec_rev.spec_path,
+                spec_rev.parent_id.as_ref().map(|p| p.as_str()),
+                now_unix
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn list_spec_revisions_for_task(&self, task_id: &TaskId) -> Result<Vec<SpecRevision>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, task_id, spec_hash, spec_path, parent_id FROM spec_revisions WHERE task_id=?1 ORDER BY created_at, rowid",
+        )?;
+        let rows = stmt.query_map(params![task_id.0], |r| {
+            Ok(SpecRevision {
+                id: SpecRevId::from_str(r.get::<_, String>(0)?),
+                task_id: TaskId::from_str(r.get::<_, String>(1)?),
+                spec_hash: r.get(2)?,
+                spec_path: r.get(3)?,
+                parent_id: r.get::<_, Option<String>>(4)?.map(SpecRevId::from_str),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    fn insert_revision(&self, revision: &Revision, now_unix: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let anchors: Vec<&str> = revision.anchors.iter().map(|a| a.as_str()).collect();
+        conn.execute(
+            "INSERT INTO revisions(id, task_id, spec_rev_id, spec_hash, profile, required_gates_json, required_validations_json, anchors_json, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                revision.id.0,
+                revision.task_id.0,
+                revision.spec_rev_id.0,
+                revision.spec_hash,
+                revision.profile,
+                serde_json::to_string(&revision.required_gates)?,
+                serde_json::to_string(&revision.required_validations)?,
+                serde_json::to_string(&anchors)?,
+                now_unix
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_revision(&self, revision_id: &RevisionId) -> Result<Option<Revision>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(Self::query_revisions(&conn, "id=?1", &[&revision_id.0])?.pop())
+    }
+
+    fn list_revisions_for_task(&self, task_id: &TaskId) -> Result<Vec<Revision>> {
+        let conn = self.conn.lock().unwrap();
+        Self::query_revisions(&conn, "task_id=?1", &[&task_id.0])
+    }
+
+    fn enqueue(&self, item: QueueItem) -> Result<EnqueueResult> {
+        let conn = self.conn.lock().unwrap();
+        let now = crate::now_unix();
+        let lane = codec::lane_to_str(&item.lane);
+        let inserted = conn.execute(
+            "INSERT INTO queue_items(id, task_id, revision_id, lane, idempotency_key, priority, enqueued_at, visible_at, attempts, max_attempts, artifact_ref)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(lane, idempotency_key) DO NOTHING",
             params![
                 item.id.0,
                 item.task_id.0,
                 item.revision_id.0,
-                Self::lane_to_str(&item.lane),
+                lane,
                 item.idempotency_key,
                 item.priority,
                 now,
                 item.visible_at_unix,
                 item.attempts as i64,
-                item.max_attempts as i64
+                item.max_attempts as i64,
+                item.artifact_ref
             ],
         )?;
+        if inserted == 1 {
+            return Ok(EnqueueResult::Inserted(item.id));
+        }
+        // rows are never deleted, so the conflicting item is still there
+        let existing: String = conn.query_row(
+            "SELECT id FROM queue_items WHERE lane=?1 AND idempotency_key=?2",
+            params![lane, item.idempotency_key],
+            |r| r.get(0),
+        )?;
+        Ok(EnqueueResult::Existing(QueueId::from_str(existing)))
+    }
+
+    fn list_queue_items_for_task(&self, task_id: &TaskId) -> Result<Vec<QueueItemRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, f.state, f.reason, f.finished_at FROM queue_items q
+             LEFT JOIN queue_finished f ON f.queue_id = q.id
+             WHERE q.task_id=?1 ORDER BY q.enqueued_at, q.rowid",
+            Self::QUEUE_ITEM_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![task_id.0], |r| {
+            let state: Option<String> = r.get(10)?;
+            let finished = match state {
+                Some(state) => Some(codec::queue_finish(&state, r.get(11)?, r.get(12)?)),
+                None => None,
+            };
+            Ok(QueueItemRecord { item: Self::queue_item_from_row(r)?, finished })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    fn reschedule(&self, queue_id: &QueueId, attempts: u32, visible_at_unix: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE queue_items SET attempts=?1, visible_at=?2 WHERE id=?3",
+            params![attempts as i64, visible_at_unix, queue_id.0],
+        )?;
+        Ok(())
+    }
+
+    fn complete_queue_item(&self, queue_id: &QueueId, now_unix: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO queue_finished(queue_id, state, reason, finished_at) VALUES (?1, 'completed', NULL, ?2)",
+            params![queue_id.0, now_unix],
+        )?;
+        Ok(())
+    }
+
+    fn dead_letter(&self, queue_id: &QueueId, reason: &str, now_unix: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO queue_finished(queue_id, state, reason, finished_at) VALUES (?1, 'dead_letter', ?2, ?3)",
+            params![queue_id.0, reason, now_unix],
+        )?;
+        Ok(())
+    }
+
+    fn cancel_queue_item(&self, queue_id: &QueueId, reason: &str, now_unix: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO queue_finished(queue_id, state, reason, finished_at) VALUES (?1, 'cancelled', ?2, ?3)",
+            params![queue_id.0, reason, now_unix],
+        )?;
         Ok(())
     }
 
@@ -260,12 +387,12 @@ impl Storage for SqliteStorage {
         let now = crate::now_unix();
         let expires = now + ttl_secs;
 
-        let tx = conn.unchecked_transaction()?;
-        tx.execute("DELETE FROM leases WHERE expires_at <= ?1", params![now])?;
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)?;
 
         // Ensure item exists and visible (cheap check)
         let exists: i64 = tx.query_row(
-            "SELECT COUNT(1) FROM queue_items WHERE id=?1 AND visible_at <= ?2",
+            "SELECT COUNT(1) FROM queue_items
+             WHERE id=?1 AND visible_at <= ?2 AND id NOT IN (SELECT queue_id FROM queue_finished)",
             params![queue_id.0, now],
             |r| r.get(0),
         )?;
@@ -299,6 +426,59 @@ impl Storage for SqliteStorage {
         Ok(())
     }
 
+    fn renew_lease(&self, queue_id: &QueueId, worker_id: &str, ttl_secs: i64) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let expires = crate::now_unix() + ttl_secs;
+        let n = conn.execute(
+            "UPDATE leases SET expires_at=?1 WHERE queue_id=?2 AND worker_id=?3",
+            params![expires, queue_id.0, worker_id],
+        )?;
+        Ok(n == 1)
+    }
+
+    fn reap_expired_leases(&self, now_unix: i64) -> Result<Vec<Lease>> {
+        let conn = self.conn.lock().unwrap();
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)?;
+        let mut reaped = vec![];
+        {
+            let mut stmt = tx.prepare("SELECT id, queue_id, worker_id, acquired_at, expires_at FROM leases WHERE expires_at <= ?1")?;
+            let rows = stmt.query_map([now_unix], |r| {
+                Ok(Lease {
+                    id: spl_core::LeaseId::from_str(r.get::<_, String>(0)?),
+                    queue_id: QueueId::from_str(r.get::<_, String>(1)?),
+                    worker_id: r.get(2)?,
+                    acquired_at_unix: r.get(3)?,
+                    expires_at_unix: r.get(4)?,
+                })
+            })?;
+            for row in rows {
+                reaped.push(row?);
+            }
+        }
+        tx.execute("DELETE FROM leases WHERE expires_at <= ?1", params![now_unix])?;
+        tx.commit()?;
+        Ok(reaped)
+    }
+
+    fn try_lock_resource(&self, name: &str, holder: &str, ttl_secs: i64) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let now = crate::now_unix();
+        // Take the lock if free or expired; re-locking by the same holder extends it.
+        let n = conn.execute(
+            "INSERT INTO resource_locks(name, holder, acquired_at, expires_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name) DO UPDATE SET holder=excluded.holder, acquired_at=excluded.acquired_at, expires_at=excluded.expires_at
+             WHERE resource_locks.holder=excluded.holder OR resource_locks.expires_at <= ?3",
+            params![name, holder, now, now + ttl_secs],
+        )?;
+        Ok(n == 1)
+    }
+
+    fn release_resource(&self, name: &str, holder: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM resource_locks WHERE name=?1 AND holder=?2", params![name, holder])?;
+        Ok(())
+    }
+
     fn create_run(&self, queue_id: &QueueId, run_id: RunId) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         let now = crate::now_unix();
@@ -317,29 +497,84 @@ impl Storage for SqliteStorage {
         Ok(())
     }
 
-    fn record_gate_outcome(&self, run_id: &RunId, outcome: &GateOutcome) -> Result<()> {
+    fn start_gate(&self, run_id: &RunId, gate: &GateName) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE runs SET current_gate=?1 WHERE id=?2", params![codec::gate_to_str(gate), run_id.0])?;
+        Ok(())
+    }
+
+    fn record_gate_outcome(&self, record: &GateOutcomeRecord) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let outcome = &record.outcome;
+        conn.execute(
+            "INSERT INTO gate_outcomes(run_id, gate, status, remediation, started_at, finished_at, artifacts_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.run_id.0,
+                codec::gate_to_str(&outcome.gate),
+                codec::gate_status_to_str(&outcome.status),
+                outcome.remediation,
+                record.started_at_unix,
+                record.finished_at_unix,
+                serde_json::to_string(&record.artifacts)?
+            ],
+        )?;
+        conn.execute("UPDATE runs SET current_gate=?1 WHERE id=?2", params![codec::gate_to_str(&outcome.gate), record.run_id.0])?;
+        Ok(())
+    }
+
+    fn finish_run(&self, run_id: &RunId, result: RunResult, now_unix: i64) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        // v0: store only current gate string; full gate history lives in evidence artifacts.
         conn.execute(
-            "UPDATE runs SET current_gate=?1 WHERE id=?2",
-            params![format!("{:?}", outcome.gate), run_id.0],
+            "UPDATE runs SET result=?1, finished_at=?2 WHERE id=?3",
+            params![codec::result_to_str(&result), now_unix, run_id.0],
         )?;
         Ok(())
     }
 
-    fn add_message(&self, task_id: &TaskId, ty: MessageType, body_md: &str, now_unix: i64) -> Result<()> {
+    fn list_gate_outcomes(&self, run_id: &RunId) -> Result<Vec<GateOutcomeRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT gate, status, remediation, started_at, finished_at, artifacts_json FROM gate_outcomes WHERE run_id=?1 ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![run_id.0], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, Option<String>>(2)?,
+                r.get::<_, i64>(3)?,
+                r.get::<_, i64>(4)?,
+                r.get::<_, String>(5)?,
+            ))
+        })?;
+        let mut records = vec![];
+        for row in rows {
+            let (gate, status, remediation, started_at, finished_at, artifacts_json) = row?;
+            let gate = codec::str_to_gate(&gate).with_context(|| format!("unknown gate {:?} in gate_outcomes", gate))?;
+            records.push(GateOutcomeRecord {
+                run_id: run_id.clone(),
+                outcome: GateOutcome { gate, status: codec::str_to_gate_status(&status), remediation },
+                started_at_unix: started_at,
+                finished_at_unix: finished_at,
+                artifacts: serde_json::from_str(&artifacts_json)?,
+            });
+        }
+        Ok(records)
+    }
+
+    fn add_message_with_payload(
+        &self,
+        task_id: &TaskId,
+        ty: MessageType,
+        body_md: &str,
+        payload_json: Option<&str>,
+        now_unix: i64,
+    ) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         let id = spl_core::TaskId::new().0;
-        let ty_s = match ty {
-            MessageType::Ask => "ask",
-            MessageType::Update => "update",
-            MessageType::Review => "review",
-            MessageType::Decision => "decision",
-            MessageType::Reset => "reset",
-        };
         conn.execute(
-            "INSERT INTO messages(id, task_id, ty, body_md, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![id, task_id.0, ty_s, body_md, now_unix],
+            "INSERT INTO messages(id, task_id, ty, body_md, payload_json, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, task_id.0, codec::message_type_to_str(&ty), body_md, payload_json, now_unix],
         )?;
         Ok(())
     }
@@ -354,8 +589,35 @@ pub fn now_unix() -> i64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use spl_core::{GateStatus, Lane};
     use tempfile::tempdir;
 
+    fn spec_revision(task: &str, id: &str) -> SpecRevision {
+        SpecRevision {
+            id: SpecRevId::from_str(id),
+            task_id: TaskId::from_str(task),
+            spec_hash: "h".into(),
+            spec_path: "/tmp/spec".into(),
+            parent_id: None,
+        }
+    }
+
+    fn insert_revision(store: &SqliteStorage, task: &str, spec_rev: &str, id: &str) -> Revision {
+        store.insert_spec_revision(&spec_revision(task, spec_rev), now_unix()).unwrap();
+        let revision = Revision {
+            id: RevisionId::from_str(id),
+            task_id: TaskId::from_str(task),
+            spec_rev_id: SpecRevId::from_str(spec_rev),
+            spec_hash: "h".into(),
+            profile: "standard".into(),
+            required_gates: vec![GateName::PreSmoke, GateName::Land],
+            required_validations: vec!["cargo test".into()],
+            anchors: vec![AnchorId::from_str("a1")],
+        };
+        store.insert_revision(&revision, now_unix()).unwrap();
+        revision
+    }
+
     #[test]
     fn sqlite_open_and_migrate() {
         let dir = tempdir().unwrap();
@@ -363,6 +625,13 @@ mod tests {
         let _ = SqliteStorage::open(&db_path).unwrap();
     }
 
+    #[test]
+    fn sqlite_storage_contract() {
+        let dir = tempdir().unwrap();
+        let store = SqliteStorage::open(&dir.path().join("spl.db")).unwrap();
+        spl_storage::run_storage_contract_suite(&store).unwrap();
+    }
+
     #[test]
     fn lease_is_exclusive() {
         let dir = tempdir().unwrap();
@@ -400,6 +669,7 @@ mod tests {
             max_attempts: 3,
             priority: 0,
             idempotency_key: "idem".into(),
+            artifact_ref: None,
         };
         store.enqueue(qi).unwrap();
 
@@ -407,5 +677,182 @@ mod tests {
         assert!(a.is_some());
         let b = store.try_acquire_lease(&QueueId::from_str("q1"), "w2", 60).unwrap();
         assert!(b.is_none());
+
+        // w1 dies: once its lease expires it is reaped exactly once and w2 can take over
+        assert!(store.renew_lease(&QueueId::from_str("q1"), "w1", -1).unwrap());
+        let reaped = store.reap_expired_leases(now_unix()).unwrap();
+        assert_eq!(reaped.len(), 1);
+        assert_eq!(reaped[0].worker_id, "w1");
+        assert!(store.reap_expired_leases(now_unix()).unwrap().is_empty());
+        assert!(!store.renew_lease(&QueueId::from_str("q1"), "w1", 60).unwrap());
+        let c = store.try_acquire_lease(&QueueId::from_str("q1"), "w2", 60).unwrap();
+        assert!(c.is_some());
+    }
+
+    #[test]
+    fn resource_lock_is_exclusive_until_released_or_expired() {
+        let dir = tempdir().unwrap();
+        let store = SqliteStorage::open(&dir.path().join("spl.db")).unwrap();
+
+        assert!(store.try_lock_resource("land", "w1", 60).unwrap());
+        assert!(!store.try_lock_resource("land", "w2", 60).unwrap());
+        assert!(store.try_lock_resource("land", "w1", 60).unwrap(), "holder can extend");
+        assert!(store.try_lock_resource("other", "w2", 60).unwrap());
+
+        store.release_resource("land", "w2").unwrap();
+        assert!(!store.try_lock_resource("land", "w2", 60).unwrap(), "only the holder releases");
+        store.release_resource("land", "w1").unwrap();
+        assert!(store.try_lock_resource("land", "w2", -1).unwrap());
+        assert!(store.try_lock_resource("land", "w3", 60).unwrap(), "expired lock is taken over");
+    }
+
+    #[test]
+    fn adds_artifact_ref_column_to_existing_db() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("spl.db");
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE queue_items (id TEXT PRIMARY KEY, task_id TEXT NOT NULL, revision_id TEXT NOT NULL, lane TEXT NOT NULL,
+                 idempotency_key TEXT NOT NULL, priority INTEGER NOT NULL, enqueued_at INTEGER NOT NULL, visible_at INTEGER NOT NULL,
+                 attempts INTEGER NOT NULL, max_attempts INTEGER NOT NULL);",
+            )
+            .unwrap();
+        }
+        let store = SqliteStorage::open(&db_path).unwrap();
+        let conn = store.conn.lock().unwrap();
+        let n: i64 = conn
+            .query_row("SELECT COUNT(1) FROM pragma_table_info('queue_items') WHERE name='artifact_ref'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(n, 1);
+    }
+
+    #[test]
+    fn dead_lettered_items_leave_the_queue() {
+        let dir = tempdir().unwrap();
+        let store = SqliteStorage::open(&dir.path().join("spl.db")).unwrap();
+        store.insert_task(Task {
+            id: TaskId::from_str("pt-1"),
+            title: "t".into(),
+            status: TaskStatus::Ready,
+            priority: 0,
+            tags: vec![],
+        }).unwrap();
+        store.insert_spec_revision(&spec_revision("pt-1", "s1"), now_unix()).unwrap();
+        {
+            let conn = store.conn.lock().unwrap();
+            conn.execute("INSERT INTO revisions(id, task_id, spec_rev_id, spec_hash, profile, required_gates_json, required_validations_json, anchors_json, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params!["r1","pt-1","s1","h","standard","[]","[]","[]", now_unix()]
+            ).unwrap();
+        }
+        store.enqueue(QueueItem {
+            id: QueueId::from_str("q1"),
+            task_id: TaskId::from_str("pt-1"),
+            revision_id: spl_core::RevisionId::from_str("r1"),
+            lane: Lane::Execute,
+            visible_at_unix: now_unix(),
+            attempts: 0,
+            max_attempts: 3,
+            priority: 0,
+            idempotency_key: "idem".into(),
+            artifact_ref: None,
+        }).unwrap();
+
+        let now = now_unix();
+        store.reschedule(&QueueId::from_str("q1"), 1, now + 60).unwrap();
+        assert!(store.load_snapshot(now).unwrap().queue.is_empty());
+        let later = store.load_snapshot(now + 60).unwrap();
+        assert_eq!(later.queue[0].attempts, 1);
+
+        store.dead_letter(&QueueId::from_str("q1"), "Crash x3", now).unwrap();
+        assert!(store.load_snapshot(now + 60).unwrap().queue.is_empty());
+        let state: String = store.conn.lock().unwrap()
+            .query_row("SELECT state FROM queue_finished WHERE queue_id='q1'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(state, "dead_letter");
+    }
+
+    #[test]
+    fn runs_persist_gate_history_current_gate_and_result() {
+        let dir = tempdir().unwrap();
+        let store = SqliteStorage::open(&dir.path().join("spl.db")).unwrap();
+        store.insert_task(Task {
+            id: TaskId::from_str("pt-1"),
+            title: "t".into(),
+            status: TaskStatus::Ready,
+            priority: 0,
+            tags: vec![],
+        }).unwrap();
+        insert_revision(&store, "pt-1", "s1", "r1");
+        store.enqueue(QueueItem {
+            id: QueueId::from_str("q1"),
+            task_id: TaskId::from_str("pt-1"),
+            revision_id: spl_core::RevisionId::from_str("r1"),
+            lane: Lane::Execute,
+            visible_at_unix: now_unix(),
+            attempts: 0,
+            max_attempts: 3,
+            priority: 0,
+            idempotency_key: "idem".into(),
+            artifact_ref: None,
+        }).unwrap();
+        let run_id = RunId::from_str("run-1");
+        store.create_run(&QueueId::from_str("q1"), run_id.clone()).unwrap();
+
+        store.start_gate(&run_id, &GateName::PreSmoke).unwrap();
+        let run = &store.load_snapshot(now_unix()).unwrap().runs[0];
+        assert_eq!(run.current_gate, Some(GateName::PreSmoke));
+        assert_eq!(run.result, None);
+
+        store.record_gate_outcome(&GateOutcomeRecord {
+            run_id: run_id.clone(),
+            outcome: GateOutcome { gate: GateName::PreSmoke, status: GateStatus::Pass, remediation: None },
+            started_at_unix: 10,
+            finished_at_unix: 12,
+            artifacts: vec!["p/run-1/pre_smoke/PreSmoke.txt".into()],
+        }).unwrap();
+        store.start_gate(&run_id, &GateName::Audit).unwrap();
+        store.record_gate_outcome(&GateOutcomeRecord {
+            run_id: run_id.clone(),
+            outcome: GateOutcome { gate: GateName::Audit, status: GateStatus::Fail, remediation: Some("fix it".into()) },
+            started_at_unix: 12,
+            finished_at_unix: 13,
+            artifacts: vec![],
+        }).unwrap();
+        store.finish_run(&run_id, RunResult::FailGate, 13).unwrap();
+
+        let run = &store.load_snapshot(now_unix()).unwrap().runs[0];
+        assert_eq!(run.current_gate, Some(GateName::Audit));
+        assert_eq!(run.result, Some(RunResult::FailGate));
+        let history = store.list_gate_outcomes(&run_id).unwrap();
+        let gates: Vec<_> = history.iter().map(|g| (g.outcome.gate.clone(), g.outcome.status.clone())).collect();
+        assert_eq!(gates, vec![(GateName::PreSmoke, GateStatus::Pass), (GateName::Audit, GateStatus::Fail)]);
+        assert_eq!((history[0].started_at_unix, history[0].finished_at_unix), (10, 12));
+        assert_eq!(history[0].artifacts, vec!["p/run-1/pre_smoke/PreSmoke.txt".to_string()]);
+        assert_eq!(history[1].outcome.remediation.as_deref(), Some("fix it"));
+    }
+
+    #[test]
+    fn revisions_round_trip_through_queries_and_snapshot() {
+        let dir = tempdir().unwrap();
+        let store = SqliteStorage::open(&dir.path().join("spl.db")).unwrap();
+        for task in ["pt-1", "pt-2"] {
+            store.insert_task(Task { id: TaskId::from_str(task), title: "t".into(), status: TaskStatus::Ready, priority: 0, tags: vec![] })
+                .unwrap();
+        }
+        let first = insert_revision(&store, "pt-1", "s1", "r1");
+        insert_revision(&store, "pt-1", "s2", "r2");
+        insert_revision(&store, "pt-2", "s3", "r3");
+
+        let got = store.get_revision(&first.id).unwrap().unwrap();
+        assert_eq!(got.required_gates, first.required_gates);
+        assert_eq!(got.required_validations, first.required_validations);
+        assert_eq!(got.anchors, first.anchors);
+        assert!(store.get_revision(&RevisionId::from_str("nope")).unwrap().is_none());
+
+        let ids = |revs: Vec<Revision>| revs.into_iter().map(|r| r.id.0).collect::<Vec<_>>();
+        assert_eq!(ids(store.list_revisions_for_task(&TaskId::from_str("pt-1")).unwrap()), vec!["r1", "r2"]);
+        assert_eq!(ids(store.load_snapshot(now_unix()).unwrap().revisions), vec!["r1", "r2", "r3"]);
     }
 }
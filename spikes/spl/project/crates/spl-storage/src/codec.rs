@@ -0,0 +1,118 @@
+//! String forms of core enums as stored in SQL backends. Every backend writes the same
+//! values so a database can be read by any of them (and dumped/compared across them).
+
+use spl_core::{GateName, GateStatus, Lane, MessageType, RunResult, TaskStatus};
+
+use crate::QueueFinish;
+
+pub fn task_status_to_str(s: &TaskStatus) -> &'static str {
+    match s {
+        TaskStatus::Draft => "draft",
+        TaskStatus::Ready => "ready",
+        TaskStatus::BlockedHitl => "blocked_hitl",
+        TaskStatus::BlockedFailure => "blocked_failure",
+        TaskStatus::Done => "done",
+        TaskStatus::Cancelled => "cancelled",
+    }
+}
+
+pub fn str_to_task_status(s: &str) -> TaskStatus {
+    match s {
+        "draft" => TaskStatus::Draft,
+        "ready" => TaskStatus::Ready,
+        "blocked_hitl" => TaskStatus::BlockedHitl,
+        "blocked_failure" => TaskStatus::BlockedFailure,
+        "done" => TaskStatus::Done,
+        "cancelled" => TaskStatus::Cancelled,
+        _ => TaskStatus::Draft,
+    }
+}
+
+pub fn lane_to_str(l: &Lane) -> &'static str {
+    match l {
+        Lane::Execute => "execute",
+        Lane::Land => "land",
+    }
+}
+
+pub fn str_to_lane(s: &str) -> Lane {
+    match s {
+        "land" => Lane::Land,
+        _ => Lane::Execute,
+    }
+}
+
+pub fn result_to_str(r: &RunResult) -> &'static str {
+    match r {
+        RunResult::Pass => "pass",
+        RunResult::FailGate => "fail_gate",
+        RunResult::BlockedHitl => "blocked_hitl",
+        RunResult::Crash => "crash",
+    }
+}
+
+pub fn str_to_result(s: &str) -> Option<RunResult> {
+    match s {
+        "pass" => Some(RunResult::Pass),
+        "fail_gate" => Some(RunResult::FailGate),
+        "blocked_hitl" => Some(RunResult::BlockedHitl),
+        "crash" => Some(RunResult::Crash),
+        _ => None,
+    }
+}
+
+pub fn gate_status_to_str(s: &GateStatus) -> &'static str {
+    match s {
+        GateStatus::Pass => "pass",
+        GateStatus::Fail => "fail",
+        GateStatus::Warn => "warn",
+        GateStatus::Skipped => "skipped",
+    }
+}
+
+pub fn str_to_gate_status(s: &str) -> GateStatus {
+    match s {
+        "pass" => GateStatus::Pass,
+        "warn" => GateStatus::Warn,
+        "skipped" => GateStatus::Skipped,
+        _ => GateStatus::Fail,
+    }
+}
+
+/// Gate names are stored in their serde form, as in `required_gates_json`.
+pub fn gate_to_str(g: &GateName) -> String {
+    serde_json::to_value(g).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+pub fn str_to_gate(s: &str) -> Option<GateName> {
+    serde_json::from_value(serde_json::Value::String(s.to_string())).ok()
+}
+
+pub fn message_type_to_str(ty: &MessageType) -> &'static str {
+    match ty {
+        MessageType::Ask => "ask",
+        MessageType::Update => "update",
+        MessageType::Review => "review",
+        MessageType::Decision => "decision",
+        MessageType::Reset => "reset",
+    }
+}
+
+pub fn str_to_message_type(s: &str) -> MessageType {
+    match s {
+        "ask" => MessageType::Ask,
+        "decision" => MessageType::Decision,
+        "reset" => MessageType::Reset,
+        "review" => MessageType::Review,
+        _ => MessageType::Update,
+    }
+}
+
+/// `queue_finished` row (state, reason, finished_at) as a `QueueFinish`.
+pub fn queue_finish(state: &str, reason: Option<String>, at_unix: i64) -> QueueFinish {
+    match state {
+        "dead_letter" => QueueFinish::DeadLetter { reason: reason.unwrap_or_default(), at_unix },
+        "cancelled" => QueueFinish::Cancelled { reason: reason.unwrap_or_default(), at_unix },
+        _ => QueueFinish::Completed { at_unix },
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{anyhow, Result};
+use spl_core::{
+    GateName, GateOutcome, GateOutcomeRecord, GateStatus, Lane, MessageType, QueueId, QueueItem, Revision, RevisionId, RunId, RunResult,
+    SpecRevId, SpecRevision, Task, TaskId, TaskStatus,
+};
+
+use crate::traits::{EnqueueResult, QueueFinish, Storage};
+
+/// Shared storage contract suite. Runs against every backend (in-memory, sqlite) on a fresh
+/// store so they cannot drift apart.
+pub fn run_storage_contract_suite(storage: &dyn Storage) -> Result<()> {
+    enqueue_is_idempotent_per_lane_and_key(storage)?;
+    snapshot_shows_only_visible_unfinished_items(storage)?;
+    leases_are_exclusive_until_released(storage)?;
+    expired_leases_are_reaped_once_and_then_leasable(storage)?;
+    task_status_transitions(storage)?;
+    priority_and_tags_are_editable(storage)?;
+    queue_history_keeps_finished_items_in_order(storage)?;
+    messages_are_ordered_by_creation(storage)?;
+    runs_keep_gate_history_and_result(storage)?;
+    Ok(())
+}
+
+/// Task `task` with one revision `{task}-rev`, so queue items can reference them
+/// (sqlite enforces the foreign keys).
+fn seed_revision(storage: &dyn Storage, task: &str) -> Result<RevisionId> {
+    storage.insert_task(Task {
+        id: TaskId::from_str(task),
+        title: task.to_string(),
+        status: TaskStatus::Ready,
+        priority: 0,
+        tags: vec![],
+    })?;
+    let spec_rev = SpecRevision {
+        id: SpecRevId::from_str(format!("{}-spec", task)),
+        task_id: TaskId::from_str(task),
+        spec_hash: "hash".into(),
+        spec_path: "spec_pack.yaml".into(),
+        parent_id: None,
+    };
+    storage.insert_spec_revision(&spec_rev, 0)?;
+    let revision = Revision {
+        id: RevisionId::from_str(format!("{}-rev", task)),
+        task_id: TaskId::from_str(task),
+        spec_rev_id: spec_rev.id,
+        spec_hash: "hash".into(),
+        profile: "standard".into(),
+        required_gates: vec![],
+        required_validations: vec![],
+        anchors: vec![],
+    };
+    storage.insert_revision(&revision, 0)?;
+    Ok(revision.id)
+}
+
+fn queue_item(id: &str, task: &str, revision: &RevisionId, lane: Lane, key: &str) -> QueueItem {
+    QueueItem {
+        id: QueueId::from_str(id),
+        task_id: TaskId::from_str(task),
+        revision_id: revision.clone(),
+        lane,
+        visible_at_unix: 0,
+        attempts: 0,
+        max_attempts: 3,
+        priority: 0,
+        idempotency_key: key.to_string(),
+        artifact_ref: None,
+    }
+}
+
+fn now_unix() -> i64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+fn expect(what: &str, ok: bool) -> Result<()> {
+    if !ok {
+        return Err(anyhow!("{}", what));
+    }
+    Ok(())
+}
+
+fn expect_eq<T: PartialEq + std::fmt::Debug>(what: &str, got: T, want: T) -> Result<()> {
+    if got != want {
+        return Err(anyhow!("{}: got {:?}, want {:?}", what, got, want));
+    }
+    Ok(())
+}
+
+fn enqueue_is_idempotent_per_lane_and_key(storage: &dyn Storage) -> Result<()> {
+    let rev = seed_revision(storage, "c-enqueue")?;
+    let first = storage.enqueue(queue_item("c-enq-1", "c-enqueue", &rev, Lane::Execute, "c-enqueue:execute"))?;
+    expect_eq("first enqueue", first, EnqueueResult::Inserted(QueueId::from_str("c-enq-1")))?;
+
+    let again = storage.enqueue(queue_item("c-enq-2", "c-enqueue", &rev, Lane::Execute, "c-enqueue:execute"))?;
+    expect_eq("same lane and key", again, EnqueueResult::Existing(QueueId::from_str("c-enq-1")))?;
+
+    // the key is scoped to its lane
+    let land = storage.enqueue(queue_item("c-enq-3", "c-enqueue", &rev, Lane::Land, "c-enqueue:execute"))?;
+    expect_eq("same key, other lane", land, EnqueueResult::Inserted(QueueId::from_str("c-enq-3")))?;
+
+    // finished items still own their key
+    storage.complete_queue_item(&QueueId::from_str("c-enq-1"), 0)?;
+    let after_finish = storage.enqueue(queue_item("c-enq-4", "c-enqueue", &rev, Lane::Execute, "c-enqueue:execute"))?;
+    expect_eq("after completion", after_finish, EnqueueResult::Existing(QueueId::from_str("c-enq-1")))?;
+
+    let snap = storage.load_snapshot(0)?;
+    let mut ids: Vec<&str> = snap.queue.iter().filter(|q| q.task_id.as_str() == "c-enqueue").map(|q| q.id.as_str()).collect();
+    ids.sort();
+    expect_eq("queued items", ids, vec!["c-enq-3"])?;
+    Ok(())
+}
+
+fn snapshot_shows_only_visible_unfinished_items(storage: &dyn Storage) -> Result<()> {
+    let rev = seed_revision(storage, "c-visible")?;
+    let now = now_unix();
+    let mut later = queue_item("c-vis-later", "c-visible", &rev, Lane::Execute, "c-visible:later");
+    later.visible_at_unix = now + 3600;
+    storage.enqueue(later)?;
+    storage.enqueue(queue_item("c-vis-done", "c-visible", &rev, Lane::Execute, "c-visible:done"))?;
+    storage.enqueue(queue_item("c-vis-dead", "c-visible", &rev, Lane::Execute, "c-visible:dead"))?;
+    storage.complete_queue_item(&QueueId::from_str("c-vis-done"), now)?;
+    storage.dead_letter(&QueueId::from_str("c-vis-dead"), "out of attempts", now)?;
+
+    let visible = |at: i64| -> Result<Vec<String>> {
+        let snap = storage.load_snapshot(at)?;
+        Ok(snap.queue.into_iter().filter(|q| q.task_id.as_str() == "c-visible").map(|q| q.id.0).collect())
+    };
+    expect_eq("visible now", visible(now)?, vec![])?;
+    expect_eq("visible once backoff passed", visible(now + 3600)?, vec!["c-vis-later".to_string()])?;
+
+    // hidden or finished items cannot be leased either
+    for id in ["c-vis-later", "c-vis-done", "c-vis-dead"] {
+        let lease = storage.try_acquire_lease(&QueueId::from_str(id), "w1", 60)?;
+        expect(&format!("lease on hidden/finished item {}", id), lease.is_none())?;
+    }
+    Ok(())
+}
+
+fn leases_are_exclusive_until_released(storage: &dyn Storage) -> Result<()> {
+    let rev = seed_revision(storage, "c-lease")?;
+    storage.enqueue(queue_item("c-lease-1", "c-lease", &rev, Lane::Execute, "c-lease"))?;
+    let id = QueueId::from_str("c-lease-1");
+
+    expect("unknown item cannot be leased", storage.try_acquire_lease(&QueueId::from_str("c-nope"), "w1", 60)?.is_none())?;
+    expect("first worker leases", storage.try_acquire_lease(&id, "w1", 60)?.is_some())?;
+    expect("second worker is refused", storage.try_acquire_lease(&id, "w2", 60)?.is_none())?;
+    expect("holder cannot lease twice", storage.try_acquire_lease(&id, "w1", 60)?.is_none())?;
+    expect("only the holder renews", !storage.renew_lease(&id, "w2", 60)?)?;
+    expect("holder renews", storage.renew_lease(&id, "w1", 60)?)?;
+
+    storage.release_lease(&id, "w2")?;
+    expect("release by non-holder is ignored", storage.try_acquire_lease(&id, "w2", 60)?.is_none())?;
+    storage.release_lease(&id, "w1")?;
+    expect("released item is leasable", storage.try_acquire_lease(&id, "w2", 60)?.is_some())?;
+
+    let leases: Vec<String> =
+        storage.load_snapshot(now_unix())?.leases.into_iter().filter(|l| l.queue_id == id).map(|l| l.worker_id).collect();
+    expect_eq("snapshot leases", leases, vec!["w2".to_string()])?;
+    storage.release_lease(&id, "w2")?;
+    Ok(())
+}
+
+fn expired_leases_are_reaped_once_and_then_leasable(storage: &dyn Storage) -> Result<()> {
+    let rev = seed_revision(storage, "c-expiry")?;
+    storage.enqueue(queue_item("c-exp-1", "c-expiry", &rev, Lane::Execute, "c-expiry:1"))?;
+    storage.enqueue(queue_item("c-exp-2", "c-expiry", &rev, Lane::Execute, "c-expiry:2"))?;
+    let (first, second) = (QueueId::from_str("c-exp-1"), QueueId::from_str("c-exp-2"));
+
+    // an expired lease still blocks the item until it is reaped, so its run gets settled
+    storage.try_acquire_lease(&first, "w-dead", -1)?;
+    expect("expired lease is not taken over", storage.try_acquire_lease(&first, "w2", 60)?.is_none())?;
+    let reaped = storage.reap_expired_leases(now_unix())?;
+    expect_eq("reaped", reaped.iter().map(|l| l.worker_id.as_str()).collect::<Vec<_>>(), vec!["w-dead"])?;
+    expect("stale holder cannot renew", !storage.renew_lease(&first, "w-dead", 60)?)?;
+    expect("reaped item is leasable", storage.try_acquire_lease(&first, "w2", 60)?.is_some())?;
+    storage.release_lease(&first, "w2")?;
+
+    storage.try_acquire_lease(&second, "w-dead", -1)?;
+    let reaped = storage.reap_expired_leases(now_unix())?;
+    let reaped: Vec<(&str, &str)> = reaped.iter().map(|l| (l.queue_id.as_str(), l.worker_id.as_str())).collect();
+    expect_eq("reaped", reaped, vec![("c-exp-2", "w-dead")])?;
+    expect("reaped once", storage.reap_expired_leases(now_unix())?.is_empty())?;
+    expect("reaped holder cannot renew", !storage.renew_lease(&second, "w-dead", 60)?)?;
+    expect("reaped item is leasable", storage.try_acquire_lease(&second, "w2", 60)?.is_some())?;
+    storage.release_lease(&second, "w2")?;
+    Ok(())
+}
+
+fn task_status(storage: &dyn Storage, task: &str) -> Result<Option<TaskStatus>> {
+    let snap = storage.load_snapshot(now_unix())?;
+    Ok(snap.tasks.into_iter().find(|t| t.id.as_str() == task).map(|t| t.status))
+}
+
+fn task_status_transitions(storage: &dyn Storage) -> Result<()> {
+    let task = Task { id: TaskId::from_str("c-status"), title: "t".into(), status: TaskStatus::Draft, priority: 0, tags: vec![] };
+    storage.insert_task(task.clone())?;
+    expect("duplicate task is rejected", storage.insert_task(task.clone()).is_err())?;
+    expect_eq("inserted", task_status(storage, "c-status")?, Some(TaskStatus::Draft))?;
+
+    for status in [TaskStatus::Ready, TaskStatus::BlockedHitl, TaskStatus::Ready, TaskStatus::BlockedFailure, TaskStatus::Done, TaskStatus::Cancelled] {
+        storage.set_task_status(&task.id, status.clone())?;
+        expect_eq("after transition", task_status(storage, "c-status")?, Some(status))?;
+    }
+    Ok(())
+}
+
+fn priority_and_tags_are_editable(storage: &dyn Storage) -> Result<()> {
+    let rev = seed_revision(storage, "c-edit")?;
+    storage.enqueue(queue_item("c-edit-done", "c-edit", &rev, Lane::Execute, "c-edit:done"))?;
+    storage.enqueue(queue_item("c-edit-queued", "c-edit", &rev, Lane::Execute, "c-edit:queued"))?;
+    storage.complete_queue_item(&QueueId::from_str("c-edit-done"), 0)?;
+    let task_id = TaskId::from_str("c-edit");
+    storage.set_task_priority(&task_id, 7)?;
+    storage.set_task_tags(&task_id, &["ui".to_string(), "urgent".to_string()])?;
+
+    let snap = storage.load_snapshot(now_unix())?;
+    let task = snap.tasks.iter().find(|t| t.id == task_id).ok_or_else(|| anyhow!("task c-edit missing from snapshot"))?;
+    expect_eq("task priority", task.priority, 7)?;
+    expect_eq("task tags", task.tags.clone(), vec!["ui".to_string(), "urgent".to_string()])?;
+    // waiting work takes the new priority; finished items keep theirs
+    let priorities: Vec<(String, i32)> =
+        storage.list_queue_items_for_task(&task_id)?.into_iter().map(|r| (r.item.id.0, r.item.priority)).collect();
+    expect_eq("item priorities", priorities, vec![("c-edit-done".to_string(), 0), ("c-edit-queued".to_string(), 7)])?;
+    Ok(())
+}
+
+fn queue_history_keeps_finished_items_in_order(storage: &dyn Storage) -> Result<()> {
+    let rev = seed_revision(storage, "c-history")?;
+    for n in 1..=4 {
+        storage.enqueue(queue_item(&format!("c-hist-{}", n), "c-history", &rev, Lane::Execute, &format!("c-history:{}", n)))?;
+    }
+    storage.complete_queue_item(&QueueId::from_str("c-hist-2"), 20)?;
+    storage.dead_letter(&QueueId::from_str("c-hist-1"), "out of attempts", 30)?;
+    storage.cancel_queue_item(&QueueId::from_str("c-hist-4"), "task cancelled", 40)?;
+    // cancelling a finished item leaves its finish alone
+    storage.cancel_queue_item(&QueueId::from_str("c-hist-2"), "task cancelled", 50)?;
+
+    let history: Vec<(String, Option<QueueFinish>)> =
+        storage.list_queue_items_for_task(&TaskId::from_str("c-history"))?.into_iter().map(|r| (r.item.id.0, r.finished)).collect();
+    expect_eq(
+        "queue history",
+        history,
+        vec![
+            ("c-hist-1".to_string(), Some(QueueFinish::DeadLetter { reason: "out of attempts".into(), at_unix: 30 })),
+            ("c-hist-2".to_string(), Some(QueueFinish::Completed { at_unix: 20 })),
+            ("c-hist-3".to_string(), None),
+            ("c-hist-4".to_string(), Some(QueueFinish::Cancelled { reason: "task cancelled".into(), at_unix: 40 })),
+        ],
+    )?;
+    Ok(())
+}
+
+fn messages_are_ordered_by_creation(storage: &dyn Storage) -> Result<()> {
+    let task = TaskId::from_str("c-messages");
+    storage.insert_task(Task { id: task.clone(), title: "t".into(), status: TaskStatus::Ready, priority: 0, tags: vec![] })?;
+    storage.add_message(&task, MessageType::Update, "third", 30)?;
+    storage.add_message(&task, MessageType::Ask, "first", 10)?;
+    storage.add_message(&task, MessageType::Update, "second", 10)?;
+    storage.add_message_with_payload(&task, MessageType::Ask, "fourth", Some(r#"{"options":[]}"#), 40)?;
+
+    let snap = storage.load_snapshot(now_unix())?;
+    let bodies: Vec<(&str, i64)> =
+        snap.messages.iter().filter(|m| m.task_id == task).map(|m| (m.body_md.as_str(), m.created_at_unix)).collect();
+    // by creation time; ties keep insertion order
+    expect_eq("messages", bodies, vec![("first", 10), ("second", 10), ("third", 30), ("fourth", 40)])?;
+    let ask = snap.messages.iter().find(|m| m.task_id == task && m.body_md == "first");
+    expect("message type kept", ask.is_some_and(|m| m.ty == MessageType::Ask && m.payload_json.is_none()))?;
+    let payload = snap.messages.iter().find(|m| m.task_id == task && m.body_md == "fourth").and_then(|m| m.payload_json.as_deref());
+    expect_eq("payload kept", payload, Some(r#"{"options":[]}"#))?;
+    Ok(())
+}
+
+fn runs_keep_gate_history_and_result(storage: &dyn Storage) -> Result<()> {
+    let rev = seed_revision(storage, "c-run")?;
+    storage.enqueue(queue_item("c-run-q", "c-run", &rev, Lane::Execute, "c-run"))?;
+    let run_id = RunId::from_str("c-run-1");
+    storage.create_run(&QueueId::from_str("c-run-q"), run_id.clone())?;
+
+    let run = |storage: &dyn Storage| -> Result<spl_core::Run> {
+        let snap = storage.load_snapshot(now_unix())?;
+        snap.runs.into_iter().find(|r| r.id == run_id).ok_or_else(|| anyhow!("run c-run-1 missing from snapshot"))
+    };
+    let created = run(storage)?;
+    expect_eq("run lane", created.lane, Lane::Execute)?;
+    expect_eq("run task", created.task_id.as_str(), "c-run")?;
+    expect_eq("new run result", created.result, None)?;
+
+    storage.start_gate(&run_id, &GateName::PreSmoke)?;
+    expect_eq("current gate", run(storage)?.current_gate, Some(GateName::PreSmoke))?;
+    for (gate, status) in [(GateName::PreSmoke, GateStatus::Pass), (GateName::Audit, GateStatus::Fail)] {
+        storage.start_gate(&run_id, &gate)?;
+        storage.record_gate_outcome(&GateOutcomeRecord {
+            run_id: run_id.clone(),
+            outcome: GateOutcome { gate, status, remediation: None },
+            started_at_unix: 1,
+            finished_at_unix: 2,
+            artifacts: vec!["evidence.txt".into()],
+        })?;
+    }
+    storage.finish_run(&run_id, RunResult::FailGate, 3)?;
+
+    let finished = run(storage)?;
+    expect_eq("result", finished.result, Some(RunResult::FailGate))?;
+    expect_eq("last gate", finished.current_gate, Some(GateName::Audit))?;
+    let history: Vec<(GateName, GateStatus)> =
+        storage.list_gate_outcomes(&run_id)?.into_iter().map(|g| (g.outcome.gate, g.outcome.status)).collect();
+    expect_eq("gate history", history, vec![(GateName::PreSmoke, GateStatus::Pass), (GateName::Audit, GateStatus::Fail)])?;
+    Ok(())
+}
@@ -1,5 +1,10 @@
+pub mod codec;
+pub mod contract;
 pub mod memory;
+pub mod migration;
 pub mod traits;
 
+pub use contract::*;
 pub use memory::*;
+pub use migration::*;
 pub use traits::*;
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 use std::sync::Mutex;
 
-use spl_core::{GateOutcome, Lease, Message, MessageType, QueueId, QueueItem, Run, RunId, Snapshot, Task, TaskId, TaskStatus};
-use crate::traits::Storage;
+use spl_core::{
+    GateName, GateOutcomeRecord, Lease, Message, MessageType, QueueId, QueueItem, Revision, RevisionId, Run, RunId, RunResult, Snapshot,
+    SpecRevision, Task, TaskId, TaskStatus,
+};
+use crate::traits::{EnqueueResult, QueueFinish, QueueItemRecord, Storage};
 
 /// In-memory storage for tests. Not durable, but good for unit/small scenario tests.
 #[derive(Default)]
@@ -13,10 +16,20 @@ pub struct InMemoryStorage {
 #[derive(Default)]
 struct Inner {
     tasks: HashMap<String, Task>,
+    /// insertion order, i.e. oldest first
+    spec_revisions: Vec<SpecRevision>,
+    /// insertion order, i.e. oldest first
+    revisions: Vec<Revision>,
     queue: HashMap<String, QueueItem>,
+    /// queue ids in enqueue order
+    enqueued: Vec<String>,
     leases: HashMap<String, Lease>,
     runs: HashMap<String, Run>,
+    gate_outcomes: Vec<GateOutcomeRecord>,
     messages: Vec<Message>,
+    finished: HashMap<String, QueueFinish>,
+    /// resource name -> (holder, expires_at_unix)
+    resources: HashMap<String, (String, i64)>,
 }
 
 impl InMemoryStorage {
@@ -28,19 +41,26 @@ impl InMemoryStorage {
 impl Storage for InMemoryStorage {
     fn load_snapshot(&self, now_unix: i64) -> anyhow::Result<Snapshot> {
         let inner = self.inner.lock().unwrap();
+        // stable: same-second messages keep insertion order, like sqlite's rowid tiebreak
+        let mut messages = inner.messages.clone();
+        messages.sort_by_key(|m| m.created_at_unix);
         Ok(Snapshot {
             now_unix,
+            vcs: Default::default(),
             tasks: inner.tasks.values().cloned().collect(),
-            revisions: vec![],
-            queue: inner.queue.values().cloned().collect(),
+            revisions: inner.revisions.clone(),
+            queue: inner.queue.values().filter(|q| q.visible_at_unix <= now_unix && !inner.finished.contains_key(&q.id.0)).cloned().collect(),
             leases: inner.leases.values().cloned().collect(),
             runs: inner.runs.values().cloned().collect(),
-            messages: inner.messages.clone(),
+            messages,
         })
     }
 
     fn insert_task(&self, task: Task) -> anyhow::Result<()> {
         let mut inner = self.inner.lock().unwrap();
+        if inner.tasks.contains_key(&task.id.0) {
+            anyhow::bail!("task {} already exists", task.id.as_str());
+        }
         inner.tasks.insert(task.id.0.clone(), task);
         Ok(())
     }
@@ -53,18 +73,123 @@ impl Storage for InMemoryStorage {
         Ok(())
     }
 
-    fn enqueue(&self, item: QueueItem) -> anyhow::Result<()> {
+    fn set_task_priority(&self, task_id: &TaskId, priority: i32) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let Inner { tasks, queue, finished, .. } = &mut *inner;
+        if let Some(t) = tasks.get_mut(&task_id.0) {
+            t.priority = priority;
+        }
+        for q in queue.values_mut().filter(|q| &q.task_id == task_id && !finished.contains_key(&q.id.0)) {
+            q.priority = priority;
+        }
+        Ok(())
+    }
+
+    fn set_task_tags(&self, task_id: &TaskId, tags: &[String]) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(t) = inner.tasks.get_mut(&task_id.0) {
+            t.tags = tags.to_vec();
+        }
+        Ok(())
+    }
+
+    fn insert_spec_revision(&self, spec_rev: &SpecRevision, _now_unix: i64) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.spec_revisions.iter().any(|s| s.id == spec_rev.id) {
+            anyhow::bail!("spec revision {} already exists", spec_rev.id.as_str());
+        }
+        inner.spec_revisions.push(spec_rev.clone());
+        Ok(())
+    }
+
+    fn list_spec_revisions_for_task(&self, task_id: &TaskId) -> anyhow::Result<Vec<SpecRevision>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.spec_revisions.iter().filter(|s| &s.task_id == task_id).cloned().collect())
+    }
+
+    fn insert_revision(&self, revision: &Revision, _now_unix: i64) -> anyhow::Result<()> {
         let mut inner = self.inner.lock().unwrap();
+        if !inner.spec_revisions.iter().any(|s| s.id == revision.spec_rev_id) {
+            anyhow::bail!("spec revision {} not found for revision {}", revision.spec_rev_id.as_str(), revision.id.as_str());
+        }
+        if inner.revisions.iter().any(|r| r.id == revision.id) {
+            anyhow::bail!("revision {} already exists", revision.id.as_str());
+        }
+        inner.revisions.push(revision.clone());
+        Ok(())
+    }
+
+    fn get_revision(&self, revision_id: &RevisionId) -> anyhow::Result<Option<Revision>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.revisions.iter().find(|r| &r.id == revision_id).cloned())
+    }
+
+    fn list_revisions_for_task(&self, task_id: &TaskId) -> anyhow::Result<Vec<Revision>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.revisions.iter().filter(|r| &r.task_id == task_id).cloned().collect())
+    }
+
+    fn enqueue(&self, item: QueueItem) -> anyhow::Result<EnqueueResult> {
+        let mut inner = self.inner.lock().unwrap();
+        let existing = inner.queue.values().find(|q| q.lane == item.lane && q.idempotency_key == item.idempotency_key);
+        if let Some(existing) = existing {
+            return Ok(EnqueueResult::Existing(existing.id.clone()));
+        }
+        if inner.queue.contains_key(&item.id.0) {
+            anyhow::bail!("queue item {} already exists", item.id.as_str());
+        }
+        let id = item.id.clone();
+        inner.enqueued.push(id.0.clone());
         inner.queue.insert(item.id.0.clone(), item);
+        Ok(EnqueueResult::Inserted(id))
+    }
+
+    fn list_queue_items_for_task(&self, task_id: &TaskId) -> anyhow::Result<Vec<QueueItemRecord>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .enqueued
+            .iter()
+            .filter_map(|id| inner.queue.get(id))
+            .filter(|q| &q.task_id == task_id)
+            .map(|q| QueueItemRecord { item: q.clone(), finished: inner.finished.get(&q.id.0).cloned() })
+            .collect())
+    }
+
+    fn reschedule(&self, queue_id: &QueueId, attempts: u32, visible_at_unix: i64) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(q) = inner.queue.get_mut(&queue_id.0) {
+            q.attempts = attempts;
+            q.visible_at_unix = visible_at_unix;
+        }
+        Ok(())
+    }
+
+    fn complete_queue_item(&self, queue_id: &QueueId, now_unix: i64) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.finished.entry(queue_id.0.clone()).or_insert(QueueFinish::Completed { at_unix: now_unix });
+        Ok(())
+    }
+
+    fn dead_letter(&self, queue_id: &QueueId, reason: &str, now_unix: i64) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.finished.insert(queue_id.0.clone(), QueueFinish::DeadLetter { reason: reason.to_string(), at_unix: now_unix });
+        Ok(())
+    }
+
+    fn cancel_queue_item(&self, queue_id: &QueueId, reason: &str, now_unix: i64) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.finished.entry(queue_id.0.clone()).or_insert(QueueFinish::Cancelled { reason: reason.to_string(), at_unix: now_unix });
         Ok(())
     }
 
     fn try_acquire_lease(&self, queue_id: &QueueId, worker_id: &str, ttl_secs: i64) -> anyhow::Result<Option<String>> {
         let mut inner = self.inner.lock().unwrap();
-        if inner.leases.contains_key(&queue_id.0) {
+        let now = chrono_like_now();
+        let held = inner.leases.contains_key(&queue_id.0);
+        let visible = inner.queue.get(&queue_id.0).is_some_and(|q| q.visible_at_unix <= now);
+        if held || !visible || inner.finished.contains_key(&queue_id.0) {
             return Ok(None);
         }
-        let now = chrono_like_now();
         let lease_id = spl_core::LeaseId::new();
         inner.leases.insert(queue_id.0.clone(), Lease {
             id: lease_id.clone(),
@@ -86,12 +211,51 @@ impl Storage for InMemoryStorage {
         Ok(())
     }
 
+    fn renew_lease(&self, queue_id: &QueueId, worker_id: &str, ttl_secs: i64) -> anyhow::Result<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.leases.get_mut(&queue_id.0) {
+            Some(l) if l.worker_id == worker_id => {
+                l.expires_at_unix = chrono_like_now() + ttl_secs;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn reap_expired_leases(&self, now_unix: i64) -> anyhow::Result<Vec<Lease>> {
+        let mut inner = self.inner.lock().unwrap();
+        let expired: Vec<String> =
+            inner.leases.values().filter(|l| l.expires_at_unix <= now_unix).map(|l| l.queue_id.0.clone()).collect();
+        Ok(expired.iter().filter_map(|q| inner.leases.remove(q)).collect())
+    }
+
+    fn try_lock_resource(&self, name: &str, holder: &str, ttl_secs: i64) -> anyhow::Result<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        let now = chrono_like_now();
+        if let Some((current, expires)) = inner.resources.get(name) {
+            if current != holder && *expires > now {
+                return Ok(false);
+            }
+        }
+        inner.resources.insert(name.to_string(), (holder.to_string(), now + ttl_secs));
+        Ok(true)
+    }
+
+    fn release_resource(&self, name: &str, holder: &str) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.resources.get(name).is_some_and(|(current, _)| current == holder) {
+            inner.resources.remove(name);
+        }
+        Ok(())
+    }
+
     fn create_run(&self, queue_id: &QueueId, run_id: RunId) -> anyhow::Result<()> {
         let mut inner = self.inner.lock().unwrap();
         let qi = inner.queue.get(&queue_id.0).cloned();
         if let Some(qi) = qi {
             inner.runs.insert(run_id.0.clone(), Run {
                 id: run_id,
+                queue_id: qi.id,
                 task_id: qi.task_id,
                 revision_id: qi.revision_id,
                 lane: qi.lane,
@@ -102,17 +266,51 @@ impl Storage for InMemoryStorage {
         Ok(())
     }
 
-    fn record_gate_outcome(&self, _run_id: &RunId, _outcome: &GateOutcome) -> anyhow::Result<()> {
+    fn start_gate(&self, run_id: &RunId, gate: &GateName) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(run) = inner.runs.get_mut(&run_id.0) {
+            run.current_gate = Some(gate.clone());
+        }
+        Ok(())
+    }
+
+    fn record_gate_outcome(&self, record: &GateOutcomeRecord) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(run) = inner.runs.get_mut(&record.run_id.0) {
+            run.current_gate = Some(record.outcome.gate.clone());
+        }
+        inner.gate_outcomes.push(record.clone());
+        Ok(())
+    }
+
+    fn finish_run(&self, run_id: &RunId, result: RunResult, _now_unix: i64) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(run) = inner.runs.get_mut(&run_id.0) {
+            run.result = Some(result);
+        }
         Ok(())
     }
 
-    fn add_message(&self, task_id: &TaskId, ty: MessageType, body_md: &str, now_unix: i64) -> anyhow::Result<()> {
+    fn list_gate_outcomes(&self, run_id: &RunId) -> anyhow::Result<Vec<GateOutcomeRecord>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.gate_outcomes.iter().filter(|g| &g.run_id == run_id).cloned().collect())
+    }
+
+    fn add_message_with_payload(
+        &self,
+        task_id: &TaskId,
+        ty: MessageType,
+        body_md: &str,
+        payload_json: Option<&str>,
+        now_unix: i64,
+    ) -> anyhow::Result<()> {
         let mut inner = self.inner.lock().unwrap();
         inner.messages.push(Message {
             id: spl_core::TaskId::new().0, // cheap unique id
             task_id: task_id.clone(),
             ty,
             body_md: body_md.to_string(),
+            payload_json: payload_json.map(str::to_string),
             created_at_unix: now_unix,
         });
         Ok(())
@@ -200,6 +398,11 @@ mod tests {
         assert_eq!(snapshot.tasks[0].status, TaskStatus::Done);
     }
 
+    #[test]
+    fn in_memory_storage_contract() {
+        crate::run_storage_contract_suite(&InMemoryStorage::new()).unwrap();
+    }
+
     #[test]
     fn test_enqueue() {
         let storage = InMemoryStorage::new();
@@ -213,6 +416,7 @@ mod tests {
             max_attempts: 0,
             priority: 0,
             idempotency_key: "".to_string(),
+            artifact_ref: None,
         };
         storage.enqueue(item.clone()).unwrap();
         let snapshot = storage.load_snapshot(0).unwrap();
@@ -220,10 +424,57 @@ mod tests {
         assert_eq!(snapshot.queue[0].id, item.id);
     }
 
+    #[test]
+    fn test_reschedule_and_finish() {
+        let storage = InMemoryStorage::new();
+        for id in ["queue1", "queue2"] {
+            storage.enqueue(QueueItem {
+                id: QueueId(id.to_string()),
+                task_id: TaskId("task1".to_string()),
+                revision_id: RevisionId("rev1".to_string()),
+                lane: spl_core::Lane::Execute,
+                visible_at_unix: 0,
+                attempts: 0,
+                max_attempts: 3,
+                priority: 0,
+                idempotency_key: id.to_string(),
+                artifact_ref: None,
+            }).unwrap();
+        }
+
+        storage.reschedule(&QueueId("queue1".to_string()), 1, 30).unwrap();
+        let snapshot = storage.load_snapshot(30).unwrap();
+        let q1 = snapshot.queue.iter().find(|q| q.id.0 == "queue1").unwrap();
+        assert_eq!(q1.attempts, 1);
+        assert_eq!(q1.visible_at_unix, 30);
+
+        storage.complete_queue_item(&QueueId("queue1".to_string()), 0).unwrap();
+        storage.dead_letter(&QueueId("queue2".to_string()), "out of attempts", 0).unwrap();
+        let snapshot = storage.load_snapshot(30).unwrap();
+        assert!(snapshot.queue.is_empty());
+        assert!(storage.try_acquire_lease(&QueueId("queue2".to_string()), "worker1", 60).unwrap().is_none());
+    }
+
+    fn enqueued(storage: &InMemoryStorage, id: &str) -> QueueId {
+        storage.enqueue(QueueItem {
+            id: QueueId(id.to_string()),
+            task_id: TaskId("task1".to_string()),
+            revision_id: RevisionId("rev1".to_string()),
+            lane: spl_core::Lane::Execute,
+            visible_at_unix: 0,
+            attempts: 0,
+            max_attempts: 3,
+            priority: 0,
+            idempotency_key: id.to_string(),
+            artifact_ref: None,
+        }).unwrap();
+        QueueId(id.to_string())
+    }
+
     #[test]
     fn test_try_acquire_and_release_lease() {
         let storage = InMemoryStorage::new();
-        let queue_id = QueueId("queue1".to_string());
+        let queue_id = enqueued(&storage, "queue1");
         let worker_id = "worker1";
         let lease_id = storage.try_acquire_lease(&queue_id, worker_id, 60).unwrap();
         assert!(lease_id.is_some());
@@ -236,6 +487,28 @@ mod tests {
         assert!(snapshot.leases.is_empty());
     }
 
+    #[test]
+    fn test_expired_lease_is_reaped_and_reclaimable() {
+        let storage = InMemoryStorage::new();
+        let queue_id = enqueued(&storage, "queue1");
+        storage.try_acquire_lease(&queue_id, "worker1", -1).unwrap().unwrap();
+
+        assert!(storage.renew_lease(&queue_id, "worker1", -1).unwrap());
+        assert!(!storage.renew_lease(&queue_id, "worker2", 60).unwrap());
+
+        let reaped = storage.reap_expired_leases(chrono_like_now()).unwrap();
+        assert_eq!(reaped.len(), 1);
+        assert_eq!(reaped[0].worker_id, "worker1");
+        assert!(storage.reap_expired_leases(chrono_like_now()).unwrap().is_empty());
+        assert!(!storage.renew_lease(&queue_id, "worker1", 60).unwrap());
+
+        // an expired lease blocks a new worker until it is reaped
+        storage.try_acquire_lease(&queue_id, "worker2", -1).unwrap().unwrap();
+        assert!(storage.try_acquire_lease(&queue_id, "worker3", 60).unwrap().is_none());
+        storage.reap_expired_leases(chrono_like_now()).unwrap();
+        assert!(storage.try_acquire_lease(&queue_id, "worker3", 60).unwrap().is_some());
+    }
+
     #[test]
     fn test_create_run() {
         let storage = InMemoryStorage::new();
@@ -253,6 +526,7 @@ mod tests {
             max_attempts: 0,
             priority: 0,
             idempotency_key: "".to_string(),
+            artifact_ref: None,
         }).unwrap();
 
         let run_id = RunId("run1".to_string());
@@ -272,4 +546,34 @@ mod tests {
         assert_eq!(snapshot.messages.len(), 1);
         assert_eq!(snapshot.messages[0].body_md, "test message");
     }
+
+    #[test]
+    fn test_revisions_need_their_spec_revision_and_show_in_snapshot() {
+        let storage = InMemoryStorage::new();
+        let revision = Revision {
+            id: RevisionId::from_str("rev1"),
+            task_id: TaskId::from_str("task1"),
+            spec_rev_id: spl_core::SpecRevId::from_str("spec1"),
+            spec_hash: "hash".to_string(),
+            profile: "standard".to_string(),
+            required_gates: vec![spl_core::GateName::Land],
+            required_validations: vec![],
+            anchors: vec![],
+        };
+        assert!(storage.insert_revision(&revision, 0).is_err());
+
+        storage.insert_spec_revision(&SpecRevision {
+            id: spl_core::SpecRevId::from_str("spec1"),
+            task_id: TaskId::from_str("task1"),
+            spec_hash: "hash".to_string(),
+            spec_path: "path".to_string(),
+            parent_id: None,
+        }, 0).unwrap();
+        storage.insert_revision(&revision, 0).unwrap();
+
+        assert_eq!(storage.get_revision(&revision.id).unwrap().unwrap().required_gates, vec![spl_core::GateName::Land]);
+        assert_eq!(storage.list_revisions_for_task(&TaskId::from_str("task1")).unwrap().len(), 1);
+        assert!(storage.list_revisions_for_task(&TaskId::from_str("task2")).unwrap().is_empty());
+        assert_eq!(storage.load_snapshot(0).unwrap().revisions.len(), 1);
+    }
 }
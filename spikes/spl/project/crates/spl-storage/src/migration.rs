@@ -0,0 +1,19 @@
+/// One numbered schema step of a SQL backend; applied in its own transaction together
+/// with the version bump.
+#[derive(Clone, Copy, Debug)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// What migrating a database involves.
+#[derive(Clone, Debug)]
+pub struct MigrationPlan {
+    /// Version before migrating; 0 for a new database.
+    pub current: u32,
+    /// The database predates versioning: `current` was inferred from its tables and gets
+    /// recorded by the first pending migration (or right away if none is pending).
+    pub legacy: bool,
+    pub pending: Vec<Migration>,
+}
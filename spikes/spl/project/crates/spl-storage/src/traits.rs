@@ -1,19 +1,114 @@
-use spl_core::{GateOutcome, MessageType, QueueId, QueueItem, RunId, Snapshot, Task, TaskId, TaskStatus};
+use spl_core::{
+    GateName, GateOutcomeRecord, Lease, MessageType, QueueId, QueueItem, Revision, RevisionId, RunId, RunResult, Snapshot, SpecRevision,
+    Task, TaskId, TaskStatus,
+};
+
+/// What `Storage::enqueue` did. Items are unique per (lane, idempotency_key), finished ones
+/// included, so re-enqueueing the same work hands back the item already recorded for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EnqueueResult {
+    Inserted(QueueId),
+    Existing(QueueId),
+}
+
+impl EnqueueResult {
+    pub fn queue_id(&self) -> &QueueId {
+        match self {
+            EnqueueResult::Inserted(id) | EnqueueResult::Existing(id) => id,
+        }
+    }
+}
+
+/// How an item left the queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QueueFinish {
+    Completed { at_unix: i64 },
+    DeadLetter { reason: String, at_unix: i64 },
+    /// Dropped before it ran: its task was cancelled or its revision superseded.
+    Cancelled { reason: String, at_unix: i64 },
+}
+
+/// A queue item whether or not it is still queued; `finished` is None while it is.
+#[derive(Clone, Debug)]
+pub struct QueueItemRecord {
+    pub item: QueueItem,
+    pub finished: Option<QueueFinish>,
+}
 
 pub trait Storage: Send + Sync {
     fn load_snapshot(&self, now_unix: i64) -> anyhow::Result<Snapshot>;
 
     fn insert_task(&self, task: Task) -> anyhow::Result<()>;
     fn set_task_status(&self, task_id: &TaskId, status: TaskStatus) -> anyhow::Result<()>;
+    /// Set the task's priority; its still-queued items take it too, so the change applies
+    /// to work already waiting.
+    fn set_task_priority(&self, task_id: &TaskId, priority: i32) -> anyhow::Result<()>;
+    fn set_task_tags(&self, task_id: &TaskId, tags: &[String]) -> anyhow::Result<()>;
+
+    fn insert_spec_revision(&self, spec_rev: &SpecRevision, now_unix: i64) -> anyhow::Result<()>;
+    /// A task's spec revisions, oldest first.
+    fn list_spec_revisions_for_task(&self, task_id: &TaskId) -> anyhow::Result<Vec<SpecRevision>>;
+    /// Store a compiled revision; its spec revision must already be stored.
+    fn insert_revision(&self, revision: &Revision, now_unix: i64) -> anyhow::Result<()>;
+    fn get_revision(&self, revision_id: &RevisionId) -> anyhow::Result<Option<Revision>>;
+    /// A task's revisions, oldest first.
+    fn list_revisions_for_task(&self, task_id: &TaskId) -> anyhow::Result<Vec<Revision>>;
 
-    fn enqueue(&self, item: QueueItem) -> anyhow::Result<()>;
+    /// Insert `item` unless its (lane, idempotency_key) is already taken; then the existing
+    /// item is left untouched and its id returned.
+    fn enqueue(&self, item: QueueItem) -> anyhow::Result<EnqueueResult>;
+
+    /// Every queue item of a task, finished ones included, oldest first.
+    fn list_queue_items_for_task(&self, task_id: &TaskId) -> anyhow::Result<Vec<QueueItemRecord>>;
+
+    /// Put a failed item back on the queue: record `attempts` and hide it until `visible_at_unix`.
+    fn reschedule(&self, queue_id: &QueueId, attempts: u32, visible_at_unix: i64) -> anyhow::Result<()>;
+    /// Take an item off the queue for good; its outcome lives in runs and task status.
+    fn complete_queue_item(&self, queue_id: &QueueId, now_unix: i64) -> anyhow::Result<()>;
+    /// Take an item off the queue because it ran out of attempts.
+    fn dead_letter(&self, queue_id: &QueueId, reason: &str, now_unix: i64) -> anyhow::Result<()>;
+    /// Take an item off the queue without running it; an item that already finished keeps
+    /// its finish.
+    fn cancel_queue_item(&self, queue_id: &QueueId, reason: &str, now_unix: i64) -> anyhow::Result<()>;
 
     /// Attempt to acquire a lease for a queue item. Returns Some(LeaseId) if acquired.
+    /// Any lease on the item blocks it, expired or not: only `reap_expired_leases` clears
+    /// an expired one, so the dead worker's run gets settled.
     fn try_acquire_lease(&self, queue_id: &QueueId, worker_id: &str, ttl_secs: i64) -> anyhow::Result<Option<String>>;
     fn release_lease(&self, queue_id: &QueueId, worker_id: &str) -> anyhow::Result<()>;
+    /// Heartbeat: push the lease's expiry to now + `ttl_secs`. Returns false if `worker_id`
+    /// no longer holds the lease (reaped and possibly taken by another worker).
+    fn renew_lease(&self, queue_id: &QueueId, worker_id: &str, ttl_secs: i64) -> anyhow::Result<bool>;
+    /// Remove every lease that expired at or before `now_unix` and return them, so the
+    /// caller can account for the abandoned runs. Each expired lease is returned once.
+    fn reap_expired_leases(&self, now_unix: i64) -> anyhow::Result<Vec<Lease>>;
+
+    /// Take the project-wide named lock `name` (e.g. "land") for `ttl_secs`. Returns false
+    /// if another holder has it and it has not expired; the same holder re-locking extends it.
+    fn try_lock_resource(&self, name: &str, holder: &str, ttl_secs: i64) -> anyhow::Result<bool>;
+    fn release_resource(&self, name: &str, holder: &str) -> anyhow::Result<()>;
 
     fn create_run(&self, queue_id: &QueueId, run_id: RunId) -> anyhow::Result<()>;
-    fn record_gate_outcome(&self, run_id: &RunId, outcome: &GateOutcome) -> anyhow::Result<()>;
+    /// The run is now executing `gate`; it stays the run's current gate until the next one
+    /// starts, so an abandoned run can be attributed to the gate it died in.
+    fn start_gate(&self, run_id: &RunId, gate: &GateName) -> anyhow::Result<()>;
+    /// Append a finished gate to its run's history.
+    fn record_gate_outcome(&self, record: &GateOutcomeRecord) -> anyhow::Result<()>;
+    /// Settle the run with its final result.
+    fn finish_run(&self, run_id: &RunId, result: RunResult, now_unix: i64) -> anyhow::Result<()>;
+    /// A run's gate history, in the order the gates finished.
+    fn list_gate_outcomes(&self, run_id: &RunId) -> anyhow::Result<Vec<GateOutcomeRecord>>;
 
-    fn add_message(&self, task_id: &TaskId, ty: MessageType, body_md: &str, now_unix: i64) -> anyhow::Result<()>;
+    fn add_message(&self, task_id: &TaskId, ty: MessageType, body_md: &str, now_unix: i64) -> anyhow::Result<()> {
+        self.add_message_with_payload(task_id, ty, body_md, None, now_unix)
+    }
+    /// `add_message` with a machine-readable payload kept next to the markdown (an ASK's packet).
+    fn add_message_with_payload(
+        &self,
+        task_id: &TaskId,
+        ty: MessageType,
+        body_md: &str,
+        payload_json: Option<&str>,
+        now_unix: i64,
+    ) -> anyhow::Result<()>;
 }
@@ -1,4 +1,4 @@
-use crate::types::{Finding, ValidateInput, ValidationCategory};
+use crate::types::{Finding, Severity, ValidateInput, ValidationCategory};
 
 pub trait Rule: Send + Sync {
     fn id(&self) -> &str;
@@ -6,7 +6,13 @@ pub trait Rule: Send + Sync {
     fn eval(&self, input: &ValidateInput) -> Vec<Finding>;
 }
 
-/// Example rule (v0): blocks if exported signature changed.
+/// Rules the validate gate runs.
+pub fn builtin_rules() -> Vec<Box<dyn Rule>> {
+    vec![Box::new(MeaningChangeExportedRule)]
+}
+
+/// Blocks on every exported anchor the head removed or whose signature it changed, unless
+/// the spec declares it, which only warns; added anchors break nothing.
 pub struct MeaningChangeExportedRule;
 
 impl Rule for MeaningChangeExportedRule {
@@ -19,30 +25,52 @@ impl Rule for MeaningChangeExportedRule {
     }
 
     fn eval(&self, input: &ValidateInput) -> Vec<Finding> {
-        if input.exported_signature_changed {
-            return vec![Finding {
-                rule_id: self.id().to_string(),
-                category: self.category(),
-                severity: crate::types::Severity::Fail,
-                message: "exported signature changed; update spec or DECISION".to_string(),
-                evidence_ref: None,
-            }];
-        }
-        vec![]
+        input
+            .exported_changes
+            .breaking()
+            .map(|change| {
+                let what = match (&change.old_hash, &change.new_hash) {
+                    (Some(old), Some(new)) => format!("signature changed ({} -> {})", short(old), short(new)),
+                    _ => "removed".to_string(),
+                };
+                let (severity, next) = if input.declared_anchors.contains(&change.anchor) {
+                    (Severity::Warn, "declared by the spec")
+                } else {
+                    (Severity::Fail, "update spec or DECISION")
+                };
+                Finding {
+                    rule_id: self.id().to_string(),
+                    category: self.category(),
+                    severity,
+                    message: format!("exported {} {}; {}", change.anchor, what, next),
+                    evidence_ref: None,
+                    anchor: Some(change.anchor.clone()),
+                }
+            })
+            .collect()
     }
 }
 
+fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(12)]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{ValidateInput, ValidationCategory};
+    use crate::types::ValidateInput;
+    use spl_index::{AnchorChange, IndexDiff};
+
+    fn change(anchor: &str, old: Option<&str>, new: Option<&str>) -> AnchorChange {
+        AnchorChange { anchor: anchor.into(), old_hash: old.map(String::from), new_hash: new.map(String::from) }
+    }
 
     #[test]
     fn test_meaning_change_exported_rule_no_change() {
         let rule = MeaningChangeExportedRule;
         let input = ValidateInput {
-            exported_signature_changed: false,
-            details: vec![],
+            exported_changes: IndexDiff { added: vec![change("rust://c::new", None, Some("1"))], ..Default::default() },
+            ..Default::default()
         };
         let findings = rule.eval(&input);
         assert!(findings.is_empty());
@@ -52,12 +80,24 @@ mod tests {
     fn test_meaning_change_exported_rule_with_change() {
         let rule = MeaningChangeExportedRule;
         let input = ValidateInput {
-            exported_signature_changed: true,
-            details: vec![],
+            exported_changes: IndexDiff {
+                added: vec![],
+                removed: vec![change("rust://c::gone", Some("0123456789abcdef"), None)],
+                changed: vec![change("rust://c::f", Some("0123456789abcdef"), Some("fedcba9876543210"))],
+            },
+            ..Default::default()
         };
         let findings = rule.eval(&input);
-        assert_eq!(findings.len(), 1);
+        assert_eq!(findings.len(), 2);
         assert_eq!(findings[0].rule_id, "meaning_change_exported");
-        assert_eq!(findings[0].severity, crate::types::Severity::Fail);
+        assert_eq!(findings[0].severity, Severity::Fail);
+        assert_eq!(findings[0].anchor.as_deref(), Some("rust://c::gone"));
+        assert_eq!(findings[0].message, "exported rust://c::gone removed; update spec or DECISION");
+        assert_eq!(findings[1].message, "exported rust://c::f signature changed (0123456789ab -> fedcba987654); update spec or DECISION");
+
+        let declared = ValidateInput { declared_anchors: vec!["rust://c::f".into()], ..input };
+        let findings = rule.eval(&declared);
+        assert_eq!((findings[0].severity.clone(), findings[1].severity.clone()), (Severity::Fail, Severity::Warn));
+        assert_eq!(findings[1].message, "exported rust://c::f signature changed (0123456789ab -> fedcba987654); declared by the spec");
     }
 }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use spl_index::IndexDiff;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ValidationCategory {
@@ -21,11 +22,16 @@ pub struct Finding {
     pub severity: Severity,
     pub message: String,
     pub evidence_ref: Option<String>,
+    /// The anchor the finding is about, if it is about one.
+    #[serde(default)]
+    pub anchor: Option<String>,
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct ValidateInput {
-    // v0 placeholder: index diffs, spec, etc.
-    pub exported_signature_changed: bool,
+    /// Public anchors that differ between the base rev and the workspace head.
+    pub exported_changes: IndexDiff,
+    /// Anchors the task's spec declares (without `#sig_hash`): the task is meant to change them.
+    pub declared_anchors: Vec<String>,
     pub details: Vec<String>,
 }
@@ -3,7 +3,7 @@ use std::process::Command;
 
 use anyhow::{anyhow, Context, Result};
 use spl_core::{TaskId, VcsType};
-use spl_vcs::{Patch, RevId, VcsAdapter, WorkspaceHandle};
+use spl_vcs::{ConflictFile, LandConflict, Patch, RevId, VcsAdapter, WorkspaceHandle};
 
 #[derive(Clone, Debug)]
 pub struct GitAdapter {
@@ -39,6 +39,64 @@ impl GitAdapter {
         Ok(())
     }
 
+    /// `git apply <args> -`, feeding the patch on stdin.
+    fn git_apply(repo_root: &Path, args: &[&str], bytes: &[u8]) -> Result<std::process::Output> {
+        let mut child = Command::new("git")
+            .arg("apply")
+            .args(args)
+            .args(["--whitespace=nowarn", "-"])
+            .current_dir(repo_root)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .with_context(|| "spawn git apply")?;
+        {
+            use std::io::Write;
+            let mut stdin = child.stdin.take().unwrap();
+            stdin.write_all(bytes)?;
+        }
+        Ok(child.wait_with_output()?)
+    }
+
+    /// Fallback when the patch no longer applies as-is: 3-way merge it using the blobs the
+    /// patch records (the workspace shares the repo's object store). Leaves the merge staged
+    /// on success. On conflict, records the conflicting hunks, resets the repo root and
+    /// returns a [`LandConflict`].
+    fn apply_three_way(&self, repo_root: &Path, patch: &Patch, plain: &std::process::Output) -> Result<()> {
+        let apply_failed = || {
+            anyhow!(
+                "git apply failed\nstdout:{}\nstderr:{}",
+                String::from_utf8_lossy(&plain.stdout),
+                String::from_utf8_lossy(&plain.stderr)
+            )
+        };
+        // the conflict cleanup below hard-resets tracked files, so never merge over local edits
+        if !Self::run(repo_root, &["git", "status", "--porcelain", "--untracked-files=no"])?.is_empty() {
+            return Err(apply_failed());
+        }
+
+        let out = Self::git_apply(repo_root, &["--3way"], &patch.bytes)?;
+        if out.status.success() {
+            return Ok(());
+        }
+
+        let unmerged = Self::run(repo_root, &["git", "diff", "--name-only", "--diff-filter=U"])?;
+        let files: Vec<ConflictFile> = unmerged
+            .lines()
+            .map(|path| ConflictFile {
+                path: path.to_string(),
+                hunks: conflict_hunks(&std::fs::read_to_string(repo_root.join(path)).unwrap_or_default()),
+            })
+            .collect();
+        Self::run(repo_root, &["git", "reset", "--hard", "-q", "HEAD"])?;
+        if files.is_empty() {
+            // 3-way was not possible at all (e.g. blobs missing); report the original failure
+            return Err(apply_failed());
+        }
+        Err(LandConflict { files }.into())
+    }
+
     fn has_staged_changes(repo_root: &Path) -> Result<bool> {
         let out = Command::new("git")
             .args(["diff", "--cached", "--name-only"])
@@ -48,6 +106,25 @@ impl GitAdapter {
     }
 }
 
+/// Conflict-marked regions of a merged file, markers included.
+fn conflict_hunks(text: &str) -> Vec<String> {
+    let mut hunks = vec![];
+    let mut current: Option<Vec<&str>> = None;
+    for line in text.lines() {
+        if line.starts_with("<<<<<<<") {
+            current = Some(vec![]);
+        }
+        if let Some(hunk) = current.as_mut() {
+            hunk.push(line);
+            if line.starts_with(">>>>>>>") {
+                hunks.push(hunk.join("\n"));
+                current = None;
+            }
+        }
+    }
+    hunks
+}
+
 impl VcsAdapter for GitAdapter {
     fn vcs_type(&self) -> VcsType {
         VcsType::Git
@@ -96,6 +173,11 @@ impl VcsAdapter for GitAdapter {
         Ok(Patch { bytes: bytes.stdout, format: "git".into() })
     }
 
+    fn changed_files(&self, repo_root: &Path, from: &RevId, to: &RevId) -> Result<Vec<String>> {
+        let out = Self::run(repo_root, &["git", "diff", "--name-only", "--no-renames", "-z", from, to])?;
+        Ok(out.split('\0').filter(|p| !p.is_empty()).map(String::from).collect())
+    }
+
     fn apply_patch_to_repo_root(&self, repo_root: &Path, patch: &Patch, message: &str) -> Result<RevId> {
         self.ensure_on_main(repo_root)?;
 
@@ -104,27 +186,10 @@ impl VcsAdapter for GitAdapter {
             return Ok(Self::run(repo_root, &["git", "rev-parse", "HEAD"])?);
         }
 
-        // git apply --index reads from stdin
-        let mut child = Command::new("git")
-            .args(["apply", "--index", "--whitespace=nowarn", "-"])
-            .current_dir(repo_root)
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .with_context(|| "spawn git apply")?;
-        {
-            use std::io::Write;
-            let mut stdin = child.stdin.take().unwrap();
-            stdin.write_all(&patch.bytes)?;
-        }
-        let out = child.wait_with_output()?;
+        let out = Self::git_apply(repo_root, &["--index"], &patch.bytes)?;
         if !out.status.success() {
-            return Err(anyhow!(
-                "git apply failed\nstdout:{}\nstderr:{}",
-                String::from_utf8_lossy(&out.stdout),
-                String::from_utf8_lossy(&out.stderr)
-            ));
+            // mainline moved under the patch's context: merge it onto the new mainline instead
+            self.apply_three_way(repo_root, patch, &out)?;
         }
 
         if !Self::has_staged_changes(repo_root)? {
@@ -163,4 +228,63 @@ mod tests {
 
         run_vcs_contract_suite(&adapter, dir.path(), &ws_root).unwrap();
     }
+
+    /// Repo whose tracked `notes.txt` has lines a..e; returns the adapter on its branch.
+    fn notes_repo(dir: &Path) -> GitAdapter {
+        init_git_repo(dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "a\nb\nc\nd\ne\n").unwrap();
+        GitAdapter::run(dir, &["git", "add", "."]).unwrap();
+        GitAdapter::run(dir, &["git", "commit", "-m", "notes"]).unwrap();
+        GitAdapter::new(GitAdapter::run(dir, &["git", "rev-parse", "--abbrev-ref", "HEAD"]).unwrap())
+    }
+
+    /// Patch made in a workspace that rewrites `notes.txt` to `content`.
+    fn workspace_patch(adapter: &GitAdapter, repo: &Path, content: &str) -> Patch {
+        let ws = adapter.create_workspace(repo, &repo.join(".spl-ws"), &TaskId::from_str("t")).unwrap();
+        std::fs::write(ws.path.join("notes.txt"), content).unwrap();
+        let base = adapter.get_base_rev(&ws.path).unwrap();
+        let head = adapter.snapshot(&ws, "ws").unwrap();
+        let patch = adapter.export_patch(&ws, &base, &head).unwrap();
+        adapter.cleanup_workspace(repo, ws).unwrap();
+        patch
+    }
+
+    fn commit_on_main(repo: &Path, content: &str) -> RevId {
+        std::fs::write(repo.join("notes.txt"), content).unwrap();
+        GitAdapter::run(repo, &["git", "commit", "-am", "mainline moved"]).unwrap();
+        GitAdapter::run(repo, &["git", "rev-parse", "HEAD"]).unwrap()
+    }
+
+    #[test]
+    fn stale_patch_is_three_way_merged_onto_new_mainline() {
+        let dir = tempdir().unwrap();
+        let adapter = notes_repo(dir.path());
+        let patch = workspace_patch(&adapter, dir.path(), "a\nB\nc\nd\ne\n");
+        // touches the patch's context lines, so a plain apply is rejected
+        let moved = commit_on_main(dir.path(), "a\nb\nc\nD\ne\n");
+
+        let landed = adapter.apply_patch_to_repo_root(dir.path(), &patch, "land").unwrap();
+
+        assert_ne!(landed, moved);
+        assert_eq!(std::fs::read_to_string(dir.path().join("notes.txt")).unwrap(), "a\nB\nc\nD\ne\n");
+        assert!(adapter.repo_root_is_clean(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn unresolvable_conflict_is_reported_and_repo_root_reset() {
+        let dir = tempdir().unwrap();
+        let adapter = notes_repo(dir.path());
+        let patch = workspace_patch(&adapter, dir.path(), "a\nfrom-task\nc\nd\ne\n");
+        let moved = commit_on_main(dir.path(), "a\nfrom-main\nc\nd\ne\n");
+
+        let err = adapter.apply_patch_to_repo_root(dir.path(), &patch, "land").unwrap_err();
+
+        let conflict = err.downcast_ref::<LandConflict>().expect("typed conflict");
+        assert_eq!(conflict.files.len(), 1);
+        assert_eq!(conflict.files[0].path, "notes.txt");
+        let hunk = &conflict.files[0].hunks[0];
+        assert!(hunk.starts_with("<<<<<<<") && hunk.contains("from-task") && hunk.contains("from-main"));
+        assert_eq!(adapter.get_base_rev(dir.path()).unwrap(), moved);
+        assert!(adapter.repo_root_is_clean(dir.path()).unwrap());
+    }
 }
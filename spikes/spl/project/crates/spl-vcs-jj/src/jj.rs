@@ -165,6 +165,13 @@ impl VcsAdapter for JjAdapter {
         Ok(Patch { bytes: out.stdout, format: "git".into() })
     }
 
+    fn changed_files(&self, repo_root: &Path, from: &RevId, to: &RevId) -> Result<Vec<String>> {
+        Self::ensure_jj_available()?;
+        // `jj diff --name-only` lists both paths of a rename, like `git diff --no-renames`
+        let out = Self::run(repo_root, &["jj", "diff", "--name-only", "--from", from, "--to", to])?;
+        Ok(out.lines().filter(|p| !p.is_empty()).map(String::from).collect())
+    }
+
     fn apply_patch_to_repo_root(&self, repo_root: &Path, patch: &Patch, message: &str) -> Result<RevId> {
         Self::ensure_jj_available()?;
         self.ensure_colocated(repo_root)?;
@@ -36,6 +36,10 @@ pub fn run_vcs_contract_suite(adapter: &dyn VcsAdapter, repo_root: &Path, ws_roo
     if before == after {
         return Err(anyhow!("expected repo to advance after landing"));
     }
+    let changed = adapter.changed_files(repo_root, &before, &after)?;
+    if changed != vec!["contract.txt".to_string()] {
+        return Err(anyhow!("expected contract.txt as the only changed file, got {:?}", changed));
+    }
 
     adapter.cleanup_workspace(repo_root, ws)?;
     Ok(())
@@ -18,6 +18,31 @@ pub struct Patch {
     pub format: String, // "git"
 }
 
+/// A patch that still conflicts with mainline after a 3-way merge. Adapters return it
+/// inside the `anyhow::Error` of `apply_patch_to_repo_root` (recover it with
+/// `downcast_ref`) so the land lane can escalate to a human instead of crashing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LandConflict {
+    pub files: Vec<ConflictFile>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConflictFile {
+    /// Path relative to the repo root.
+    pub path: String,
+    /// Each conflict-marked region (`<<<<<<<` through `>>>>>>>`), verbatim.
+    pub hunks: Vec<String>,
+}
+
+impl std::fmt::Display for LandConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let paths: Vec<&str> = self.files.iter().map(|c| c.path.as_str()).collect();
+        write!(f, "patch conflicts with mainline in {}", paths.join(", "))
+    }
+}
+
+impl std::error::Error for LandConflict {}
+
 pub trait VcsAdapter: Send + Sync {
     fn vcs_type(&self) -> VcsType;
 
@@ -31,6 +56,13 @@ pub trait VcsAdapter: Send + Sync {
 
     fn export_patch(&self, ws: &WorkspaceHandle, base: &RevId, head: &RevId) -> Result<Patch>;
 
+    /// Paths (relative to the repo root, `/`-separated) that differ between `from` and `to`:
+    /// added, modified and deleted, with a rename listed as both of its paths.
+    fn changed_files(&self, repo_root: &Path, from: &RevId, to: &RevId) -> Result<Vec<String>>;
+
+    /// Land `patch` on mainline and return the new mainline rev. A patch that no longer
+    /// applies cleanly is 3-way merged onto the current mainline; if that still conflicts,
+    /// the repo root is left untouched and the error carries a [`LandConflict`].
     fn apply_patch_to_repo_root(&self, repo_root: &Path, patch: &Patch, message: &str) -> Result<RevId>;
 
     fn cleanup_workspace(&self, repo_root: &Path, ws: WorkspaceHandle) -> Result<()>;